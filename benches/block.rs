@@ -2,7 +2,8 @@
 
 extern crate test;
 use automesh::{
-    FiniteElementMethods, HexahedralFiniteElements, NSD, Octree, Smoothing, Tree, Voxels,
+    Defeaturing, DefeaturingMethod, FiniteElementMethods, HexahedralFiniteElements, NSD, Octree,
+    Smoothing, Tree, Voxels,
 };
 use std::{
     fs::{read_dir, remove_file},
@@ -14,6 +15,8 @@ const REMOVE: Option<Vec<u8>> = None;
 const SCALE: [f64; NSD] = [1.0, 1.0, 1.0];
 const TRANSLATE: [f64; NSD] = [0.0, 0.0, 0.0];
 
+const DEFEATURE_MINIMUM: usize = 8;
+
 const SMOOTHING_ITERATIONS: usize = 1;
 const SMOOTHING_PASS_BAND: f64 = 0.1;
 const SMOOTHING_SCALE_DEFLATE: f64 = 0.6307;
@@ -85,6 +88,26 @@ macro_rules! bench_block {
             Ok(())
         }
         #[bench]
+        fn defeature_octree(bencher: &mut Bencher) {
+            let npy = format!("benches/block/block_{}.npy", $nel);
+            let defeaturing = Defeaturing {
+                method: DefeaturingMethod::Octree,
+                minimum: DEFEATURE_MINIMUM,
+                ..Default::default()
+            };
+            bencher.iter(|| Voxels::from_npy(&npy).unwrap().defeature_with(&defeaturing));
+        }
+        #[bench]
+        fn defeature_union_find(bencher: &mut Bencher) {
+            let npy = format!("benches/block/block_{}.npy", $nel);
+            let defeaturing = Defeaturing {
+                method: DefeaturingMethod::UnionFind,
+                minimum: DEFEATURE_MINIMUM,
+                ..Default::default()
+            };
+            bencher.iter(|| Voxels::from_npy(&npy).unwrap().defeature_with(&defeaturing));
+        }
+        #[bench]
         fn from_inp(bencher: &mut Bencher) -> Result<(), String> {
             let voxels =
                 Voxels::from_spn(&format!("benches/block/block_{}.spn", $nel), NEL.into())?;
@@ -7,13 +7,20 @@ pub mod test;
 pub mod tri;
 pub use tri::{TRI, TriangularFiniteElements};
 use tri::{
-    calculate_maximum_edge_ratios_tri, calculate_maximum_skews_tri,
-    calculate_minimum_scaled_jacobians_tri, write_finite_elements_metrics_tri,
+    calculate_aspect_ratios_tri, calculate_conditions_tri, calculate_element_areas_tri,
+    calculate_enclosed_volumes_tri, calculate_maximum_angles_tri,
+    calculate_maximum_edge_ratios_tri, calculate_maximum_normal_deviations_tri,
+    calculate_maximum_skews_tri, calculate_minimum_angles_tri,
+    calculate_minimum_scaled_jacobians_tri, calculate_radius_ratios_tri, calculate_shapes_tri,
+    write_finite_elements_metrics_tri,
 };
 
 #[cfg(feature = "profile")]
 use std::time::Instant;
 
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
 use super::{Coordinate, Coordinates, NSD, Tessellation, Vector};
 use chrono::Utc;
 use conspire::math::{Tensor, TensorArray, TensorVec};
@@ -21,6 +28,8 @@ use ndarray::{Array1, Array2, s};
 use ndarray_npy::WriteNpyExt;
 use netcdf::{Error as ErrorNetCDF, create};
 use std::{
+    array::from_fn,
+    collections::HashMap,
     fs::File,
     io::{BufRead, BufReader, BufWriter, Error as ErrorIO, Write},
     path::{Path, PathBuf},
@@ -33,7 +42,7 @@ use vtkio::{
     },
 };
 
-const ELEMENT_NUMBERING_OFFSET: usize = 1;
+pub(crate) const ELEMENT_NUMBERING_OFFSET: usize = 1;
 pub const NODE_NUMBERING_OFFSET: usize = 1;
 
 /// A vector of finite element block IDs.
@@ -47,12 +56,138 @@ pub type Metrics = Array1<f64>;
 pub type Nodes = Vec<usize>;
 pub type ReorderedConnectivity = Vec<Vec<i32>>;
 
+/// Possible quality metrics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    AspectRatio,
+    Condition,
+    DiagonalRatio,
+    Distortion,
+    EdgeRatio,
+    ElementArea,
+    ElementVolume,
+    MaximumAngle,
+    MinimumAngle,
+    NormalDeviation,
+    Oddy,
+    RadiusRatio,
+    RelativeSizeSquared,
+    ScaledJacobian,
+    Shape,
+    Shear,
+    Skew,
+    Stretch,
+    Taper,
+    Warpage,
+}
+
+impl Metric {
+    /// Returns the header used for the metric in output files.
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::AspectRatio => "aspect ratio",
+            Self::Condition => "maximum condition",
+            Self::DiagonalRatio => "diagonal ratio",
+            Self::Distortion => "distortion",
+            Self::EdgeRatio => "maximum edge ratio",
+            Self::ElementArea => "element area",
+            Self::ElementVolume => "element volume",
+            Self::MaximumAngle => "maximum angle",
+            Self::MinimumAngle => "minimum angle",
+            Self::NormalDeviation => "maximum normal deviation",
+            Self::Oddy => "maximum oddy",
+            Self::RadiusRatio => "radius ratio",
+            Self::RelativeSizeSquared => "relative size squared",
+            Self::ScaledJacobian => "minimum scaled jacobian",
+            Self::Shape => "minimum shape",
+            Self::Shear => "minimum shear",
+            Self::Skew => "maximum skew",
+            Self::Stretch => "stretch",
+            Self::Taper => "maximum taper",
+            Self::Warpage => "maximum warpage",
+        }
+    }
+    /// Returns whether larger values of the metric indicate better quality.
+    pub fn higher_is_better(&self) -> bool {
+        matches!(
+            self,
+            Self::DiagonalRatio
+                | Self::Distortion
+                | Self::ElementArea
+                | Self::ElementVolume
+                | Self::MinimumAngle
+                | Self::RelativeSizeSquared
+                | Self::ScaledJacobian
+                | Self::Shape
+                | Self::Shear
+                | Self::Stretch
+        )
+    }
+}
+
+impl TryFrom<&str> for Metric {
+    type Error = String;
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name.to_lowercase().replace(['_', '-'], " ").as_str() {
+            "aspect ratio" => Ok(Self::AspectRatio),
+            "condition" | "maximum condition" | "condition number" => Ok(Self::Condition),
+            "diagonal" | "diagonal ratio" => Ok(Self::DiagonalRatio),
+            "distortion" => Ok(Self::Distortion),
+            "edge ratio" | "maximum edge ratio" => Ok(Self::EdgeRatio),
+            "area" | "element area" => Ok(Self::ElementArea),
+            "volume" | "element volume" => Ok(Self::ElementVolume),
+            "maximum angle" => Ok(Self::MaximumAngle),
+            "minimum angle" => Ok(Self::MinimumAngle),
+            "normal deviation" | "maximum normal deviation" => Ok(Self::NormalDeviation),
+            "oddy" | "maximum oddy" => Ok(Self::Oddy),
+            "radius ratio" => Ok(Self::RadiusRatio),
+            "relative size" | "relative size squared" => Ok(Self::RelativeSizeSquared),
+            "scaled jacobian" | "minimum scaled jacobian" => Ok(Self::ScaledJacobian),
+            "shape" | "minimum shape" => Ok(Self::Shape),
+            "shear" | "minimum shear" => Ok(Self::Shear),
+            "skew" | "maximum skew" => Ok(Self::Skew),
+            "stretch" => Ok(Self::Stretch),
+            "taper" | "maximum taper" => Ok(Self::Taper),
+            "warpage" | "maximum warpage" => Ok(Self::Warpage),
+            _ => Err(format!("Invalid metric {} specified", name)),
+        }
+    }
+}
+
+/// Possible smoothing convergence criteria.
+pub enum Convergence {
+    MaximumDisplacement(f64),
+    Quality(Metric, f64),
+    RmsDisplacement(f64),
+}
+
 /// Possible smoothing methods.
+#[derive(Clone, Copy, Debug)]
 pub enum Smoothing {
+    HcLaplacian(usize, f64, f64, f64),
     Laplacian(usize, f64),
     Taubin(usize, f64, f64),
 }
 
+/// Options for smoothing, beyond the smoothing method itself.
+#[derive(Default)]
+pub struct SmoothingOptions<'a> {
+    /// Smoothing method of individual blocks, where `None` freezes the block.
+    pub block_smoothing: Vec<(u8, Option<Smoothing>)>,
+    /// Criterion for terminating smoothing before the maximum number of iterations.
+    pub convergence: Option<Convergence>,
+    /// Dihedral angle in degrees above which exterior surface edges are preserved as features.
+    pub feature_angle: Option<f64>,
+    /// Whether the interface nodes are also projected onto the reference surface.
+    pub project_interface: bool,
+    /// Reference surface that the exterior nodes are projected onto after each iteration.
+    pub reference_surface: Option<&'a Tessellation>,
+    /// Whether smoothing backtracks nodal moves that would invert elements.
+    pub safeguard: bool,
+    /// Whether smoothing preserves the volume of each element block.
+    pub volume_preserving: bool,
+}
+
 /// The finite elements type.
 pub struct FiniteElements<const N: usize> {
     boundary_nodes: Nodes,
@@ -61,6 +196,7 @@ pub struct FiniteElements<const N: usize> {
     exterior_nodes: Nodes,
     interface_nodes: Nodes,
     interior_nodes: Nodes,
+    iterations: usize,
     nodal_coordinates: Coordinates,
     nodal_influencers: VecConnectivity,
     node_element_connectivity: VecConnectivity,
@@ -69,6 +205,7 @@ pub struct FiniteElements<const N: usize> {
     prescribed_nodes_homogeneous: Nodes,
     prescribed_nodes_inhomogeneous: Nodes,
     prescribed_nodes_inhomogeneous_coordinates: Coordinates,
+    rejected_moves: usize,
 }
 
 pub const HEX: usize = 8;
@@ -76,6 +213,45 @@ pub const HEX: usize = 8;
 /// The hexahedral finite elements type.
 pub type HexahedralFiniteElements = FiniteElements<HEX>;
 
+/// Possible finite element meshes read from a file.
+pub enum FiniteElementsKind {
+    Hexahedral(HexahedralFiniteElements),
+    Triangular(TriangularFiniteElements),
+    /// Hexahedral elements read from a missing or unrecognized element type, with a warning.
+    AssumedHexahedral(HexahedralFiniteElements, String),
+}
+
+impl FiniteElementsKind {
+    /// Constructs and returns a new finite elements type from an Abaqus input file,
+    /// with the element type detected from the `*ELEMENT, TYPE=` keyword.
+    ///
+    /// Unrecognized or missing element types are read as hexahedral elements,
+    /// returned with a warning for the caller to report.
+    pub fn from_inp(file_path: &str) -> Result<Self, ErrorIO> {
+        match element_type_from_inp(file_path)?.as_deref() {
+            Some(element_type) if element_type.starts_with("C3D8") => Ok(Self::Hexahedral(
+                HexahedralFiniteElements::from_inp(file_path)?,
+            )),
+            Some(
+                "CPE3" | "CPS3" | "M3D3" | "R3D3" | "S3" | "S3R" | "SFM3D3" | "STRI3" | "TRI3",
+            ) => Ok(Self::Triangular(TriangularFiniteElements::from_inp(
+                file_path,
+            )?)),
+            element_type => Ok(Self::AssumedHexahedral(
+                HexahedralFiniteElements::from_inp(file_path)?,
+                format!(
+                    "{} element type in input file {}, reading as hexahedral elements",
+                    element_type.map_or("Missing".to_string(), |element_type| format!(
+                        "Unrecognized {}",
+                        element_type
+                    )),
+                    file_path
+                ),
+            )),
+        }
+    }
+}
+
 /// Methods common to all finite element types.
 pub trait FiniteElementMethods<const N: usize>
 where
@@ -89,6 +265,18 @@ where
     ) -> Self;
     /// Constructs and returns a new finite elements type from an Abaqus input file.
     fn from_inp(file_path: &str) -> Result<Self, ErrorIO>;
+    /// Returns the nodes of the named node set (*NSET) in an Abaqus input file,
+    /// numbered in the order that the nodes are read by `from_inp`.
+    fn node_set_from_inp(file_path: &str, name: &str) -> Result<Nodes, ErrorIO>;
+    /// Calculates and returns the volume of each element block,
+    /// using the enclosed volume for surface meshes, which requires each block to be closed.
+    fn calculate_block_volumes(&self) -> Result<Vec<(u8, f64)>, String>;
+    /// Calculates and returns the given quality metric for each element.
+    fn calculate_metric(&self, metric: &Metric) -> Result<Metrics, String>;
+    /// Calculates the nodes on the exterior faces of hexes or the boundary edges of triangles.
+    fn calculate_exterior_nodes(&self) -> Nodes;
+    /// Calculates the nodes within the axis-aligned box between the minimum and maximum corners.
+    fn calculate_nodes_in_box(&self, minimum: &[f64; NSD], maximum: &[f64; NSD]) -> Nodes;
     /// Calculates and returns the discrete Laplacian for the given node-to-node connectivity.
     fn laplacian(&self, node_node_connectivity: &VecConnectivity) -> Coordinates;
     /// Calculates and sets the nodal influencers.
@@ -99,8 +287,20 @@ where
     fn node_element_connectivity(&mut self) -> Result<(), &str>;
     /// Calculates and sets the node-to-node connectivity.
     fn node_node_connectivity(&mut self) -> Result<(), &str>;
+    /// Optimizes the nodal coordinates by maximizing the minimum scaled Jacobian
+    /// of each nodal patch below the target, untangling any inverted elements.
+    /// Exterior nodes move along the reference surface if one is given,
+    /// and otherwise only within the faces, edges, or corners of the exterior.
+    fn optimize(
+        &mut self,
+        iterations: usize,
+        target: f64,
+        reference_surface: Option<&Tessellation>,
+    ) -> Result<(), String>;
     /// Smooths the nodal coordinates according to the provided smoothing method.
     fn smooth(&mut self, method: Smoothing) -> Result<(), &str>;
+    /// Smooths the nodal coordinates according to the provided smoothing method and options.
+    fn smooth_with(&mut self, method: Smoothing, options: &SmoothingOptions) -> Result<(), &str>;
     /// Writes the finite elements data to a new Exodus file.
     fn write_exo(&self, file_path: &str) -> Result<(), ErrorNetCDF>;
     /// Writes the finite elements data to a new Abaqus file.
@@ -109,6 +309,8 @@ where
     fn write_mesh(&self, file_path: &str) -> Result<(), ErrorIO>;
     /// Writes the finite elements quality metrics to a new file.
     fn write_metrics(&self, file_path: &str) -> Result<(), ErrorIO>;
+    /// Writes the selected finite elements quality metrics to a new file.
+    fn write_metrics_selected(&self, file_path: &str, metrics: &[Metric]) -> Result<(), ErrorIO>;
     /// Writes the finite elements data to a new VTK file.
     fn write_vtk(&self, file_path: &str) -> Result<(), ErrorVtk>;
     /// Returns a reference to the boundary nodes.
@@ -123,6 +325,8 @@ where
     fn get_interface_nodes(&self) -> &Nodes;
     /// Returns a reference to the interior nodes.
     fn get_interior_nodes(&self) -> &Nodes;
+    /// Returns the number of iterations performed during the last smoothing.
+    fn get_iterations(&self) -> usize;
     /// Returns a reference to the nodal coordinates.
     fn get_nodal_coordinates(&self) -> &Coordinates;
     /// Returns a mutable reference to the nodal coordinates.
@@ -141,6 +345,8 @@ where
     fn get_prescribed_nodes_inhomogeneous(&self) -> &Nodes;
     /// Returns a reference to the coordinates of the inhomogeneously-prescribed nodes.
     fn get_prescribed_nodes_inhomogeneous_coordinates(&self) -> &Coordinates;
+    /// Returns the number of nodal moves rejected by the safeguard during the last smoothing.
+    fn get_rejected_moves(&self) -> usize;
     /// Sets the prescribed nodes if opted to do so.
     fn set_prescribed_nodes(
         &mut self,
@@ -165,6 +371,7 @@ where
             exterior_nodes: vec![],
             interface_nodes: vec![],
             interior_nodes: vec![],
+            iterations: 0,
             nodal_coordinates,
             nodal_influencers: vec![],
             node_element_connectivity: vec![],
@@ -173,6 +380,7 @@ where
             prescribed_nodes_homogeneous: vec![],
             prescribed_nodes_inhomogeneous: vec![],
             prescribed_nodes_inhomogeneous_coordinates: Coordinates::zero(0),
+            rejected_moves: 0,
         }
     }
     fn from_inp(file_path: &str) -> Result<Self, ErrorIO> {
@@ -184,41 +392,69 @@ where
             nodal_coordinates,
         ))
     }
-    fn laplacian(&self, node_node_connectivity: &VecConnectivity) -> Coordinates {
-        let nodal_coordinates = self.get_nodal_coordinates();
-        node_node_connectivity
+    fn node_set_from_inp(file_path: &str, name: &str) -> Result<Nodes, ErrorIO> {
+        node_set_from_inp(file_path, name)
+    }
+    fn calculate_block_volumes(&self) -> Result<Vec<(u8, f64)>, String> {
+        calculate_block_volumes(
+            self.get_element_blocks(),
+            self.get_element_node_connectivity(),
+            self.get_nodal_coordinates(),
+        )
+    }
+    fn calculate_metric(&self, metric: &Metric) -> Result<Metrics, String> {
+        calculate_metric(
+            metric,
+            self.get_element_node_connectivity(),
+            self.get_nodal_coordinates(),
+        )
+    }
+    fn calculate_exterior_nodes(&self) -> Nodes {
+        let mut exterior_nodes: Nodes =
+            calculate_boundary_entities(self.get_element_node_connectivity())
+                .into_iter()
+                .flatten()
+                .collect();
+        exterior_nodes.sort();
+        exterior_nodes.dedup();
+        exterior_nodes
+    }
+    fn calculate_nodes_in_box(&self, minimum: &[f64; NSD], maximum: &[f64; NSD]) -> Nodes {
+        self.get_nodal_coordinates()
             .iter()
             .enumerate()
-            .map(|(node_index_i, connectivity)| {
-                if connectivity.is_empty() {
-                    Coordinate::zero()
-                } else {
-                    connectivity
-                        .iter()
-                        .map(|node_j| nodal_coordinates[node_j - NODE_NUMBERING_OFFSET].clone())
-                        .sum::<Coordinate>()
-                        / (connectivity.len() as f64)
-                        - &nodal_coordinates[node_index_i]
-                }
+            .filter(|(_, coordinates)| {
+                (0..NSD).all(|i| minimum[i] <= coordinates[i] && coordinates[i] <= maximum[i])
             })
+            .map(|(node, _)| node + NODE_NUMBERING_OFFSET)
             .collect()
     }
+    fn laplacian(&self, node_node_connectivity: &VecConnectivity) -> Coordinates {
+        let nodal_coordinates = self.get_nodal_coordinates();
+        map_nodes(node_node_connectivity.len(), |node_index_i| {
+            let connectivity = &node_node_connectivity[node_index_i];
+            if connectivity.is_empty() {
+                Coordinate::zero()
+            } else {
+                connectivity
+                    .iter()
+                    .map(|node_j| nodal_coordinates[node_j - NODE_NUMBERING_OFFSET].clone())
+                    .sum::<Coordinate>()
+                    / (connectivity.len() as f64)
+                    - &nodal_coordinates[node_index_i]
+            }
+        })
+    }
     fn nodal_influencers(&mut self) {
         #[cfg(feature = "profile")]
         let time = Instant::now();
         let mut nodal_influencers: VecConnectivity = self.get_node_node_connectivity().clone();
         let prescribed_nodes = self.get_prescribed_nodes();
-        if !self.get_exterior_nodes().is_empty() {
-            let mut boundary_nodes = self.get_boundary_nodes().clone();
-            boundary_nodes
-                .retain(|boundary_node| prescribed_nodes.binary_search(boundary_node).is_err());
-            boundary_nodes.iter().for_each(|boundary_node| {
-                nodal_influencers[boundary_node - NODE_NUMBERING_OFFSET].retain(|node| {
-                    boundary_nodes.binary_search(node).is_ok()
-                        || prescribed_nodes.binary_search(node).is_ok()
-                })
-            });
-        }
+        let boundary_nodes = self.get_boundary_nodes();
+        boundary_nodes.iter().for_each(|boundary_node| {
+            nodal_influencers[boundary_node - NODE_NUMBERING_OFFSET]
+                .retain(|node| boundary_nodes.binary_search(node).is_ok())
+        });
         prescribed_nodes.iter().for_each(|prescribed_node| {
             nodal_influencers[prescribed_node - NODE_NUMBERING_OFFSET].clear()
         });
@@ -230,44 +466,36 @@ where
         );
     }
     fn nodal_hierarchy(&mut self) -> Result<(), &str> {
-        if N != HEX {
-            return Err("Only implemented nodal_hierarchy method for hexes.");
-        }
         let node_element_connectivity = self.get_node_element_connectivity();
         if !node_element_connectivity.is_empty() {
             #[cfg(feature = "profile")]
             let time = Instant::now();
             let element_blocks = self.get_element_blocks();
+            let mut on_exterior = vec![false; node_element_connectivity.len()];
+            self.calculate_exterior_nodes()
+                .into_iter()
+                .for_each(|node| on_exterior[node - NODE_NUMBERING_OFFSET] = true);
             let mut connected_blocks: Blocks = vec![];
             let mut exterior_nodes = vec![];
             let mut interface_nodes = vec![];
             let mut interior_nodes = vec![];
-            let mut number_of_connected_blocks = 0;
-            let mut number_of_connected_elements = 0;
             node_element_connectivity
                 .iter()
+                .zip(on_exterior)
                 .enumerate()
-                .for_each(|(node, connected_elements)| {
+                .for_each(|(node, (connected_elements, exterior))| {
                     connected_blocks = connected_elements
                         .iter()
                         .map(|element| element_blocks[element - ELEMENT_NUMBERING_OFFSET])
                         .collect();
                     connected_blocks.sort();
                     connected_blocks.dedup();
-                    number_of_connected_blocks = connected_blocks.len();
-                    number_of_connected_elements = connected_elements.len();
-                    if number_of_connected_blocks > 1 {
+                    if connected_blocks.len() > 1 {
                         interface_nodes.push(node + NODE_NUMBERING_OFFSET);
-                        //
-                        // THIS IS WHERE IT IS ASSUMED THAT THE MESH IS PERFECTLY STRUCTURED
-                        // ONLY AFFECTS HIERARCHICAL SMOOTHING
-                        //
-                        if number_of_connected_elements < HEX {
-                            exterior_nodes.push(node + NODE_NUMBERING_OFFSET);
-                        }
-                    } else if number_of_connected_elements < HEX {
+                    }
+                    if exterior {
                         exterior_nodes.push(node + NODE_NUMBERING_OFFSET);
-                    } else {
+                    } else if connected_blocks.len() == 1 {
                         interior_nodes.push(node + NODE_NUMBERING_OFFSET);
                     }
                 });
@@ -299,6 +527,39 @@ where
         let time = Instant::now();
         let number_of_nodes = self.get_nodal_coordinates().len();
         let mut node_element_connectivity = vec![vec![]; number_of_nodes];
+        #[cfg(feature = "parallel")]
+        {
+            //
+            // Sorting the node-element pairs keeps the elements of each node in ascending order,
+            // which matches the serial construction below.
+            //
+            let mut pairs: Vec<(usize, usize)> = self
+                .get_element_node_connectivity()
+                .par_iter()
+                .enumerate()
+                .flat_map_iter(|(element, connectivity)| {
+                    connectivity
+                        .iter()
+                        .map(move |node| (node - NODE_NUMBERING_OFFSET, element))
+                })
+                .collect();
+            pairs.par_sort_unstable();
+            pairs
+                .par_chunk_by(|(node_a, _), (node_b, _)| node_a == node_b)
+                .map(|chunk| {
+                    (
+                        chunk[0].0,
+                        chunk
+                            .iter()
+                            .map(|(_, element)| element + ELEMENT_NUMBERING_OFFSET)
+                            .collect(),
+                    )
+                })
+                .collect::<Vec<(usize, Vec<usize>)>>()
+                .into_iter()
+                .for_each(|(node, elements)| node_element_connectivity[node] = elements);
+        }
+        #[cfg(not(feature = "parallel"))]
         self.get_element_node_connectivity()
             .iter()
             .enumerate()
@@ -321,18 +582,18 @@ where
         if !node_element_connectivity.is_empty() {
             #[cfg(feature = "profile")]
             let time = Instant::now();
-            let mut element_connectivity = [0; N];
             let element_node_connectivity = self.get_element_node_connectivity();
-            let number_of_nodes = self.get_nodal_coordinates().len();
-            let mut node_node_connectivity: VecConnectivity = vec![vec![]; number_of_nodes];
-            node_node_connectivity
-                .iter_mut()
-                .zip(node_element_connectivity.iter().enumerate())
-                .try_for_each(|(connectivity, (node, node_connectivity))| {
+            #[cfg(feature = "parallel")]
+            let node_element_connectivity_iter = node_element_connectivity.par_iter();
+            #[cfg(not(feature = "parallel"))]
+            let node_element_connectivity_iter = node_element_connectivity.iter();
+            let node_node_connectivity = node_element_connectivity_iter
+                .enumerate()
+                .map(|(node, node_connectivity)| {
+                    let mut connectivity = vec![];
                     node_connectivity.iter().try_for_each(|element| {
-                        element_connectivity.clone_from(
-                            &element_node_connectivity[element - ELEMENT_NUMBERING_OFFSET],
-                        );
+                        let element_connectivity =
+                            &element_node_connectivity[element - ELEMENT_NUMBERING_OFFSET];
                         if let Some(neighbors) = element_connectivity
                             .iter()
                             .position(|&n| n == node + NODE_NUMBERING_OFFSET)
@@ -346,12 +607,12 @@ where
                         } else {
                             Err("The element-to-node connectivity has been incorrectly calculated")
                         }
-                    })
-                })?;
-            node_node_connectivity.iter_mut().for_each(|connectivity| {
-                connectivity.sort();
-                connectivity.dedup();
-            });
+                    })?;
+                    connectivity.sort();
+                    connectivity.dedup();
+                    Ok(connectivity)
+                })
+                .collect::<Result<VecConnectivity, &str>>()?;
             self.node_node_connectivity = node_node_connectivity;
             #[cfg(feature = "profile")]
             println!(
@@ -363,114 +624,166 @@ where
             Err("Need to calculate the node-to-element connectivity first")
         }
     }
+    fn optimize(
+        &mut self,
+        iterations: usize,
+        target: f64,
+        reference_surface: Option<&Tessellation>,
+    ) -> Result<(), String> {
+        if N != HEX {
+            return Err("Only implemented optimize method for hexes.".to_string());
+        }
+        if self.get_node_element_connectivity().is_empty() {
+            return Err("Need to calculate the node-to-element connectivity first".to_string());
+        }
+        if self.get_nodal_influencers().is_empty() {
+            return Err("Need to calculate the nodal influencers first".to_string());
+        }
+        #[cfg(feature = "profile")]
+        let time = Instant::now();
+        let reference_surface = reference_surface.map(ReferenceSurface::new);
+        let nodal_constraints = calculate_exterior_constraints(
+            &self.element_node_connectivity,
+            &self.nodal_coordinates,
+        );
+        let mut iteration = 1;
+        while iteration <= iterations
+            && optimize_nodal_patches_hex(
+                target,
+                &self.element_node_connectivity,
+                &self.node_element_connectivity,
+                &self.nodal_influencers,
+                &nodal_constraints,
+                reference_surface.as_ref(),
+                &mut self.nodal_coordinates,
+            )
+        {
+            iteration += 1;
+        }
+        #[cfg(feature = "profile")]
+        println!(
+            "             \x1b[1;93mOptimization iterations 1..{}\x1b[0m {:?} ",
+            iteration.min(iterations),
+            time.elapsed()
+        );
+        let minimum_scaled_jacobians = calculate_minimum_scaled_jacobians_hex(
+            self.get_element_node_connectivity(),
+            self.get_nodal_coordinates(),
+        );
+        let inverted_elements: Vec<usize> = minimum_scaled_jacobians
+            .iter()
+            .enumerate()
+            .filter(|(_, minimum_scaled_jacobian)| minimum_scaled_jacobian <= &&0.0)
+            .map(|(element, _)| element + ELEMENT_NUMBERING_OFFSET)
+            .collect();
+        if inverted_elements.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unable to untangle elements {:?}, with a minimum scaled Jacobian of {:.6e}",
+                inverted_elements,
+                minimum_scaled_jacobians
+                    .iter()
+                    .copied()
+                    .fold(f64::INFINITY, f64::min)
+            ))
+        }
+    }
     fn smooth(&mut self, method: Smoothing) -> Result<(), &str> {
-        if !self.get_node_node_connectivity().is_empty() {
-            let smoothing_iterations;
-            let smoothing_scale_deflate;
-            let mut smoothing_scale_inflate = 0.0;
-            match method {
-                Smoothing::Laplacian(iterations, scale) => {
-                    if scale <= 0.0 || scale >= 1.0 {
-                        return Err("Need to specify 0.0 < scale < 1.0");
-                    } else {
-                        smoothing_iterations = iterations;
-                        smoothing_scale_deflate = scale;
-                    }
-                }
-                Smoothing::Taubin(iterations, pass_band, scale) => {
-                    if pass_band <= 0.0 || pass_band >= 1.0 {
-                        return Err("Need to specify 0.0 < pass-band < 1.0");
-                    } else if scale <= 0.0 || scale >= 1.0 {
-                        return Err("Need to specify 0.0 < scale < 1.0");
-                    } else {
-                        smoothing_iterations = iterations;
-                        smoothing_scale_deflate = scale;
-                        smoothing_scale_inflate = scale / (pass_band * scale - 1.0);
-                        if smoothing_scale_deflate >= -smoothing_scale_inflate {
-                            return Err("Inflation scale must be larger than deflation scale");
-                        }
-                    }
-                }
-            }
-            let prescribed_nodes_inhomogeneous = self.get_prescribed_nodes_inhomogeneous().clone();
-            let prescribed_nodes_inhomogeneous_coordinates: Coordinates = self
-                .get_prescribed_nodes_inhomogeneous_coordinates()
-                .iter()
-                .cloned()
-                .collect();
-            let nodal_coordinates_mut = self.get_nodal_coordinates_mut();
-            prescribed_nodes_inhomogeneous
-                .iter()
-                .zip(prescribed_nodes_inhomogeneous_coordinates.iter())
-                .for_each(|(node, coordinates)| {
-                    nodal_coordinates_mut[node - NODE_NUMBERING_OFFSET] = coordinates.clone()
-                });
-            let mut iteration = 1;
-            let mut laplacian;
-            let mut scale;
-            #[cfg(feature = "profile")]
-            let mut frequency = 1;
-            #[cfg(feature = "profile")]
-            while (smoothing_iterations / frequency) > 10 {
-                frequency *= 10;
-            }
-            #[cfg(feature = "profile")]
-            let remainder = (smoothing_iterations as f64 / frequency as f64 == 10.0) as usize;
-            #[cfg(feature = "profile")]
-            let width = smoothing_iterations.to_string().len();
-            #[cfg(feature = "profile")]
-            let mut time = Instant::now();
-            while iteration <= smoothing_iterations {
-                scale = if smoothing_scale_inflate < 0.0 && iteration % 2 == 1 {
-                    smoothing_scale_inflate
+        self.smooth_with(method, &SmoothingOptions::default())
+    }
+    fn smooth_with(&mut self, method: Smoothing, options: &SmoothingOptions) -> Result<(), &str> {
+        let reference_surface = options.reference_surface.map(ReferenceSurface::new);
+        if options.block_smoothing.is_empty() {
+            let nodal_influencers = self.nodal_influencers.clone();
+            self.feature_influencers(options.feature_angle);
+            let result = self.smooth_pass(method, options, reference_surface.as_ref());
+            self.nodal_influencers = nodal_influencers;
+            return result;
+        }
+        if self.get_node_element_connectivity().is_empty() {
+            return Err("Need to calculate the node-to-element connectivity first");
+        }
+        //
+        // Nodes touching a frozen block are held fixed, nodes only in a block with its own
+        // smoothing are moved in a pass for that block, and all other nodes (including those
+        // on interfaces between blocks) are moved in a pass with the provided method.
+        //
+        let block_smoothing = &options.block_smoothing;
+        let node_passes: Vec<Option<usize>> = self
+            .get_node_element_connectivity()
+            .iter()
+            .map(|connected_elements| {
+                let mut connected_blocks: Blocks = connected_elements
+                    .iter()
+                    .map(|element| self.element_blocks[element - ELEMENT_NUMBERING_OFFSET])
+                    .collect();
+                connected_blocks.sort();
+                connected_blocks.dedup();
+                let settings: Vec<Option<usize>> = connected_blocks
+                    .iter()
+                    .map(|block| {
+                        block_smoothing
+                            .iter()
+                            .position(|(smoothing_block, _)| smoothing_block == block)
+                    })
+                    .collect();
+                if settings
+                    .iter()
+                    .flatten()
+                    .any(|&index| block_smoothing[index].1.is_none())
+                {
+                    None
+                } else if let [Some(index)] = settings[..] {
+                    Some(index + 1)
                 } else {
-                    smoothing_scale_deflate
-                };
-                laplacian = self.laplacian(self.get_nodal_influencers());
-                self.get_nodal_coordinates_mut()
-                    .iter_mut()
-                    .zip(laplacian.iter())
-                    .for_each(|(coordinate, entry)| *coordinate += entry * scale);
-                #[cfg(feature = "profile")]
-                if frequency == 1 {
-                    println!(
-                        "             \x1b[1;93mSmoothing iteration {}\x1b[0m {:?} ",
-                        format_args!("{:width$}", iteration, width = width),
-                        time.elapsed()
-                    );
-                    time = Instant::now();
-                } else if iteration % frequency == 0 {
-                    println!(
-                        "             \x1b[1;93mSmoothing iterations {}..{}\x1b[0m {:?} ",
-                        format_args!(
-                            "{:width$}",
-                            iteration - frequency + 1,
-                            width = width - remainder
-                        ),
-                        format_args!("{:.>width$}", iteration, width = width),
-                        time.elapsed()
-                    );
-                    time = Instant::now();
+                    Some(0)
+                }
+            })
+            .collect();
+        let prescribed_nodes_homogeneous = self.prescribed_nodes_homogeneous.clone();
+        let prescribed_nodes = self.prescribed_nodes.clone();
+        let mut iterations = 0;
+        let mut rejected_moves = 0;
+        let mut result = Ok(());
+        for (pass, pass_method) in std::iter::once(Some(method))
+            .chain(block_smoothing.iter().map(|(_, smoothing)| *smoothing))
+            .enumerate()
+        {
+            if let Some(pass_method) = pass_method {
+                self.prescribed_nodes_homogeneous = node_passes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, node_pass)| **node_pass != Some(pass))
+                    .map(|(node, _)| node + NODE_NUMBERING_OFFSET)
+                    .chain(prescribed_nodes_homogeneous.iter().copied())
+                    .collect();
+                self.prescribed_nodes_homogeneous.sort();
+                self.prescribed_nodes_homogeneous.dedup();
+                self.prescribed_nodes = self
+                    .prescribed_nodes_homogeneous
+                    .iter()
+                    .chain(self.prescribed_nodes_inhomogeneous.iter())
+                    .copied()
+                    .collect();
+                self.prescribed_nodes.sort();
+                self.prescribed_nodes.dedup();
+                self.nodal_influencers();
+                self.feature_influencers(options.feature_angle);
+                result = self.smooth_pass(pass_method, options, reference_surface.as_ref());
+                iterations += self.iterations;
+                rejected_moves += self.rejected_moves;
+                if result.is_err() {
+                    break;
                 }
-                iteration += 1;
-            }
-            #[cfg(feature = "profile")]
-            if smoothing_iterations % frequency != 0 {
-                println!(
-                    "             \x1b[1;93mSmoothing iterations {}..{}\x1b[0m {:?} ",
-                    format_args!(
-                        "{:width$}",
-                        iteration - 1 - (smoothing_iterations % frequency),
-                        width = width - remainder
-                    ),
-                    format_args!("{:.>width$}", smoothing_iterations, width = width),
-                    time.elapsed()
-                );
             }
-            Ok(())
-        } else {
-            Err("Need to calculate the node-to-node connectivity first")
         }
+        self.prescribed_nodes_homogeneous = prescribed_nodes_homogeneous;
+        self.prescribed_nodes = prescribed_nodes;
+        self.nodal_influencers();
+        self.iterations = iterations;
+        self.rejected_moves = rejected_moves;
+        result
     }
     fn write_exo(&self, file_path: &str) -> Result<(), ErrorNetCDF> {
         write_finite_elements_to_exodus(
@@ -503,6 +816,14 @@ where
             self.get_nodal_coordinates(),
         )
     }
+    fn write_metrics_selected(&self, file_path: &str, metrics: &[Metric]) -> Result<(), ErrorIO> {
+        write_finite_elements_metrics_selected(
+            file_path,
+            metrics,
+            self.get_element_node_connectivity(),
+            self.get_nodal_coordinates(),
+        )
+    }
     fn write_vtk(&self, file_path: &str) -> Result<(), ErrorVtk> {
         write_finite_elements_to_vtk(
             file_path,
@@ -529,6 +850,9 @@ where
     fn get_interior_nodes(&self) -> &Nodes {
         &self.interior_nodes
     }
+    fn get_iterations(&self) -> usize {
+        self.iterations
+    }
     fn get_nodal_coordinates(&self) -> &Coordinates {
         &self.nodal_coordinates
     }
@@ -556,6 +880,9 @@ where
     fn get_prescribed_nodes_inhomogeneous_coordinates(&self) -> &Coordinates {
         &self.prescribed_nodes_inhomogeneous_coordinates
     }
+    fn get_rejected_moves(&self) -> usize {
+        self.rejected_moves
+    }
     fn set_prescribed_nodes(
         &mut self,
         homogeneous: Option<Nodes>,
@@ -582,6 +909,8 @@ where
             .into_iter()
             .chain(self.prescribed_nodes_inhomogeneous.clone())
             .collect();
+        self.prescribed_nodes.sort();
+        self.prescribed_nodes.dedup();
         Ok(())
     }
 }
@@ -594,40 +923,351 @@ pub trait FiniteElementSpecifics {
     fn into_tesselation(self) -> Tessellation;
 }
 
-impl FiniteElementSpecifics for HexahedralFiniteElements {
-    fn connected_nodes(node: &usize) -> Vec<usize> {
-        match node {
-            0 => vec![1, 3, 4],
-            1 => vec![0, 2, 5],
-            2 => vec![1, 3, 6],
-            3 => vec![0, 2, 7],
-            4 => vec![0, 5, 7],
-            5 => vec![1, 4, 6],
-            6 => vec![2, 5, 7],
-            7 => vec![3, 4, 6],
-            _ => panic!(),
+impl<const N: usize> FiniteElements<N>
+where
+    Self: FiniteElementMethods<N> + FiniteElementSpecifics,
+{
+    fn feature_influencers(&mut self, feature_angle: Option<f64>) {
+        //
+        // Nodes on a feature edge are only influenced by their two neighbors along it,
+        // and corners where more features meet (or where a feature ends) are held fixed.
+        //
+        if let Some(feature_angle) = feature_angle {
+            let mut feature_neighbors: VecConnectivity = vec![vec![]; self.nodal_influencers.len()];
+            calculate_feature_edges(
+                self.get_element_node_connectivity(),
+                self.get_nodal_coordinates(),
+                feature_angle,
+            )
+            .into_iter()
+            .for_each(|[node_a, node_b]| {
+                feature_neighbors[node_a - NODE_NUMBERING_OFFSET].push(node_b);
+                feature_neighbors[node_b - NODE_NUMBERING_OFFSET].push(node_a);
+            });
+            self.nodal_influencers
+                .iter_mut()
+                .zip(feature_neighbors)
+                .filter(|(influencers, neighbors)| !influencers.is_empty() && !neighbors.is_empty())
+                .for_each(|(influencers, neighbors)| {
+                    if neighbors.len() == 2 {
+                        *influencers = neighbors
+                    } else {
+                        influencers.clear()
+                    }
+                });
         }
     }
-    fn into_tesselation(self) -> Tessellation {
-        unimplemented!()
-    }
-}
-
-fn reorder_connectivity<const N: usize>(
-    element_blocks: &Blocks,
-    element_blocks_unique: &Blocks,
-    element_node_connectivity: &Connectivity<N>,
-) -> ReorderedConnectivity {
-    element_blocks_unique
-        .iter()
-        .map(|unique_block| {
-            element_blocks
-                .iter()
-                .enumerate()
-                .filter(|&(_, &block)| &block == unique_block)
-                .flat_map(|(element, _)| {
-                    element_node_connectivity[element]
-                        .iter()
+    fn smooth_pass(
+        &mut self,
+        method: Smoothing,
+        options: &SmoothingOptions,
+        reference_surface: Option<&ReferenceSurface>,
+    ) -> Result<(), &'static str> {
+        if !self.get_node_node_connectivity().is_empty() {
+            let smoothing_iterations;
+            let smoothing_scale_deflate;
+            let mut smoothing_scale_inflate = 0.0;
+            let mut hc_coefficients = None;
+            match method {
+                Smoothing::HcLaplacian(iterations, alpha, beta, scale) => {
+                    if !(0.0..=1.0).contains(&alpha) {
+                        return Err("Need to specify 0.0 <= alpha <= 1.0");
+                    } else if !(0.0..=1.0).contains(&beta) {
+                        return Err("Need to specify 0.0 <= beta <= 1.0");
+                    } else if scale <= 0.0 || scale > 1.0 {
+                        return Err("Need to specify 0.0 < scale <= 1.0");
+                    } else {
+                        smoothing_iterations = iterations;
+                        smoothing_scale_deflate = scale;
+                        hc_coefficients = Some((alpha, beta));
+                    }
+                }
+                Smoothing::Laplacian(iterations, scale) => {
+                    if scale <= 0.0 || scale >= 1.0 {
+                        return Err("Need to specify 0.0 < scale < 1.0");
+                    } else {
+                        smoothing_iterations = iterations;
+                        smoothing_scale_deflate = scale;
+                    }
+                }
+                Smoothing::Taubin(iterations, pass_band, scale) => {
+                    if pass_band <= 0.0 || pass_band >= 1.0 {
+                        return Err("Need to specify 0.0 < pass-band < 1.0");
+                    } else if scale <= 0.0 || scale >= 1.0 {
+                        return Err("Need to specify 0.0 < scale < 1.0");
+                    } else {
+                        smoothing_iterations = iterations;
+                        smoothing_scale_deflate = scale;
+                        smoothing_scale_inflate = scale / (pass_band * scale - 1.0);
+                        if smoothing_scale_deflate >= -smoothing_scale_inflate {
+                            return Err("Inflation scale must be larger than deflation scale");
+                        }
+                    }
+                }
+            }
+            let prescribed_nodes_inhomogeneous = self.get_prescribed_nodes_inhomogeneous().clone();
+            let prescribed_nodes_inhomogeneous_coordinates: Coordinates = self
+                .get_prescribed_nodes_inhomogeneous_coordinates()
+                .iter()
+                .cloned()
+                .collect();
+            let nodal_coordinates_mut = self.get_nodal_coordinates_mut();
+            prescribed_nodes_inhomogeneous
+                .iter()
+                .zip(prescribed_nodes_inhomogeneous_coordinates.iter())
+                .for_each(|(node, coordinates)| {
+                    nodal_coordinates_mut[node - NODE_NUMBERING_OFFSET] = coordinates.clone()
+                });
+            let block_volumes = if options.volume_preserving {
+                Some(self.calculate_block_volumes().map_err(|_| {
+                    "Volume preservation requires each block of triangles to be a closed surface"
+                })?)
+            } else {
+                None
+            };
+            self.iterations = 0;
+            self.rejected_moves = 0;
+            let mut quality = match &options.convergence {
+                Some(Convergence::Quality(metric, _)) => worst_metric(
+                    metric,
+                    &calculate_metric(
+                        metric,
+                        self.get_element_node_connectivity(),
+                        self.get_nodal_coordinates(),
+                    )
+                    .map_err(|_| "Convergence metric is not available for this element type")?,
+                ),
+                _ => 0.0,
+            };
+            let original_coordinates = if hc_coefficients.is_some() {
+                self.get_nodal_coordinates().clone()
+            } else {
+                Coordinates::zero(0)
+            };
+            let projected_nodes: Nodes = if reference_surface.is_some() {
+                let mut nodes = match N {
+                    HEX => self.calculate_exterior_nodes(),
+                    TRI => (NODE_NUMBERING_OFFSET
+                        ..self.get_nodal_coordinates().len() + NODE_NUMBERING_OFFSET)
+                        .collect(),
+                    _ => panic!(),
+                };
+                if options.project_interface {
+                    nodes.extend(
+                        self.get_node_element_connectivity()
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, connected_elements)| {
+                                connected_elements.iter().any(|element| {
+                                    self.element_blocks[element - ELEMENT_NUMBERING_OFFSET]
+                                        != self.element_blocks
+                                            [connected_elements[0] - ELEMENT_NUMBERING_OFFSET]
+                                })
+                            })
+                            .map(|(node, _)| node + NODE_NUMBERING_OFFSET),
+                    );
+                    nodes.sort();
+                    nodes.dedup();
+                }
+                nodes.retain(|node| {
+                    !self.nodal_influencers[node - NODE_NUMBERING_OFFSET].is_empty()
+                });
+                nodes
+            } else {
+                vec![]
+            };
+            let mut previous_coordinates;
+            let mut iteration = 1;
+            let mut laplacian;
+            let mut scale;
+            #[cfg(feature = "profile")]
+            let mut frequency = 1;
+            #[cfg(feature = "profile")]
+            while (smoothing_iterations / frequency) > 10 {
+                frequency *= 10;
+            }
+            #[cfg(feature = "profile")]
+            let remainder = (smoothing_iterations as f64 / frequency as f64 == 10.0) as usize;
+            #[cfg(feature = "profile")]
+            let width = smoothing_iterations.to_string().len();
+            #[cfg(feature = "profile")]
+            let mut time = Instant::now();
+            while iteration <= smoothing_iterations {
+                scale = if smoothing_scale_inflate < 0.0 && iteration % 2 == 1 {
+                    smoothing_scale_inflate
+                } else {
+                    smoothing_scale_deflate
+                };
+                laplacian = self.laplacian(self.get_nodal_influencers());
+                previous_coordinates = if hc_coefficients.is_some()
+                    || options.safeguard
+                    || options.convergence.is_some()
+                {
+                    self.get_nodal_coordinates().clone()
+                } else {
+                    Coordinates::zero(0)
+                };
+                if !laplacian.is_empty() {
+                    let nodal_coordinates = self.get_nodal_coordinates();
+                    self.nodal_coordinates = map_nodes(nodal_coordinates.len(), |node| {
+                        &laplacian[node] * scale + &nodal_coordinates[node]
+                    });
+                }
+                if let Some((alpha, beta)) = hc_coefficients {
+                    hc_correction(
+                        alpha,
+                        beta,
+                        &self.nodal_influencers,
+                        &original_coordinates,
+                        &previous_coordinates,
+                        &mut self.nodal_coordinates,
+                    )
+                }
+                if let Some(reference_surface) = reference_surface {
+                    projected_nodes.iter().for_each(|node| {
+                        self.nodal_coordinates[node - NODE_NUMBERING_OFFSET] = reference_surface
+                            .closest_point(&self.nodal_coordinates[node - NODE_NUMBERING_OFFSET])
+                    })
+                }
+                if let Some(target_volumes) = &block_volumes {
+                    correct_block_volumes(
+                        target_volumes,
+                        &self.element_blocks,
+                        &self.element_node_connectivity,
+                        &self.nodal_influencers,
+                        &mut self.nodal_coordinates,
+                    )
+                }
+                if options.safeguard {
+                    self.rejected_moves += safeguard_step(
+                        &self.element_node_connectivity,
+                        &previous_coordinates,
+                        &mut self.nodal_coordinates,
+                    )
+                }
+                self.iterations = iteration;
+                if match &options.convergence {
+                    None => false,
+                    Some(Convergence::MaximumDisplacement(tolerance)) => {
+                        self.nodal_coordinates
+                            .iter()
+                            .zip(previous_coordinates.iter())
+                            .map(|(coordinate, previous)| (coordinate - previous).norm())
+                            .fold(0.0, f64::max)
+                            < *tolerance
+                    }
+                    Some(Convergence::Quality(metric, tolerance)) => {
+                        if smoothing_scale_inflate < 0.0 && iteration % 2 == 1 {
+                            false
+                        } else {
+                            let previous_quality = quality;
+                            quality = worst_metric(
+                                metric,
+                                &calculate_metric(
+                                    metric,
+                                    &self.element_node_connectivity,
+                                    &self.nodal_coordinates,
+                                )
+                                .unwrap(),
+                            );
+                            if metric.higher_is_better() {
+                                quality - previous_quality < *tolerance
+                            } else {
+                                previous_quality - quality < *tolerance
+                            }
+                        }
+                    }
+                    Some(Convergence::RmsDisplacement(tolerance)) => {
+                        (self
+                            .nodal_coordinates
+                            .iter()
+                            .zip(previous_coordinates.iter())
+                            .map(|(coordinate, previous)| (coordinate - previous).norm_squared())
+                            .sum::<f64>()
+                            / self.nodal_coordinates.len() as f64)
+                            .sqrt()
+                            < *tolerance
+                    }
+                } {
+                    break;
+                }
+                #[cfg(feature = "profile")]
+                if frequency == 1 {
+                    println!(
+                        "             \x1b[1;93mSmoothing iteration {}\x1b[0m {:?} ",
+                        format_args!("{:width$}", iteration, width = width),
+                        time.elapsed()
+                    );
+                    time = Instant::now();
+                } else if iteration % frequency == 0 {
+                    println!(
+                        "             \x1b[1;93mSmoothing iterations {}..{}\x1b[0m {:?} ",
+                        format_args!(
+                            "{:width$}",
+                            iteration - frequency + 1,
+                            width = width - remainder
+                        ),
+                        format_args!("{:.>width$}", iteration, width = width),
+                        time.elapsed()
+                    );
+                    time = Instant::now();
+                }
+                iteration += 1;
+            }
+            #[cfg(feature = "profile")]
+            if smoothing_iterations % frequency != 0 {
+                println!(
+                    "             \x1b[1;93mSmoothing iterations {}..{}\x1b[0m {:?} ",
+                    format_args!(
+                        "{:width$}",
+                        iteration - 1 - (smoothing_iterations % frequency),
+                        width = width - remainder
+                    ),
+                    format_args!("{:.>width$}", smoothing_iterations, width = width),
+                    time.elapsed()
+                );
+            }
+            Ok(())
+        } else {
+            Err("Need to calculate the node-to-node connectivity first")
+        }
+    }
+}
+
+impl FiniteElementSpecifics for HexahedralFiniteElements {
+    fn connected_nodes(node: &usize) -> Vec<usize> {
+        match node {
+            0 => vec![1, 3, 4],
+            1 => vec![0, 2, 5],
+            2 => vec![1, 3, 6],
+            3 => vec![0, 2, 7],
+            4 => vec![0, 5, 7],
+            5 => vec![1, 4, 6],
+            6 => vec![2, 5, 7],
+            7 => vec![3, 4, 6],
+            _ => panic!(),
+        }
+    }
+    fn into_tesselation(self) -> Tessellation {
+        unimplemented!()
+    }
+}
+
+fn reorder_connectivity<const N: usize>(
+    element_blocks: &Blocks,
+    element_blocks_unique: &Blocks,
+    element_node_connectivity: &Connectivity<N>,
+) -> ReorderedConnectivity {
+    element_blocks_unique
+        .iter()
+        .map(|unique_block| {
+            element_blocks
+                .iter()
+                .enumerate()
+                .filter(|&(_, &block)| &block == unique_block)
+                .flat_map(|(element, _)| {
+                    element_node_connectivity[element]
+                        .iter()
                         .map(|entry| *entry as i32)
                         .collect::<Vec<i32>>()
                 })
@@ -644,6 +1284,117 @@ fn automesh_header() -> String {
     )
 }
 
+fn element_type_from_inp(file_path: &str) -> Result<Option<String>, ErrorIO> {
+    let mut file = BufReader::new(File::open(file_path)?);
+    let mut buffer = String::new();
+    while file.read_line(&mut buffer)? > 0 {
+        if buffer.trim().to_uppercase().starts_with("*ELEMENT") {
+            return Ok(buffer
+                .split(",")
+                .map(|entry| entry.trim().to_uppercase())
+                .find_map(|entry| {
+                    entry
+                        .strip_prefix("TYPE=")
+                        .map(|kind| kind.trim().to_string())
+                }));
+        }
+        buffer.clear();
+    }
+    Err(ErrorIO::other(format!(
+        "No elements found in input file {}",
+        file_path
+    )))
+}
+
+fn node_set_from_inp(file_path: &str, name: &str) -> Result<Nodes, ErrorIO> {
+    let invalid_entry = |entry: &str| {
+        ErrorIO::other(format!(
+            "Invalid entry {} in node set {} of input file {}",
+            entry, name, file_path
+        ))
+    };
+    let file = BufReader::new(File::open(file_path)?);
+    let mut node_numbers = vec![];
+    let mut set_numbers = vec![];
+    let mut found = false;
+    let mut reading_nodes = false;
+    let mut reading_set = false;
+    let mut generate = false;
+    for line in file.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("**") {
+            continue;
+        } else if line.starts_with('*') {
+            let keywords: Vec<String> = line
+                .split(",")
+                .map(|entry| entry.trim().to_uppercase().replace(" ", ""))
+                .collect();
+            reading_nodes = keywords[0] == "*NODE";
+            reading_set = keywords[0] == "*NSET"
+                && keywords.contains(&format!("NSET={}", name.to_uppercase()));
+            generate = keywords.iter().any(|keyword| keyword == "GENERATE");
+            found |= reading_set;
+        } else if reading_nodes {
+            node_numbers.push(
+                line.split(",")
+                    .next()
+                    .and_then(|entry| entry.trim().parse::<usize>().ok())
+                    .ok_or_else(|| {
+                        ErrorIO::other(format!("Invalid node {} in input file {}", line, file_path))
+                    })?,
+            );
+        } else if reading_set {
+            let entries = line
+                .split(",")
+                .map(|entry| entry.trim())
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| entry.parse::<usize>().map_err(|_| invalid_entry(entry)))
+                .collect::<Result<Vec<usize>, ErrorIO>>()?;
+            if generate {
+                match entries[..] {
+                    [first, last] => set_numbers.extend(first..=last),
+                    [first, last, step] if step > 0 => {
+                        set_numbers.extend((first..=last).step_by(step))
+                    }
+                    _ => return Err(invalid_entry(line)),
+                }
+            } else {
+                set_numbers.extend(entries)
+            }
+        }
+    }
+    if !found {
+        return Err(ErrorIO::other(format!(
+            "No node set {} found in input file {}",
+            name, file_path
+        )));
+    }
+    let mut mapping = vec![0_usize; node_numbers.iter().max().map_or(0, |max| max + 1)];
+    node_numbers
+        .iter()
+        .enumerate()
+        .for_each(|(new, old)| mapping[*old] = new + NODE_NUMBERING_OFFSET);
+    let mut nodes = set_numbers
+        .into_iter()
+        .map(|number| {
+            mapping
+                .get(number)
+                .copied()
+                .filter(|node| node > &0)
+                .ok_or_else(|| {
+                    ErrorIO::other(format!(
+                        "Node {} of node set {} not found in input file {}",
+                        number, name, file_path
+                    ))
+                })
+        })
+        .collect::<Result<Nodes, ErrorIO>>()?;
+    nodes.sort();
+    nodes.dedup();
+    Ok(nodes)
+}
+
 fn finite_element_data_from_inp<const N: usize>(
     file_path: &str,
 ) -> Result<(Blocks, Connectivity<N>, Coordinates), ErrorIO> {
@@ -707,7 +1458,12 @@ fn finite_element_data_from_inp<const N: usize>(
                     .map(|entry| entry.trim().parse::<usize>().unwrap())
                     .collect::<Vec<usize>>()
                     .try_into()
-                    .unwrap(),
+                    .map_err(|_| {
+                        ErrorIO::other(format!(
+                            "Expected {} nodes per element in input file {}",
+                            N, file_path
+                        ))
+                    })?,
             );
             element_numbers.push(
                 buffer
@@ -841,6 +1597,70 @@ fn write_finite_elements_to_abaqus<const N: usize>(
     file.flush()
 }
 
+/// Writes an Abaqus input file one node and one element at a time.
+///
+/// The nodes must be written before the elements, and the elements of each block together.
+pub(crate) struct AbaqusWriter<const N: usize> {
+    element_number_width: usize,
+    elements: bool,
+    file: BufWriter<File>,
+    node_number_width: usize,
+}
+
+impl<const N: usize> AbaqusWriter<N> {
+    /// Creates the file and writes the heading for the given numbers of elements and nodes.
+    pub(crate) fn create(file_path: &str, elements: usize, nodes: usize) -> Result<Self, ErrorIO> {
+        let mut file = BufWriter::new(File::create(file_path)?);
+        write_heading_to_inp(&mut file)?;
+        write_nodes_header_to_inp(&mut file)?;
+        Ok(Self {
+            element_number_width: elements.to_string().chars().count(),
+            elements: false,
+            file,
+            node_number_width: nodes.to_string().chars().count(),
+        })
+    }
+    /// Writes the coordinates of a node.
+    pub(crate) fn node(&mut self, node: usize, coordinates: &Coordinate) -> Result<(), ErrorIO> {
+        write_node_to_inp(&mut self.file, node, coordinates, &self.node_number_width)
+    }
+    /// Starts the elements of a block.
+    pub(crate) fn block(&mut self, block: &u8) -> Result<(), ErrorIO> {
+        newline(&mut self.file)?;
+        if !self.elements {
+            self.elements = true;
+            end_section(&mut self.file)?;
+            write_elements_header_to_inp(&mut self.file)?;
+        }
+        write_element_block_header_to_inp::<N>(&mut self.file, block)
+    }
+    /// Writes the connectivity of an element.
+    pub(crate) fn element(
+        &mut self,
+        element: usize,
+        connectivity: &[usize; N],
+    ) -> Result<(), ErrorIO> {
+        write_element_to_inp(
+            &mut self.file,
+            element,
+            connectivity,
+            &self.element_number_width,
+            &self.node_number_width,
+        )
+    }
+    /// Writes the sections of the blocks and flushes the file.
+    pub(crate) fn finish(mut self, blocks: &Blocks) -> Result<(), ErrorIO> {
+        newline(&mut self.file)?;
+        if !self.elements {
+            end_section(&mut self.file)?;
+            write_elements_header_to_inp(&mut self.file)?;
+        }
+        end_section(&mut self.file)?;
+        write_solid_sections_to_inp(&mut self.file, blocks)?;
+        self.file.flush()
+    }
+}
+
 fn write_heading_to_inp(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
     let prefix = "*HEADING\n";
     let middle = automesh_header().replace(", ", "\n");
@@ -857,28 +1677,17 @@ fn write_nodal_coordinates_to_inp(
 ) -> Result<(), ErrorIO> {
     #[cfg(feature = "profile")]
     let time = Instant::now();
-    file.write_all(
-        "********************************** N O D E S **********************************\n"
-            .as_bytes(),
-    )?;
-    file.write_all("*NODE, NSET=ALLNODES".as_bytes())?;
+    write_nodes_header_to_inp(file)?;
     nodal_coordinates
         .iter()
         .enumerate()
         .try_for_each(|(node, coordinates)| {
-            indent(file)?;
-            file.write_all(
-                format!(
-                    "{:>width$}",
-                    node + NODE_NUMBERING_OFFSET,
-                    width = node_number_width
-                )
-                .as_bytes(),
-            )?;
-            coordinates.iter().try_for_each(|coordinate| {
-                delimiter(file)?;
-                file.write_all(format!("{:>15.6e}", coordinate).as_bytes())
-            })
+            write_node_to_inp(
+                file,
+                node + NODE_NUMBERING_OFFSET,
+                coordinates,
+                node_number_width,
+            )
         })?;
     newline(file)?;
     let result = end_section(file);
@@ -899,15 +1708,7 @@ fn write_element_node_connectivity_to_inp<const N: usize>(
 ) -> Result<(), ErrorIO> {
     #[cfg(feature = "profile")]
     let time = Instant::now();
-    let element_type = match N {
-        HEX => "C3D8R",
-        TRI => "TRI3",
-        _ => panic!(),
-    };
-    file.write_all(
-        "********************************** E L E M E N T S ****************************\n"
-            .as_bytes(),
-    )?;
+    write_elements_header_to_inp(file)?;
     let mut element_blocks_unique = element_blocks.clone();
     element_blocks_unique.sort();
     element_blocks_unique.dedup();
@@ -915,45 +1716,24 @@ fn write_element_node_connectivity_to_inp<const N: usize>(
         .iter()
         .clone()
         .try_for_each(|current_block| {
-            file.write_all(
-                format!("*ELEMENT, TYPE={}, ELSET=EB{}", element_type, current_block).as_bytes(),
-            )?;
+            write_element_block_header_to_inp::<N>(file, current_block)?;
             element_blocks
                 .iter()
                 .enumerate()
                 .filter(|(_, block)| block == &current_block)
                 .try_for_each(|(element, _)| {
-                    indent(file)?;
-                    file.write_all(
-                        format!(
-                            "{:>width$}",
-                            element + ELEMENT_NUMBERING_OFFSET,
-                            width = element_number_width
-                        )
-                        .as_bytes(),
-                    )?;
-                    element_node_connectivity[element]
-                        .iter()
-                        .try_for_each(|entry| {
-                            delimiter(file)?;
-                            file.write_all(
-                                format!("{:>width$}", entry, width = node_number_width + 3)
-                                    .as_bytes(),
-                            )
-                        })
+                    write_element_to_inp(
+                        file,
+                        element + ELEMENT_NUMBERING_OFFSET,
+                        &element_node_connectivity[element],
+                        element_number_width,
+                        node_number_width,
+                    )
                 })?;
             newline(file)
         })?;
     end_section(file)?;
-    let result = element_blocks_unique.iter().try_for_each(|block| {
-        file.write_all(
-            format!(
-                "*SOLID SECTION, ELSET=EB{}, MATERIAL=Default-Steel\n",
-                block
-            )
-            .as_bytes(),
-        )
-    });
+    let result = write_solid_sections_to_inp(file, &element_blocks_unique);
     #[cfg(feature = "profile")]
     println!(
         "             \x1b[1;93mElement-to-node connectivity\x1b[0m {:?}",
@@ -962,15 +1742,83 @@ fn write_element_node_connectivity_to_inp<const N: usize>(
     result
 }
 
-fn end_section(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
-    file.write_all(&[42, 42, 10])
+fn write_nodes_header_to_inp(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
+    file.write_all(
+        "********************************** N O D E S **********************************\n"
+            .as_bytes(),
+    )?;
+    file.write_all("*NODE, NSET=ALLNODES".as_bytes())
 }
 
-fn delimiter(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
-    file.write_all(&[44, 32])
+fn write_node_to_inp(
+    file: &mut BufWriter<File>,
+    node: usize,
+    coordinates: &Coordinate,
+    node_number_width: &usize,
+) -> Result<(), ErrorIO> {
+    indent(file)?;
+    file.write_all(format!("{:>width$}", node, width = node_number_width).as_bytes())?;
+    coordinates.iter().try_for_each(|coordinate| {
+        delimiter(file)?;
+        file.write_all(format!("{:>15.6e}", coordinate).as_bytes())
+    })
 }
 
-fn indent(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
+fn write_elements_header_to_inp(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
+    file.write_all(
+        "********************************** E L E M E N T S ****************************\n"
+            .as_bytes(),
+    )
+}
+
+fn write_element_block_header_to_inp<const N: usize>(
+    file: &mut BufWriter<File>,
+    block: &u8,
+) -> Result<(), ErrorIO> {
+    let element_type = match N {
+        HEX => "C3D8R",
+        TRI => "TRI3",
+        _ => panic!(),
+    };
+    file.write_all(format!("*ELEMENT, TYPE={}, ELSET=EB{}", element_type, block).as_bytes())
+}
+
+fn write_element_to_inp<const N: usize>(
+    file: &mut BufWriter<File>,
+    element: usize,
+    connectivity: &[usize; N],
+    element_number_width: &usize,
+    node_number_width: &usize,
+) -> Result<(), ErrorIO> {
+    indent(file)?;
+    file.write_all(format!("{:>width$}", element, width = element_number_width).as_bytes())?;
+    connectivity.iter().try_for_each(|entry| {
+        delimiter(file)?;
+        file.write_all(format!("{:>width$}", entry, width = node_number_width + 3).as_bytes())
+    })
+}
+
+fn write_solid_sections_to_inp(file: &mut BufWriter<File>, blocks: &Blocks) -> Result<(), ErrorIO> {
+    blocks.iter().try_for_each(|block| {
+        file.write_all(
+            format!(
+                "*SOLID SECTION, ELSET=EB{}, MATERIAL=Default-Steel\n",
+                block
+            )
+            .as_bytes(),
+        )
+    })
+}
+
+fn end_section(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
+    file.write_all(&[42, 42, 10])
+}
+
+fn delimiter(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
+    file.write_all(&[44, 32])
+}
+
+fn indent(file: &mut BufWriter<File>) -> Result<(), ErrorIO> {
     file.write_all(&[10, 32, 32, 32, 32])
 }
 
@@ -1069,10 +1917,10 @@ fn metrics_headers<const N: usize>() -> String {
         HEX => {
             "maximum edge ratio,minimum scaled jacobian,maximum skew,element volume\n".to_string()
         }
-        TRI => {
-            "maximum edge ratio,minimum scaled jacobian,maximum skew,element area,minimum angle\n"
-                .to_string()
-        }
+        TRI => "maximum edge ratio,minimum scaled jacobian,maximum skew,element area,\
+            minimum angle,maximum angle,aspect ratio,radius ratio,maximum condition,\
+            minimum shape,maximum normal deviation\n"
+            .to_string(),
         _ => panic!(),
     }
 }
@@ -1097,6 +1945,211 @@ fn write_finite_elements_metrics<const N: usize>(
     }
 }
 
+fn calculate_metric<const N: usize>(
+    metric: &Metric,
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Result<Metrics, String> {
+    match (N, metric) {
+        (_, Metric::EdgeRatio) => Ok(calculate_maximum_edge_ratios(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (_, Metric::ScaledJacobian) => Ok(calculate_minimum_scaled_jacobians(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (_, Metric::Skew) => Ok(calculate_maximum_skews(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Condition) => Ok(calculate_maximum_conditions_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::DiagonalRatio) => Ok(calculate_diagonal_ratios_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Distortion) => Ok(calculate_distortions_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::ElementVolume) => Ok(calculate_element_volumes_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Oddy) => Ok(calculate_maximum_oddys_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::RelativeSizeSquared) => Ok(calculate_relative_sizes_squared_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Shape) => Ok(calculate_minimum_shapes_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Shear) => Ok(calculate_minimum_shears_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Stretch) => Ok(calculate_stretches_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Taper) => Ok(calculate_maximum_tapers_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (HEX, Metric::Warpage) => Ok(calculate_maximum_warpages_hex(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::ElementArea) => Ok(calculate_element_areas_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::AspectRatio) => Ok(calculate_aspect_ratios_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::Condition) => Ok(calculate_conditions_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::MaximumAngle) => Ok(calculate_maximum_angles_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::MinimumAngle) => Ok(calculate_minimum_angles_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::NormalDeviation) => Ok(calculate_maximum_normal_deviations_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::RadiusRatio) => Ok(calculate_radius_ratios_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        (TRI, Metric::Shape) => Ok(calculate_shapes_tri(
+            element_node_connectivity,
+            nodal_coordinates,
+        )),
+        _ => Err(format!(
+            "Metric {} is not available for this element type",
+            metric.header()
+        )),
+    }
+}
+
+fn calculate_metrics<const N: usize>(
+    metrics: &[Metric],
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Result<Vec<Metrics>, String> {
+    metrics
+        .iter()
+        .map(|metric| calculate_metric(metric, element_node_connectivity, nodal_coordinates))
+        .collect()
+}
+
+fn map_elements<const N: usize, F>(element_node_connectivity: &Connectivity<N>, map: F) -> Metrics
+where
+    F: Fn(&[usize; N]) -> f64 + Send + Sync,
+{
+    Metrics::from_vec(map_indices(element_node_connectivity.len(), |element| {
+        map(&element_node_connectivity[element])
+    }))
+}
+
+fn map_indices<T, F>(number_of_indices: usize, map: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        (0..number_of_indices).into_par_iter().map(map).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..number_of_indices).map(map).collect()
+    }
+}
+
+fn map_nodes<F>(number_of_nodes: usize, map: F) -> Coordinates
+where
+    F: Fn(usize) -> Coordinate + Send + Sync,
+{
+    map_indices(number_of_nodes, map).into_iter().collect()
+}
+
+fn write_finite_elements_metrics_selected<const N: usize>(
+    file_path: &str,
+    metrics: &[Metric],
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Result<(), ErrorIO> {
+    let columns = calculate_metrics(metrics, element_node_connectivity, nodal_coordinates)
+        .map_err(ErrorIO::other)?;
+    #[cfg(feature = "profile")]
+    let time = Instant::now();
+    let number_of_elements = element_node_connectivity.len();
+    let input_extension = Path::new(&file_path)
+        .extension()
+        .and_then(|ext| ext.to_str());
+    match input_extension {
+        Some("csv") => {
+            let mut file = BufWriter::new(File::create(file_path)?);
+            let header_string = metrics
+                .iter()
+                .map(|metric| metric.header())
+                .collect::<Vec<&str>>()
+                .join(",");
+            writeln!(file, "{}", header_string)?;
+            (0..number_of_elements).try_for_each(|element| {
+                writeln!(
+                    file,
+                    "{}",
+                    columns
+                        .iter()
+                        .map(|column| format!("{:>10.6e}", column[element]))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                )
+            })?;
+            file.flush()?
+        }
+        Some("npy") => {
+            let file = BufWriter::new(File::create(file_path)?);
+            let mut metrics_set =
+                Array2::<f64>::from_elem((number_of_elements, columns.len()), 0.0);
+            columns
+                .iter()
+                .enumerate()
+                .for_each(|(index, column)| metrics_set.slice_mut(s![.., index]).assign(column));
+            metrics_set.write_npy(file).map_err(ErrorIO::other)?;
+        }
+        _ => {
+            return Err(ErrorIO::other(format!(
+                "Invalid extension .{} from output file {}",
+                input_extension.unwrap_or("UNDEFINED"),
+                file_path
+            )));
+        }
+    }
+    #[cfg(feature = "profile")]
+    println!(
+        "             \x1b[1;93mWriting selected metrics to file\x1b[0m {:?}",
+        time.elapsed()
+    );
+    Ok(())
+}
+
 fn write_finite_elements_metrics_hex<const N: usize>(
     file_path: &str,
     element_node_connectivity: &Connectivity<N>,
@@ -1107,12 +2160,24 @@ fn write_finite_elements_metrics_hex<const N: usize>(
     if N != HEX {
         panic!("Only implemented for hexahedral elements.")
     }
-    let maximum_edge_ratios =
-        calculate_maximum_edge_ratios(element_node_connectivity, nodal_coordinates);
-    let minimum_scaled_jacobians =
-        calculate_minimum_scaled_jacobians(element_node_connectivity, nodal_coordinates);
-    let maximum_skews = calculate_maximum_skews(element_node_connectivity, nodal_coordinates);
-    let volumes = calculate_element_volumes_hex(element_node_connectivity, nodal_coordinates);
+    let [
+        maximum_edge_ratios,
+        minimum_scaled_jacobians,
+        maximum_skews,
+        volumes,
+    ]: [Metrics; 4] = calculate_metrics(
+        &[
+            Metric::EdgeRatio,
+            Metric::ScaledJacobian,
+            Metric::Skew,
+            Metric::ElementVolume,
+        ],
+        element_node_connectivity,
+        nodal_coordinates,
+    )
+    .map_err(ErrorIO::other)?
+    .try_into()
+    .unwrap();
     #[cfg(feature = "profile")]
     let time = Instant::now();
     let mut file = BufWriter::new(File::create(file_path)?);
@@ -1239,43 +2304,37 @@ fn calculate_maximum_edge_ratios_hex<const N: usize>(
     if N != HEX {
         panic!("Only implemented for hexahedral elements.")
     }
-    let mut l1 = 0.0;
-    let mut l2 = 0.0;
-    let mut l3 = 0.0;
-    let maximum_edge_ratios = element_node_connectivity
-        .iter()
-        .map(|connectivity| {
-            l1 = (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET])
-                .norm();
-            l2 = (&nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET])
-                .norm();
-            l3 = (&nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                + &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET])
-                .norm();
-            [l1, l2, l3].into_iter().reduce(f64::max).unwrap()
-                / [l1, l2, l3].into_iter().reduce(f64::min).unwrap()
-        })
-        .collect();
+    let maximum_edge_ratios = map_elements(element_node_connectivity, |connectivity| {
+        let l1 = (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET])
+            .norm();
+        let l2 = (&nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET])
+            .norm();
+        let l3 = (&nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            + &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET])
+            .norm();
+        [l1, l2, l3].into_iter().reduce(f64::max).unwrap()
+            / [l1, l2, l3].into_iter().reduce(f64::min).unwrap()
+    });
     maximum_edge_ratios
 }
 
@@ -1288,94 +2347,9 @@ fn calculate_minimum_scaled_jacobians_hex<const N: usize>(
     if N != HEX {
         panic!("Only implemented for hexahedral elements.")
     }
-    let mut u = Vector::zero();
-    let mut v = Vector::zero();
-    let mut w = Vector::zero();
-    let mut n = Vector::zero();
-    let minimum_scaled_jacobians = element_node_connectivity
-        .iter()
-        .map(|connectivity| {
-            connectivity
-                .iter()
-                .enumerate()
-                .map(|(index, node)| {
-                    match index {
-                        0 => {
-                            u = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        1 => {
-                            u = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        2 => {
-                            u = &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        3 => {
-                            u = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        4 => {
-                            u = &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        5 => {
-                            u = &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        6 => {
-                            u = &nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        7 => {
-                            u = &nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            v = &nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                            w = &nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET]
-                                - &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
-                        }
-                        _ => panic!(),
-                    }
-                    n = u.cross(&v);
-                    (&n * &w) / u.norm() / v.norm() / w.norm()
-                })
-                .collect::<Vec<f64>>()
-                .into_iter()
-                .reduce(f64::min)
-                .unwrap()
-        })
-        .collect();
-    minimum_scaled_jacobians
+    map_elements(element_node_connectivity, |connectivity| {
+        calculate_element_minimum_scaled_jacobian_hex(connectivity, nodal_coordinates)
+    })
 }
 
 fn calculate_element_principal_axes<const N: usize>(
@@ -1423,50 +2397,1179 @@ fn calculate_maximum_skews_hex<const N: usize>(
     if N != HEX {
         panic!("Only implemented for hexahedral elements.")
     }
-    let mut x1 = Vector::zero();
-    let mut x2 = Vector::zero();
-    let mut x3 = Vector::zero();
-    let maximum_skews = element_node_connectivity
+    let maximum_skews = map_elements(element_node_connectivity, |connectivity| {
+        let (mut x1, mut x2, mut x3) =
+            calculate_element_principal_axes(connectivity, nodal_coordinates);
+        x1.normalize();
+        x2.normalize();
+        x3.normalize();
+        [(&x1 * &x2).abs(), (&x1 * &x3).abs(), (&x2 * &x3).abs()]
+            .into_iter()
+            .reduce(f64::max)
+            .unwrap()
+    });
+    maximum_skews
+}
+
+fn calculate_block_volumes<const N: usize>(
+    element_blocks: &Blocks,
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Result<Vec<(u8, f64)>, String> {
+    let mut blocks = element_blocks.clone();
+    blocks.sort();
+    blocks.dedup();
+    //
+    // The enclosed volume of a block of triangles is only defined when the block is a closed
+    // surface, since the tetrahedra formed with the origin do not cancel for an open patch.
+    //
+    if N == TRI
+        && let Some(block) = blocks.iter().find(|&&block| {
+            !calculate_boundary_entities(
+                &element_blocks
+                    .iter()
+                    .zip(element_node_connectivity.iter())
+                    .filter(|(element_block, _)| element_block == &&block)
+                    .map(|(_, connectivity)| *connectivity)
+                    .collect(),
+            )
+            .is_empty()
+        })
+    {
+        return Err(format!(
+            "Block {} is not a closed surface, so its volume is undefined",
+            block
+        ));
+    }
+    let element_volumes = match N {
+        HEX => calculate_element_volumes_hex(element_node_connectivity, nodal_coordinates),
+        TRI => calculate_enclosed_volumes_tri(element_node_connectivity, nodal_coordinates),
+        _ => panic!(),
+    };
+    Ok(blocks
+        .into_iter()
+        .map(|block| {
+            (
+                block,
+                element_blocks
+                    .iter()
+                    .zip(element_volumes.iter())
+                    .filter(|(element_block, _)| element_block == &&block)
+                    .map(|(_, volume)| volume)
+                    .sum(),
+            )
+        })
+        .collect())
+}
+
+fn worst_metric(metric: &Metric, metrics: &Metrics) -> f64 {
+    if metric.higher_is_better() {
+        metrics.iter().copied().fold(f64::MAX, f64::min)
+    } else {
+        metrics.iter().copied().fold(f64::MIN, f64::max)
+    }
+}
+
+fn hc_correction(
+    alpha: f64,
+    beta: f64,
+    nodal_influencers: &VecConnectivity,
+    original_coordinates: &Coordinates,
+    previous_coordinates: &Coordinates,
+    nodal_coordinates: &mut Coordinates,
+) {
+    //
+    // Vollmer J, Mencl R, Mueller H. Improved Laplacian smoothing of noisy surface meshes.
+    // Computer Graphics Forum. 1999;18(3):131-138.
+    //
+    let differences: Coordinates = nodal_coordinates
         .iter()
-        .map(|connectivity| {
-            (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
-            x1.normalize();
-            x2.normalize();
-            x3.normalize();
-            [(&x1 * &x2).abs(), (&x1 * &x3).abs(), (&x2 * &x3).abs()]
-                .into_iter()
-                .reduce(f64::max)
-                .unwrap()
+        .zip(original_coordinates.iter().zip(previous_coordinates.iter()))
+        .map(|(coordinate, (original, previous))| {
+            coordinate.clone() - (original * alpha + previous * (1.0 - alpha))
         })
         .collect();
-    maximum_skews
+    nodal_coordinates
+        .iter_mut()
+        .zip(nodal_influencers.iter().zip(differences.iter()))
+        .filter(|(_, (influencers, _))| !influencers.is_empty())
+        .for_each(|(coordinate, (influencers, difference))| {
+            *coordinate -= difference * beta
+                + influencers
+                    .iter()
+                    .map(|node| differences[node - NODE_NUMBERING_OFFSET].clone())
+                    .sum::<Coordinate>()
+                    * ((1.0 - beta) / influencers.len() as f64)
+        });
 }
 
-fn calculate_element_volumes_hex<const N: usize>(
+fn calculate_boundary_entities<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+) -> VecConnectivity {
+    //
+    // The boundary of a hexahedral mesh is made of the faces belonging to only one element,
+    // and the boundary of a triangular surface is made of the edges belonging to only one
+    // element. This is purely topological, so it does not assume a structured mesh.
+    //
+    let local_entities: Vec<&[usize]> = match N {
+        HEX => HEX_FACES.iter().map(|face| &face[..]).collect(),
+        TRI => TRI_EDGES.iter().map(|edge| &edge[..]).collect(),
+        _ => panic!(),
+    };
+    let mut entity_counts: HashMap<Vec<usize>, (usize, Vec<usize>)> = HashMap::new();
+    element_node_connectivity.iter().for_each(|connectivity| {
+        local_entities.iter().for_each(|entity| {
+            let nodes: Vec<usize> = entity.iter().map(|&index| connectivity[index]).collect();
+            let mut key = nodes.clone();
+            key.sort();
+            entity_counts.entry(key).or_insert((0, nodes)).0 += 1;
+        })
+    });
+    let mut boundary_entities: VecConnectivity = entity_counts
+        .into_values()
+        .filter(|(count, _)| *count == 1)
+        .map(|(_, nodes)| nodes)
+        .collect();
+    boundary_entities.sort();
+    boundary_entities
+}
+
+fn calculate_feature_edges<const N: usize>(
     element_node_connectivity: &Connectivity<N>,
     nodal_coordinates: &Coordinates,
-) -> Metrics {
-    // #TODO: consider rearchitect, as these types of if-type-checks
-    // indicate rearchitecture may help code logic.
-    if N != HEX {
-        panic!("Only implemented for hexahedral elements.")
-    }
-    #[cfg(feature = "profile")]
-    let time = Instant::now();
-    let mut x1 = Vector::zero();
-    let mut x2 = Vector::zero();
-    let mut x3 = Vector::zero();
-    let element_volumes = element_node_connectivity
+    feature_angle: f64,
+) -> Vec<[usize; 2]> {
+    //
+    // The exterior surface is the hexahedral faces belonging to only one element,
+    // or the triangles themselves. Surface edges are features when they are not shared by
+    // exactly two faces, or when the angle between the normals of the two faces is too large.
+    // Nodes on exactly two feature edges then move only along the edge, and other feature
+    // nodes are corners that are held fixed.
+    //
+    let faces: VecConnectivity = match N {
+        HEX => calculate_boundary_entities(element_node_connectivity),
+        TRI => element_node_connectivity
+            .iter()
+            .map(|connectivity| connectivity.to_vec())
+            .collect(),
+        _ => panic!(),
+    };
+    let normals: Vec<Vector> = faces
         .iter()
-        .map(|connectivity| {
-            (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
-            &x2.cross(&x3) * &x1 / 64.0
+        .map(|face| {
+            let normal = (&nodal_coordinates[face[2] - NODE_NUMBERING_OFFSET]
+                - &nodal_coordinates[face[0] - NODE_NUMBERING_OFFSET])
+                .cross(
+                    &(&nodal_coordinates[face[face.len() - 1] - NODE_NUMBERING_OFFSET]
+                        - &nodal_coordinates[face[1] - NODE_NUMBERING_OFFSET]),
+                );
+            if normal.norm() > 0.0 {
+                normal.normalized()
+            } else {
+                normal
+            }
         })
         .collect();
-    #[cfg(feature = "profile")]
-    println!(
-        "             \x1b[1;93mHexahedron element volumes\x1b[0m {:?}",
-        time.elapsed()
-    );
-    element_volumes
+    let mut edge_faces: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+    faces.iter().enumerate().for_each(|(index, face)| {
+        (0..face.len()).for_each(|edge| {
+            let node_a = face[edge];
+            let node_b = face[(edge + 1) % face.len()];
+            edge_faces
+                .entry([node_a.min(node_b), node_a.max(node_b)])
+                .or_default()
+                .push(index)
+        })
+    });
+    let minimum_cosine = feature_angle.to_radians().cos();
+    let mut feature_edges: Vec<[usize; 2]> = edge_faces
+        .into_iter()
+        .filter(|(_, faces)| {
+            faces.len() != 2 || &normals[faces[0]] * &normals[faces[1]] < minimum_cosine
+        })
+        .map(|(edge, _)| edge)
+        .collect();
+    feature_edges.sort();
+    feature_edges
+}
+
+struct ReferenceSurface {
+    cells: VecConnectivity,
+    cell_size: f64,
+    minimum: [f64; NSD],
+    number_of_cells: [usize; NSD],
+    triangles: Vec<[Coordinate; TRI]>,
+}
+
+impl ReferenceSurface {
+    fn new(tessellation: &Tessellation) -> Self {
+        //
+        // The triangles are binned into a uniform grid of cells covering their bounding box,
+        // so that the closest point is found by searching shells of cells around a point.
+        //
+        let data = tessellation.get_data();
+        let triangles: Vec<[Coordinate; TRI]> = data
+            .faces
+            .iter()
+            .map(|face| {
+                face.vertices.map(|vertex| {
+                    let vertex = data.vertices[vertex];
+                    Coordinate::new([vertex[0].into(), vertex[1].into(), vertex[2].into()])
+                })
+            })
+            .collect();
+        let mut minimum = [f64::INFINITY; NSD];
+        let mut maximum = [f64::NEG_INFINITY; NSD];
+        triangles.iter().flatten().for_each(|vertex| {
+            (0..NSD).for_each(|i| {
+                minimum[i] = minimum[i].min(vertex[i]);
+                maximum[i] = maximum[i].max(vertex[i]);
+            })
+        });
+        let extent = (0..NSD)
+            .map(|i| maximum[i] - minimum[i])
+            .fold(0.0, f64::max);
+        let cell_size = if extent > 0.0 {
+            extent / (triangles.len() as f64).cbrt().max(1.0)
+        } else {
+            1.0
+        };
+        let number_of_cells =
+            from_fn(|i| (((maximum[i] - minimum[i]) / cell_size).ceil() as usize).max(1));
+        let mut reference_surface = Self {
+            cells: vec![],
+            cell_size,
+            minimum,
+            number_of_cells,
+            triangles,
+        };
+        let mut cells: VecConnectivity = vec![vec![]; number_of_cells.iter().product::<usize>()];
+        reference_surface
+            .triangles
+            .iter()
+            .enumerate()
+            .for_each(|(index, triangle)| {
+                let lower: [usize; NSD] = from_fn(|i| {
+                    triangle
+                        .iter()
+                        .map(|vertex| reference_surface.cell_index(vertex, i))
+                        .min()
+                        .unwrap()
+                });
+                let upper: [usize; NSD] = from_fn(|i| {
+                    triangle
+                        .iter()
+                        .map(|vertex| reference_surface.cell_index(vertex, i))
+                        .max()
+                        .unwrap()
+                });
+                (lower[0]..=upper[0]).for_each(|i| {
+                    (lower[1]..=upper[1]).for_each(|j| {
+                        (lower[2]..=upper[2])
+                            .for_each(|k| cells[reference_surface.cell([i, j, k])].push(index))
+                    })
+                })
+            });
+        reference_surface.cells = cells;
+        reference_surface
+    }
+    fn cell(&self, indices: [usize; NSD]) -> usize {
+        indices[0] + self.number_of_cells[0] * (indices[1] + self.number_of_cells[1] * indices[2])
+    }
+    fn cell_index(&self, point: &Coordinate, i: usize) -> usize {
+        (((point[i] - self.minimum[i]) / self.cell_size).max(0.0) as usize)
+            .min(self.number_of_cells[i] - 1)
+    }
+    fn closest_point(&self, point: &Coordinate) -> Coordinate {
+        let center: [usize; NSD] = from_fn(|i| self.cell_index(point, i));
+        let maximum_shell = *self.number_of_cells.iter().max().unwrap();
+        let mut closest = point.clone();
+        let mut distance = f64::INFINITY;
+        let mut shell = 0;
+        while shell <= maximum_shell {
+            let lower: [usize; NSD] = from_fn(|i| center[i].saturating_sub(shell));
+            let upper: [usize; NSD] =
+                from_fn(|i| (center[i] + shell).min(self.number_of_cells[i] - 1));
+            (lower[0]..=upper[0]).for_each(|i| {
+                (lower[1]..=upper[1]).for_each(|j| {
+                    (lower[2]..=upper[2]).for_each(|k| {
+                        if [i, j, k]
+                            .iter()
+                            .zip(center.iter())
+                            .any(|(index, center)| index.abs_diff(*center) == shell)
+                        {
+                            self.cells[self.cell([i, j, k])]
+                                .iter()
+                                .for_each(|&triangle| {
+                                    let candidate =
+                                        closest_point_on_triangle(point, &self.triangles[triangle]);
+                                    let candidate_distance = (&candidate - point).norm();
+                                    if candidate_distance < distance {
+                                        distance = candidate_distance;
+                                        closest = candidate;
+                                    }
+                                })
+                        }
+                    })
+                })
+            });
+            if distance <= shell as f64 * self.cell_size {
+                break;
+            }
+            shell += 1;
+        }
+        closest
+    }
+}
+
+fn closest_point_on_triangle(point: &Coordinate, triangle: &[Coordinate; TRI]) -> Coordinate {
+    //
+    // Ericson, Real-Time Collision Detection (2005), Section 5.1.5.
+    //
+    let [a, b, c] = triangle;
+    let ab = b - a;
+    let ac = c - a;
+    let ap = point - a;
+    let d1 = &ab * &ap;
+    let d2 = &ac * &ap;
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a.clone();
+    }
+    let bp = point - b;
+    let d3 = &ab * &bp;
+    let d4 = &ac * &bp;
+    if d3 >= 0.0 && d4 <= d3 {
+        return b.clone();
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = point - c;
+    let d5 = &ab * &cp;
+    let d6 = &ac * &cp;
+    if d6 >= 0.0 && d5 <= d6 {
+        return c.clone();
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+const SAFEGUARD_BACKTRACKS: usize = 4;
+const VOLUME_CORRECTION_STEPS: usize = 8;
+const VOLUME_TOLERANCE: f64 = 1e-12;
+
+/// The orientation of an element, used to detect inversions during smoothing.
+enum Orientation {
+    /// The minimum scaled Jacobian of a hexahedron.
+    Hexahedron(f64),
+    /// The (unnormalized) normal of a triangle.
+    Triangle(Vector),
+}
+
+impl Orientation {
+    fn is_inverted_from(&self, before: &Self) -> bool {
+        match (before, self) {
+            (Self::Hexahedron(before), Self::Hexahedron(after)) => *before > 0.0 && *after <= 0.0,
+            (Self::Triangle(before), Self::Triangle(after)) => {
+                before.norm() > 0.0 && before * after <= 0.0
+            }
+            _ => panic!(),
+        }
+    }
+}
+
+fn calculate_element_orientations<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Vec<Orientation> {
+    element_node_connectivity
+        .iter()
+        .map(|connectivity| match N {
+            HEX => Orientation::Hexahedron(calculate_element_minimum_scaled_jacobian_hex(
+                connectivity,
+                nodal_coordinates,
+            )),
+            TRI => Orientation::Triangle(
+                (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+                    - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET])
+                    .cross(
+                        &(&nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+                            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]),
+                    ),
+            ),
+            _ => panic!(),
+        })
+        .collect()
+}
+
+fn safeguard_step<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    previous_coordinates: &Coordinates,
+    nodal_coordinates: &mut Coordinates,
+) -> usize {
+    //
+    // Elements that were valid before the step and are inverted after it have the moves of their
+    // nodes halved a few times, and then rejected altogether if the element remains inverted.
+    // Hexahedra are inverted when a corner scaled Jacobian is non-positive,
+    // and triangles when their normal flips relative to before the step.
+    //
+    let orientations =
+        calculate_element_orientations(element_node_connectivity, previous_coordinates);
+    let mut rejected = vec![false; nodal_coordinates.len()];
+    let mut backtrack = 0;
+    loop {
+        let mut offending_nodes: Nodes = element_node_connectivity
+            .iter()
+            .zip(orientations.iter())
+            .zip(calculate_element_orientations(
+                element_node_connectivity,
+                nodal_coordinates,
+            ))
+            .filter(|((_, before), after)| after.is_inverted_from(before))
+            .flat_map(|((connectivity, _), _)| connectivity.iter())
+            .map(|node| node - NODE_NUMBERING_OFFSET)
+            .filter(|node| nodal_coordinates[*node] != previous_coordinates[*node])
+            .collect();
+        if offending_nodes.is_empty() {
+            break;
+        }
+        offending_nodes.sort();
+        offending_nodes.dedup();
+        offending_nodes.into_iter().for_each(|node| {
+            rejected[node] = true;
+            nodal_coordinates[node] = if backtrack < SAFEGUARD_BACKTRACKS {
+                (nodal_coordinates[node].clone() + &previous_coordinates[node]) * 0.5
+            } else {
+                previous_coordinates[node].clone()
+            }
+        });
+        backtrack += 1;
+    }
+    rejected.into_iter().filter(|rejected| *rejected).count()
+}
+
+fn correct_block_volumes<const N: usize>(
+    target_volumes: &[(u8, f64)],
+    element_blocks: &Blocks,
+    element_node_connectivity: &Connectivity<N>,
+    nodal_influencers: &VecConnectivity,
+    nodal_coordinates: &mut Coordinates,
+) {
+    //
+    // The volume of each block is a constraint enforced with Lagrange multipliers, so each
+    // Newton step is the smallest nodal displacement that restores every block volume to first
+    // order. Nodes shared between blocks move under the constraints of all of their blocks, and
+    // nodes without influencers (prescribed) are held fixed.
+    //
+    let element_constraints: Vec<usize> = element_blocks
+        .iter()
+        .map(|block| {
+            target_volumes
+                .iter()
+                .position(|(target_block, _)| target_block == block)
+                .unwrap()
+        })
+        .collect();
+    for _ in 0..VOLUME_CORRECTION_STEPS {
+        let mut volumes = vec![0.0; target_volumes.len()];
+        let mut nodal_gradients: Vec<Vec<(usize, Vector)>> = vec![vec![]; nodal_coordinates.len()];
+        element_node_connectivity
+            .iter()
+            .zip(element_constraints.iter())
+            .for_each(|(connectivity, &constraint)| {
+                let (volume, gradients) =
+                    calculate_element_volume_gradients(connectivity, nodal_coordinates);
+                volumes[constraint] += volume;
+                connectivity
+                    .iter()
+                    .zip(gradients)
+                    .for_each(|(node, gradient)| {
+                        let gradients = &mut nodal_gradients[node - NODE_NUMBERING_OFFSET];
+                        match gradients.iter_mut().find(|(entry, _)| entry == &constraint) {
+                            Some((_, sum)) => *sum += gradient,
+                            None => gradients.push((constraint, gradient)),
+                        }
+                    })
+            });
+        let residuals: Vec<f64> = target_volumes
+            .iter()
+            .zip(volumes.iter())
+            .map(|((_, target), volume)| target - volume)
+            .collect();
+        if residuals
+            .iter()
+            .zip(target_volumes.iter())
+            .all(|(residual, (_, target))| residual.abs() <= VOLUME_TOLERANCE * target.abs())
+        {
+            break;
+        }
+        let mut gram = vec![vec![0.0; target_volumes.len()]; target_volumes.len()];
+        nodal_gradients
+            .iter()
+            .zip(nodal_influencers.iter())
+            .filter(|(_, influencers)| !influencers.is_empty())
+            .for_each(|(gradients, _)| {
+                gradients.iter().for_each(|(a, gradient_a)| {
+                    gradients
+                        .iter()
+                        .for_each(|(b, gradient_b)| gram[*a][*b] += gradient_a * gradient_b)
+                })
+            });
+        let multipliers = solve_gram_system(gram, residuals);
+        nodal_coordinates
+            .iter_mut()
+            .zip(nodal_gradients.iter().zip(nodal_influencers.iter()))
+            .filter(|(_, (_, influencers))| !influencers.is_empty())
+            .for_each(|(coordinate, (gradients, _))| {
+                gradients.iter().for_each(|(constraint, gradient)| {
+                    *coordinate += gradient * multipliers[*constraint]
+                })
+            });
+    }
+}
+
+fn calculate_element_volume_gradients<const N: usize>(
+    connectivity: &[usize; N],
+    nodal_coordinates: &Coordinates,
+) -> (f64, [Vector; N]) {
+    //
+    // The hexahedral volume is x1.(x2 x x3)/64 in terms of the principal axes, which are sums
+    // of the nodes weighted by their reference coordinates, and the triangular volume is that
+    // of the tetrahedron formed with the origin, X0.(X1 x X2)/6.
+    //
+    match N {
+        HEX => {
+            let (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
+            let x2_x3 = x2.cross(&x3);
+            let x3_x1 = x3.cross(&x1);
+            let x1_x2 = x1.cross(&x2);
+            (
+                &x2_x3 * &x1 / 64.0,
+                from_fn(|node| {
+                    let [xi, eta, zeta] = HEX_REFERENCE_NODES[node];
+                    (&x2_x3 * xi + &x3_x1 * eta + &x1_x2 * zeta) / 64.0
+                }),
+            )
+        }
+        TRI => {
+            let [x0, x1, x2] = [0, 1, 2]
+                .map(|node| &nodal_coordinates[connectivity[node] - NODE_NUMBERING_OFFSET]);
+            let x1_x2 = x1.cross(x2);
+            let gradients = [x1_x2.clone(), x2.cross(x0), x0.cross(x1)];
+            (x0 * &x1_x2 / 6.0, from_fn(|node| &gradients[node] / 6.0))
+        }
+        _ => panic!(),
+    }
+}
+
+fn solve_gram_system(mut gram: Vec<Vec<f64>>, mut residuals: Vec<f64>) -> Vec<f64> {
+    //
+    // Gaussian elimination of the symmetric positive semi-definite Gram matrix of the constraint
+    // gradients, where a vanishing pivot (a block with no free nodes, or a constraint dependent
+    // on the others) leaves the corresponding multiplier at zero.
+    //
+    let size = residuals.len();
+    let scale = (0..size).map(|a| gram[a][a]).fold(0.0, f64::max);
+    let mut active = vec![false; size];
+    for pivot in 0..size {
+        if gram[pivot][pivot] <= VOLUME_TOLERANCE * scale {
+            continue;
+        }
+        active[pivot] = true;
+        for row in pivot + 1..size {
+            let factor = gram[row][pivot] / gram[pivot][pivot];
+            if factor != 0.0 {
+                let pivot_row = gram[pivot].clone();
+                gram[row]
+                    .iter_mut()
+                    .zip(pivot_row.iter())
+                    .skip(pivot)
+                    .for_each(|(entry, pivot_entry)| *entry -= factor * pivot_entry);
+                residuals[row] -= factor * residuals[pivot]
+            }
+        }
+    }
+    let mut multipliers = vec![0.0; size];
+    for pivot in (0..size).rev().filter(|&pivot| active[pivot]) {
+        multipliers[pivot] = (residuals[pivot]
+            - (pivot + 1..size)
+                .map(|column| gram[pivot][column] * multipliers[column])
+                .sum::<f64>())
+            / gram[pivot][pivot]
+    }
+    multipliers
+}
+
+fn calculate_element_volumes_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    #[cfg(feature = "profile")]
+    let time = Instant::now();
+    let element_volumes = map_elements(element_node_connectivity, |connectivity| {
+        let (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
+        &x2.cross(&x3) * &x1 / 64.0
+    });
+    #[cfg(feature = "profile")]
+    println!(
+        "             \x1b[1;93mHexahedron element volumes\x1b[0m {:?}",
+        time.elapsed()
+    );
+    element_volumes
+}
+
+// Knupp 2006
+// https://www.osti.gov/servlets/purl/901967
+// pages 89-104
+
+const HEX_CORNERS: [[usize; NSD + 1]; HEX] = [
+    [0, 1, 3, 4],
+    [1, 2, 0, 5],
+    [2, 3, 1, 6],
+    [3, 0, 2, 7],
+    [4, 7, 5, 0],
+    [5, 4, 6, 1],
+    [6, 5, 7, 2],
+    [7, 6, 4, 3],
+];
+
+const HEX_DIAGONALS: [[usize; 2]; 4] = [[0, 6], [1, 7], [2, 4], [3, 5]];
+
+const HEX_EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [4, 5],
+    [5, 6],
+    [6, 7],
+    [7, 4],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+const TRI_EDGES: [[usize; 2]; 3] = [[0, 1], [1, 2], [2, 0]];
+
+const HEX_FACES: [[usize; 4]; 6] = [
+    [0, 1, 5, 4],
+    [1, 2, 6, 5],
+    [2, 3, 7, 6],
+    [3, 0, 4, 7],
+    [0, 3, 2, 1],
+    [4, 5, 6, 7],
+];
+
+const HEX_REFERENCE_NODES: [[f64; NSD]; HEX] = [
+    [-1.0, -1.0, -1.0],
+    [1.0, -1.0, -1.0],
+    [1.0, 1.0, -1.0],
+    [-1.0, 1.0, -1.0],
+    [-1.0, -1.0, 1.0],
+    [1.0, -1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [-1.0, 1.0, 1.0],
+];
+
+fn determinant(columns: &[Vector; NSD]) -> f64 {
+    &columns[0].cross(&columns[1]) * &columns[2]
+}
+
+fn frobenius_norm_squared(columns: &[Vector; NSD]) -> f64 {
+    columns.iter().map(|column| column * column).sum()
+}
+
+fn adjugate_frobenius_norm_squared(columns: &[Vector; NSD]) -> f64 {
+    [
+        columns[1].cross(&columns[2]),
+        columns[2].cross(&columns[0]),
+        columns[0].cross(&columns[1]),
+    ]
+    .iter()
+    .map(|row| row * row)
+    .sum()
+}
+
+fn metric_tensor_frobenius_norm_squared(columns: &[Vector; NSD]) -> f64 {
+    columns
+        .iter()
+        .flat_map(|column_i| {
+            columns
+                .iter()
+                .map(move |column_j| (column_i * column_j).powi(2))
+        })
+        .sum()
+}
+
+fn calculate_element_corner_edges_hex<const N: usize>(
+    connectivity: &[usize; N],
+    nodal_coordinates: &Coordinates,
+) -> [[Vector; NSD]; HEX] {
+    from_fn(|corner| {
+        let node = &nodal_coordinates[connectivity[HEX_CORNERS[corner][0]] - NODE_NUMBERING_OFFSET];
+        from_fn(|edge| {
+            &nodal_coordinates[connectivity[HEX_CORNERS[corner][edge + 1]] - NODE_NUMBERING_OFFSET]
+                - node
+        })
+    })
+}
+
+fn calculate_element_lengths_hex<const N: usize, const M: usize>(
+    connectivity: &[usize; N],
+    nodal_coordinates: &Coordinates,
+    pairs: &[[usize; 2]; M],
+) -> [f64; M] {
+    from_fn(|index| {
+        (&nodal_coordinates[connectivity[pairs[index][1]] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[pairs[index][0]] - NODE_NUMBERING_OFFSET])
+            .norm()
+    })
+}
+
+fn calculate_element_jacobian_hex<const N: usize>(
+    connectivity: &[usize; N],
+    nodal_coordinates: &Coordinates,
+    [xi, eta, zeta]: [f64; NSD],
+) -> f64 {
+    let mut columns = from_fn(|_| Vector::zero());
+    connectivity
+        .iter()
+        .zip(HEX_REFERENCE_NODES.iter())
+        .for_each(|(node, [xi_a, eta_a, zeta_a])| {
+            let coordinate = &nodal_coordinates[node - NODE_NUMBERING_OFFSET];
+            columns[0] += coordinate * (xi_a * (1.0 + eta_a * eta) * (1.0 + zeta_a * zeta) / 8.0);
+            columns[1] += coordinate * (eta_a * (1.0 + xi_a * xi) * (1.0 + zeta_a * zeta) / 8.0);
+            columns[2] += coordinate * (zeta_a * (1.0 + xi_a * xi) * (1.0 + eta_a * eta) / 8.0);
+        });
+    determinant(&columns)
+}
+
+fn calculate_element_minimum_scaled_jacobian_hex<const N: usize>(
+    connectivity: &[usize; N],
+    nodal_coordinates: &Coordinates,
+) -> f64 {
+    //
+    // Corners with a collapsed edge are degenerate and have a scaled Jacobian of zero.
+    //
+    calculate_element_corner_edges_hex(connectivity, nodal_coordinates)
+        .iter()
+        .map(|edges| {
+            let [u, v, w] = edges.each_ref().map(|edge| edge.norm());
+            if u > 0.0 && v > 0.0 && w > 0.0 {
+                determinant(edges) / u / v / w
+            } else {
+                0.0
+            }
+        })
+        .reduce(f64::min)
+        .unwrap()
+}
+
+fn calculate_patch_minimum_scaled_jacobian_hex<const N: usize>(
+    elements: &[usize],
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> f64 {
+    elements
+        .iter()
+        .map(|element| {
+            calculate_element_minimum_scaled_jacobian_hex(
+                &element_node_connectivity[element - ELEMENT_NUMBERING_OFFSET],
+                nodal_coordinates,
+            )
+        })
+        .reduce(f64::min)
+        .unwrap_or(f64::INFINITY)
+}
+
+fn optimize_nodal_patches_hex<const N: usize>(
+    target: f64,
+    element_node_connectivity: &Connectivity<N>,
+    node_element_connectivity: &VecConnectivity,
+    nodal_influencers: &VecConnectivity,
+    nodal_constraints: &[Vec<Vector>],
+    reference_surface: Option<&ReferenceSurface>,
+    nodal_coordinates: &mut Coordinates,
+) -> bool {
+    //
+    // Each free node is moved to increase the minimum scaled Jacobian of its patch of elements,
+    // first toward the centroid of its influencers, then along the finite difference gradient
+    // with a backtracking line search. Moves that do not improve the patch are rejected,
+    // so the minimum scaled Jacobian of each patch never decreases. Exterior nodes are kept
+    // on the reference surface if one is given, and otherwise their moves along the constrained
+    // directions (the normals of the adjacent exterior faces) are removed.
+    //
+    let mut improved = false;
+    node_element_connectivity
+        .iter()
+        .zip(nodal_influencers.iter().zip(nodal_constraints.iter()))
+        .enumerate()
+        .filter(|(_, (_, (influencers, _)))| !influencers.is_empty())
+        .for_each(|(node, (elements, (influencers, constraints)))| {
+            let mut quality = calculate_patch_minimum_scaled_jacobian_hex(
+                elements,
+                element_node_connectivity,
+                nodal_coordinates,
+            );
+            if quality >= target {
+                return;
+            }
+            let centroid = influencers
+                .iter()
+                .map(|influencer| nodal_coordinates[influencer - NODE_NUMBERING_OFFSET].clone())
+                .sum::<Coordinate>()
+                / (influencers.len() as f64);
+            let length = influencers
+                .iter()
+                .map(|influencer| {
+                    (&nodal_coordinates[influencer - NODE_NUMBERING_OFFSET]
+                        - &nodal_coordinates[node])
+                        .norm()
+                })
+                .sum::<f64>()
+                / (influencers.len() as f64);
+            let original = nodal_coordinates[node].clone();
+            let constrain = |coordinate: Coordinate| {
+                if constraints.is_empty() {
+                    coordinate
+                } else if let Some(reference_surface) = reference_surface {
+                    reference_surface.closest_point(&coordinate)
+                } else {
+                    let displacement = &coordinate - &original;
+                    constraints
+                        .iter()
+                        .fold(coordinate, |coordinate, constraint| {
+                            coordinate - constraint * (constraint * &displacement)
+                        })
+                }
+            };
+            let centroid = constrain(centroid);
+            let mut evaluate = |coordinate: &Coordinate| {
+                nodal_coordinates[node] = coordinate.clone();
+                calculate_patch_minimum_scaled_jacobian_hex(
+                    elements,
+                    element_node_connectivity,
+                    nodal_coordinates,
+                )
+            };
+            let mut best = original.clone();
+            let trial = evaluate(&centroid);
+            if trial > quality {
+                quality = trial;
+                best = centroid;
+            }
+            let step = 1e-3 * length;
+            let mut gradient = Vector::zero();
+            (0..NSD).for_each(|direction| {
+                let mut forward = best.clone();
+                let mut backward = best.clone();
+                forward[direction] += step;
+                backward[direction] -= step;
+                gradient[direction] =
+                    (evaluate(&constrain(forward)) - evaluate(&constrain(backward))) / (2.0 * step);
+            });
+            let norm = gradient.norm();
+            if norm > 0.0 {
+                let direction = gradient / norm;
+                let mut distance = 0.5 * length;
+                while distance > step {
+                    let candidate = constrain(&direction * distance + &best);
+                    let trial = evaluate(&candidate);
+                    if trial > quality {
+                        quality = trial;
+                        best = candidate;
+                        break;
+                    }
+                    distance *= 0.5;
+                }
+            }
+            if best != original {
+                improved = true;
+            }
+            nodal_coordinates[node] = best;
+        });
+    improved
+}
+
+fn calculate_exterior_constraints<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Vec<Vec<Vector>> {
+    //
+    // The constrained directions of each exterior node are an orthonormal basis spanning the
+    // normals of its adjacent exterior faces, starting from their average, where normals within
+    // 30 degrees of the span so far are considered the same (sin 30 = 0.5). A node on a face
+    // then moves in the tangent plane, a node on an edge along the edge, and a corner is fixed.
+    //
+    let mut face_normals: Vec<Vec<Vector>> = vec![vec![]; nodal_coordinates.len()];
+    calculate_boundary_entities(element_node_connectivity)
+        .iter()
+        .for_each(|face| {
+            let [x0, x1, x2, x3] =
+                from_fn(|index| &nodal_coordinates[face[index] - NODE_NUMBERING_OFFSET]);
+            let mut normal = (x2 - x0).cross(&(x3 - x1));
+            if normal.norm() > 0.0 {
+                normal.normalize();
+                face.iter().for_each(|node| {
+                    face_normals[node - NODE_NUMBERING_OFFSET].push(normal.clone())
+                })
+            }
+        });
+    face_normals
+        .into_iter()
+        .map(|normals| {
+            let mut constraints: Vec<Vector> = vec![];
+            let average = normals.iter().cloned().sum::<Vector>();
+            std::iter::once(average).chain(normals).for_each(|normal| {
+                let residual = constraints
+                    .iter()
+                    .fold(normal.clone(), |residual, constraint| {
+                        &residual - constraint * (constraint * &normal)
+                    });
+                let norm = residual.norm();
+                if norm > 0.5 * normal.norm() && constraints.len() < NSD {
+                    constraints.push(residual / norm)
+                }
+            });
+            constraints
+        })
+        .collect()
+}
+
+fn calculate_maximum_conditions_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        calculate_element_corner_edges_hex(connectivity, nodal_coordinates)
+            .iter()
+            .map(|columns| {
+                let jacobian = determinant(columns);
+                if jacobian > 0.0 {
+                    (frobenius_norm_squared(columns) * adjugate_frobenius_norm_squared(columns))
+                        .sqrt()
+                        / jacobian
+                        / 3.0
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .reduce(f64::max)
+            .unwrap()
+    })
+}
+
+fn calculate_diagonal_ratios_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        let diagonals =
+            calculate_element_lengths_hex(connectivity, nodal_coordinates, &HEX_DIAGONALS);
+        diagonals.into_iter().reduce(f64::min).unwrap()
+            / diagonals.into_iter().reduce(f64::max).unwrap()
+    })
+}
+
+fn calculate_distortions_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    let gauss_point = 1.0 / 3.0_f64.sqrt();
+    let gauss_points: [[f64; NSD]; HEX] =
+        from_fn(|index| HEX_REFERENCE_NODES[index].map(|entry| entry * gauss_point));
+    let reference_volume = 8.0;
+    map_elements(element_node_connectivity, |connectivity| {
+        let jacobians_gauss = gauss_points
+            .map(|point| calculate_element_jacobian_hex(connectivity, nodal_coordinates, point));
+        let jacobians_nodes = HEX_REFERENCE_NODES
+            .map(|point| calculate_element_jacobian_hex(connectivity, nodal_coordinates, point));
+        jacobians_gauss
+            .into_iter()
+            .chain(jacobians_nodes)
+            .reduce(f64::min)
+            .unwrap()
+            * reference_volume
+            / jacobians_gauss.into_iter().sum::<f64>()
+    })
+}
+
+fn calculate_maximum_oddys_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        let (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
+        calculate_element_corner_edges_hex(connectivity, nodal_coordinates)
+            .into_iter()
+            .chain([[x1.clone(), x2.clone(), x3.clone()]])
+            .map(|columns| {
+                let jacobian = determinant(&columns);
+                if jacobian > 0.0 {
+                    (metric_tensor_frobenius_norm_squared(&columns)
+                        - frobenius_norm_squared(&columns).powi(2) / 3.0)
+                        / jacobian.powf(4.0 / 3.0)
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .reduce(f64::max)
+            .unwrap()
+    })
+}
+
+fn calculate_relative_sizes_squared_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    let volumes = calculate_element_volumes_hex(element_node_connectivity, nodal_coordinates);
+    let average_volume = volumes.mean().unwrap_or(0.0);
+    volumes
+        .iter()
+        .map(|volume| {
+            let size = volume / average_volume;
+            if size > 0.0 {
+                size.min(1.0 / size).powi(2)
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn calculate_minimum_shapes_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        calculate_element_corner_edges_hex(connectivity, nodal_coordinates)
+            .iter()
+            .map(|columns| {
+                let jacobian = determinant(columns);
+                if jacobian > 0.0 {
+                    3.0 * jacobian.powf(2.0 / 3.0) / frobenius_norm_squared(columns)
+                } else {
+                    0.0
+                }
+            })
+            .reduce(f64::min)
+            .unwrap()
+    })
+}
+
+fn calculate_minimum_shears_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        calculate_element_minimum_scaled_jacobian_hex(connectivity, nodal_coordinates).max(0.0)
+    })
+}
+
+fn calculate_stretches_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        3.0_f64.sqrt()
+            * calculate_element_lengths_hex(connectivity, nodal_coordinates, &HEX_EDGES)
+                .into_iter()
+                .reduce(f64::min)
+                .unwrap()
+            / calculate_element_lengths_hex(connectivity, nodal_coordinates, &HEX_DIAGONALS)
+                .into_iter()
+                .reduce(f64::max)
+                .unwrap()
+    })
+}
+
+fn calculate_maximum_tapers_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        let x = |node: usize| &nodal_coordinates[connectivity[node] - NODE_NUMBERING_OFFSET];
+        let (x1, x2, x3) = calculate_element_principal_axes(connectivity, nodal_coordinates);
+        let x12 = (x(0) - x(1) + x(2) - x(3) + x(4) - x(5) + x(6) - x(7)).norm();
+        let x13 = (x(0) - x(1) - x(2) + x(3) - x(4) + x(5) + x(6) - x(7)).norm();
+        let x23 = (x(0) - x(2) + x(1) - x(3) - x(4) - x(5) + x(6) + x(7)).norm();
+        let (l1, l2, l3) = (x1.norm(), x2.norm(), x3.norm());
+        [x12 / l1.min(l2), x13 / l1.min(l3), x23 / l2.min(l3)]
+            .into_iter()
+            .reduce(f64::max)
+            .unwrap()
+    })
+}
+
+fn calculate_maximum_warpages_hex<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    if N != HEX {
+        panic!("Only implemented for hexahedral elements.")
+    }
+    map_elements(element_node_connectivity, |connectivity| {
+        HEX_FACES
+            .iter()
+            .map(|face| {
+                let edges: [Vector; 4] = from_fn(|index| {
+                    &nodal_coordinates[connectivity[face[(index + 1) % 4]] - NODE_NUMBERING_OFFSET]
+                        - &nodal_coordinates[connectivity[face[index]] - NODE_NUMBERING_OFFSET]
+                });
+                let normals: [Vector; 4] =
+                    from_fn(|index| edges[(index + 3) % 4].cross(&edges[index]));
+                if normals.iter().any(|normal| normal.norm() <= 0.0) {
+                    f64::INFINITY
+                } else {
+                    let normals = normals.map(|normal| normal.normalized());
+                    1.0 - (&normals[0] * &normals[2])
+                        .min(&normals[1] * &normals[3])
+                        .powi(3)
+                }
+            })
+            .reduce(f64::max)
+            .unwrap()
+    })
 }
@@ -1,7 +1,8 @@
 use super::{
     super::py::{IntoFoo, PyCoordinates, PyIntermediateError},
-    Blocks, Connectivity, FiniteElementMethods, HEX, Smoothing, TRI, finite_element_data_from_inp,
-    write_finite_elements_metrics, write_finite_elements_to_abaqus,
+    Blocks, Connectivity, FiniteElementMethods, HEX, Metric, Smoothing, SmoothingOptions, TRI,
+    finite_element_data_from_inp, write_finite_elements_metrics,
+    write_finite_elements_metrics_selected, write_finite_elements_to_abaqus,
     write_finite_elements_to_exodus, write_finite_elements_to_mesh, write_finite_elements_to_vtk,
 };
 use pyo3::prelude::*;
@@ -55,7 +56,8 @@ impl HexahedralFiniteElements {
         ))
     }
     /// Smooths the nodal coordinates according to the provided smoothing method.
-    #[pyo3(signature = (method="Taubin", hierarchical=false, iterations=10, pass_band=0.1, scale=0.6307))]
+    #[pyo3(signature = (method="Taubin", hierarchical=false, iterations=10, pass_band=0.1, scale=0.6307, preserve_volume=false, alpha=0.1, beta=0.6))]
+    #[allow(clippy::too_many_arguments)]
     pub fn smooth(
         &mut self,
         method: &str,
@@ -63,6 +65,9 @@ impl HexahedralFiniteElements {
         iterations: usize,
         pass_band: f64,
         scale: f64,
+        preserve_volume: bool,
+        alpha: f64,
+        beta: f64,
     ) -> Result<(), PyIntermediateError> {
         let mut finite_elements = super::HexahedralFiniteElements::from_data(
             self.element_blocks.clone(),
@@ -75,13 +80,24 @@ impl HexahedralFiniteElements {
             finite_elements.nodal_hierarchy()?;
         }
         finite_elements.nodal_influencers();
+        let options = SmoothingOptions {
+            volume_preserving: preserve_volume,
+            ..Default::default()
+        };
         match method {
+            "HC" | "hc" => {
+                finite_elements.smooth_with(
+                    Smoothing::HcLaplacian(iterations, alpha, beta, scale),
+                    &options,
+                )?;
+            }
             "Gauss" | "gauss" | "Gaussian" | "gaussian" | "Laplacian" | "Laplace" | "laplacian"
             | "laplace" => {
-                finite_elements.smooth(Smoothing::Laplacian(iterations, scale))?;
+                finite_elements.smooth_with(Smoothing::Laplacian(iterations, scale), &options)?;
             }
             "Taubin" | "taubin" => {
-                finite_elements.smooth(Smoothing::Taubin(iterations, pass_band, scale))?;
+                finite_elements
+                    .smooth_with(Smoothing::Taubin(iterations, pass_band, scale), &options)?;
             }
             _ => return Err(format!("Invalid smoothing method {} specified.", method))?,
         }
@@ -118,12 +134,30 @@ impl HexahedralFiniteElements {
         )?)
     }
     /// Writes the finite elements quality metrics to a new file.
-    pub fn write_metrics(&self, file_path: &str) -> Result<(), PyIntermediateError> {
-        Ok(write_finite_elements_metrics(
-            file_path,
-            &self.element_node_connectivity,
-            &self.nodal_coordinates.as_foo(),
-        )?)
+    #[pyo3(signature = (file_path, names=None))]
+    pub fn write_metrics(
+        &self,
+        file_path: &str,
+        names: Option<Vec<String>>,
+    ) -> Result<(), PyIntermediateError> {
+        if let Some(names) = names {
+            let metrics = names
+                .iter()
+                .map(|name| Metric::try_from(name.as_str()))
+                .collect::<Result<Vec<Metric>, String>>()?;
+            Ok(write_finite_elements_metrics_selected(
+                file_path,
+                &metrics,
+                &self.element_node_connectivity,
+                &self.nodal_coordinates.as_foo(),
+            )?)
+        } else {
+            Ok(write_finite_elements_metrics(
+                file_path,
+                &self.element_node_connectivity,
+                &self.nodal_coordinates.as_foo(),
+            )?)
+        }
     }
     /// Writes the finite elements data to a new VTK file.
     pub fn write_vtk(&self, file_path: &str) -> Result<(), PyIntermediateError> {
@@ -163,7 +197,8 @@ impl TriangularFiniteElements {
         ))
     }
     /// Smooths the nodal coordinates according to the provided smoothing method.
-    #[pyo3(signature = (method="Taubin", hierarchical=false, iterations=10, pass_band=0.1, scale=0.6307))]
+    #[pyo3(signature = (method="Taubin", hierarchical=false, iterations=10, pass_band=0.1, scale=0.6307, preserve_volume=false, alpha=0.1, beta=0.6))]
+    #[allow(clippy::too_many_arguments)]
     pub fn smooth(
         &mut self,
         method: &str,
@@ -171,6 +206,9 @@ impl TriangularFiniteElements {
         iterations: usize,
         pass_band: f64,
         scale: f64,
+        preserve_volume: bool,
+        alpha: f64,
+        beta: f64,
     ) -> Result<(), PyIntermediateError> {
         let mut finite_elements = super::TriangularFiniteElements::from_data(
             self.element_blocks.clone(),
@@ -183,13 +221,24 @@ impl TriangularFiniteElements {
             finite_elements.nodal_hierarchy()?;
         }
         finite_elements.nodal_influencers();
+        let options = SmoothingOptions {
+            volume_preserving: preserve_volume,
+            ..Default::default()
+        };
         match method {
+            "HC" | "hc" => {
+                finite_elements.smooth_with(
+                    Smoothing::HcLaplacian(iterations, alpha, beta, scale),
+                    &options,
+                )?;
+            }
             "Gauss" | "gauss" | "Gaussian" | "gaussian" | "Laplacian" | "Laplace" | "laplacian"
             | "laplace" => {
-                finite_elements.smooth(Smoothing::Laplacian(iterations, scale))?;
+                finite_elements.smooth_with(Smoothing::Laplacian(iterations, scale), &options)?;
             }
             "Taubin" | "taubin" => {
-                finite_elements.smooth(Smoothing::Taubin(iterations, pass_band, scale))?;
+                finite_elements
+                    .smooth_with(Smoothing::Taubin(iterations, pass_band, scale), &options)?;
             }
             _ => return Err(format!("Invalid smoothing method {} specified.", method))?,
         }
@@ -226,12 +275,30 @@ impl TriangularFiniteElements {
         )?)
     }
     /// Writes the finite elements quality metrics to a new file.
-    pub fn write_metrics(&self, file_path: &str) -> Result<(), PyIntermediateError> {
-        Ok(write_finite_elements_metrics(
-            file_path,
-            &self.element_node_connectivity,
-            &self.nodal_coordinates.as_foo(),
-        )?)
+    #[pyo3(signature = (file_path, names=None))]
+    pub fn write_metrics(
+        &self,
+        file_path: &str,
+        names: Option<Vec<String>>,
+    ) -> Result<(), PyIntermediateError> {
+        if let Some(names) = names {
+            let metrics = names
+                .iter()
+                .map(|name| Metric::try_from(name.as_str()))
+                .collect::<Result<Vec<Metric>, String>>()?;
+            Ok(write_finite_elements_metrics_selected(
+                file_path,
+                &metrics,
+                &self.element_node_connectivity,
+                &self.nodal_coordinates.as_foo(),
+            )?)
+        } else {
+            Ok(write_finite_elements_metrics(
+                file_path,
+                &self.element_node_connectivity,
+                &self.nodal_coordinates.as_foo(),
+            )?)
+        }
     }
     /// Writes the finite elements data to a new VTK file.
     pub fn write_vtk(&self, file_path: &str) -> Result<(), PyIntermediateError> {
@@ -1,12 +1,18 @@
 use crate::fem::calculate_element_volumes_hex;
 
 use super::{
-    Blocks, Connectivity, Coordinates, FiniteElementMethods, HEX, HexahedralFiniteElements, Nodes,
-    Smoothing, TRI, VecConnectivity, automesh_header, calculate_maximum_edge_ratios,
-    calculate_maximum_skews, calculate_minimum_scaled_jacobians, metrics_headers,
-    tri::{calculate_element_areas_tri, calculate_minimum_angles_tri},
+    Blocks, Connectivity, Convergence, Coordinate, Coordinates, FiniteElementMethods,
+    FiniteElementSpecifics, HEX, HexahedralFiniteElements, Metric, Nodes, Smoothing,
+    SmoothingOptions, TRI, TriangularFiniteElements, VecConnectivity, automesh_header,
+    calculate_maximum_edge_ratios, calculate_maximum_skews, calculate_minimum_scaled_jacobians,
+    metrics_headers,
+    tri::{
+        calculate_aspect_ratios_tri, calculate_conditions_tri, calculate_element_areas_tri,
+        calculate_maximum_angles_tri, calculate_maximum_normal_deviations_tri,
+        calculate_minimum_angles_tri, calculate_radius_ratios_tri, calculate_shapes_tri,
+    },
 };
-use conspire::math::{Tensor, TensorVec};
+use conspire::math::{Tensor, TensorArray, TensorVec};
 
 const DEG_TO_RAD: f64 = std::f64::consts::PI / 180.0;
 const RAD_TO_DEG: f64 = 1.0 / DEG_TO_RAD;
@@ -3014,6 +3020,101 @@ fn valence_3_and_4_noised() {
         });
 }
 
+#[test]
+fn hexahedral_quality_metrics() {
+    // A unit cube and a 2x1x1 box, with gold values derived by hand
+    // from the definitions in Knupp 2006, https://www.osti.gov/servlets/purl/901967
+    let finite_elements = HexahedralFiniteElements::from_data(
+        vec![1, 1],
+        vec![[1, 2, 3, 4, 5, 6, 7, 8], [9, 10, 11, 12, 13, 14, 15, 16]],
+        Coordinates::new(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 1.0, 1.0],
+            [2.0, 0.0, 0.0],
+            [4.0, 0.0, 0.0],
+            [4.0, 1.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [2.0, 0.0, 1.0],
+            [4.0, 0.0, 1.0],
+            [4.0, 1.0, 1.0],
+            [2.0, 1.0, 1.0],
+        ]),
+    );
+    let gold = [
+        (Metric::Condition, [1.0, 1.5_f64.sqrt()]),
+        (Metric::DiagonalRatio, [1.0, 1.0]),
+        (Metric::Distortion, [1.0, 1.0]),
+        (Metric::Oddy, [0.0, 6.0 / 2.0_f64.powf(4.0 / 3.0)]),
+        (Metric::RelativeSizeSquared, [4.0 / 9.0, 9.0 / 16.0]),
+        (Metric::Shape, [1.0, 2.0_f64.powf(2.0 / 3.0) / 2.0]),
+        (Metric::Shear, [1.0, 1.0]),
+        (Metric::Stretch, [1.0, 0.5_f64.sqrt()]),
+        (Metric::Taper, [0.0, 0.0]),
+        (Metric::Warpage, [0.0, 0.0]),
+    ];
+    gold.iter().for_each(|(metric, values)| {
+        finite_elements
+            .calculate_metric(metric)
+            .unwrap()
+            .iter()
+            .zip(values.iter())
+            .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON))
+    });
+    assert_eq!(
+        finite_elements.calculate_metric(&Metric::MinimumAngle),
+        Err("Metric minimum angle is not available for this element type".to_string())
+    );
+    assert_eq!(
+        Metric::try_from("relative_size"),
+        Ok(Metric::RelativeSizeSquared)
+    );
+    assert_eq!(
+        Metric::try_from("Maximum Edge Ratio"),
+        Ok(Metric::EdgeRatio)
+    );
+    assert_eq!(
+        Metric::try_from("foo"),
+        Err("Invalid metric foo specified".to_string())
+    );
+}
+
+#[test]
+fn hexahedral_quality_metrics_degenerate() {
+    // A unit square flattened into a hexahedron, whose corners all have a collapsed edge
+    let finite_elements = HexahedralFiniteElements::from_data(
+        vec![1],
+        vec![[1, 2, 3, 4, 1, 2, 3, 4]],
+        Coordinates::new(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ]),
+    );
+    let gold = [
+        (Metric::Condition, f64::INFINITY),
+        (Metric::Oddy, f64::INFINITY),
+        (Metric::ScaledJacobian, 0.0),
+        (Metric::Shape, 0.0),
+        (Metric::Shear, 0.0),
+        (Metric::Warpage, f64::INFINITY),
+    ];
+    gold.iter().for_each(|(metric, value)| {
+        assert_eq!(
+            finite_elements.calculate_metric(metric).unwrap()[0],
+            *value,
+            "{}",
+            metric.header()
+        )
+    });
+}
+
 #[test]
 fn triangular_unit_tests() {
     // Reference: https://autotwin.github.io/automesh/cli/metrics.html#triangular-unit-tests
@@ -3220,6 +3321,83 @@ fn triangular_unit_tests() {
         .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON,));
 }
 
+#[test]
+fn triangular_quality_metrics() {
+    // An equilateral triangle of unit side length in the xy-plane,
+    // and a right isosceles triangle of unit leg length in the xz-plane
+    // hinged to the first along their shared edge.
+    let element_node_connectivity = vec![[1, 2, 3], [2, 1, 4]];
+    let nodal_coordinates = Coordinates::new(&[
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.5, 3.0_f64.sqrt() / 2.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ]);
+    let maximum_angles_gold = [60.0, 90.0];
+    let aspect_ratios_gold = [1.0, (2.0_f64.sqrt() + 1.0) / 3.0_f64.sqrt()];
+    let radius_ratios_gold = [1.0, (2.0_f64.sqrt() + 1.0) / 2.0];
+    let conditions_gold = [1.0, 2.0 / 3.0_f64.sqrt()];
+    let shapes_gold = [1.0, 3.0_f64.sqrt() / 2.0];
+    let normal_deviations_gold = [90.0, 90.0];
+    calculate_maximum_angles_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(maximum_angles_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated * RAD_TO_DEG - gold).abs() < EPSILON));
+    calculate_aspect_ratios_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(aspect_ratios_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON));
+    calculate_radius_ratios_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(radius_ratios_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON));
+    calculate_conditions_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(conditions_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON));
+    calculate_shapes_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(shapes_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated - gold).abs() < EPSILON));
+    calculate_maximum_normal_deviations_tri(&element_node_connectivity, &nodal_coordinates)
+        .iter()
+        .zip(normal_deviations_gold.iter())
+        .for_each(|(calculated, gold)| assert!((calculated * RAD_TO_DEG - gold).abs() < EPSILON));
+}
+
+#[test]
+fn triangular_metrics_default_and_selected() {
+    // The default triangular metrics include all of the triangular metrics,
+    // and the selected metrics are written in the order given.
+    let finite_elements = TriangularFiniteElements::from_data(
+        vec![1],
+        vec![[1, 2, 3]],
+        Coordinates::new(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]),
+    );
+    let file_path = std::env::temp_dir().join("automesh_triangular_metrics.csv");
+    let file_path = file_path.to_str().unwrap();
+    finite_elements.write_metrics(file_path).unwrap();
+    let contents = std::fs::read_to_string(file_path).unwrap();
+    let mut lines = contents.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "maximum edge ratio,minimum scaled jacobian,maximum skew,element area,minimum angle,\
+        maximum angle,aspect ratio,radius ratio,maximum condition,minimum shape,\
+        maximum normal deviation"
+    );
+    assert_eq!(lines.next().unwrap().split(',').count(), 11);
+    finite_elements
+        .write_metrics_selected(file_path, &[Metric::AspectRatio, Metric::NormalDeviation])
+        .unwrap();
+    let contents = std::fs::read_to_string(file_path).unwrap();
+    let mut lines = contents.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "aspect ratio,maximum normal deviation"
+    );
+    assert_eq!(lines.next().unwrap().split(',').count(), 2);
+}
+
 #[test]
 fn metrics_headers_test() {
     // The headers for metrics files are unique depending on if the
@@ -3233,9 +3411,10 @@ fn metrics_headers_test() {
     assert_eq!(hex_header_gold, hex_header_result);
 
     // Test TRI headers
-    let tri_header_gold =
-        "maximum edge ratio,minimum scaled jacobian,maximum skew,element area,minimum angle\n"
-            .to_string();
+    let tri_header_gold = "maximum edge ratio,minimum scaled jacobian,maximum skew,element area,\
+        minimum angle,maximum angle,aspect ratio,radius ratio,maximum condition,\
+        minimum shape,maximum normal deviation\n"
+        .to_string();
     let tri_header_result = metrics_headers::<TRI>();
     assert_eq!(tri_header_gold, tri_header_result);
 
@@ -3266,3 +3445,695 @@ fn metrics_headers_test() {
 //     let tri_format_result = metrics_format::<TRI>();
 //     assert_eq!(tri_format_gold, tri_format_result);
 // }
+
+fn hexahedral_grid(
+    elements: [usize; 3],
+    block: impl Fn(usize, usize, usize) -> u8,
+) -> HexahedralFiniteElements {
+    let [nelx, nely, nelz] = elements;
+    let node = |i: usize, j: usize, k: usize| 1 + i + (nelx + 1) * (j + (nely + 1) * k);
+    let nodal_coordinates = Coordinates::new(
+        &(0..=nelz)
+            .flat_map(|k| {
+                (0..=nely)
+                    .flat_map(move |j| (0..=nelx).map(move |i| [i as f64, j as f64, k as f64]))
+            })
+            .collect::<Vec<[f64; 3]>>(),
+    );
+    let (element_blocks, element_node_connectivity): (Blocks, Connectivity<HEX>) = (0..nelz)
+        .flat_map(|k| (0..nely).flat_map(move |j| (0..nelx).map(move |i| (i, j, k))))
+        .map(|(i, j, k)| {
+            (
+                block(i, j, k),
+                [
+                    node(i, j, k),
+                    node(i + 1, j, k),
+                    node(i + 1, j + 1, k),
+                    node(i, j + 1, k),
+                    node(i, j, k + 1),
+                    node(i + 1, j, k + 1),
+                    node(i + 1, j + 1, k + 1),
+                    node(i, j + 1, k + 1),
+                ],
+            )
+        })
+        .unzip();
+    let mut finite_elements = HexahedralFiniteElements::from_data(
+        element_blocks,
+        element_node_connectivity,
+        nodal_coordinates,
+    );
+    finite_elements.node_element_connectivity().unwrap();
+    finite_elements.node_node_connectivity().unwrap();
+    finite_elements.nodal_influencers();
+    finite_elements
+}
+
+fn closed_cube_surfaces(offsets: &[[f64; 3]]) -> TriangularFiniteElements {
+    // Each offset is a closed and outward-oriented unit cube surface in its own block.
+    let corners = (0..8).map(|corner| [corner % 2, (corner / 2) % 2, corner / 4]);
+    let nodal_coordinates = Coordinates::new(
+        &offsets
+            .iter()
+            .flat_map(|offset| {
+                corners.clone().map(|[i, j, k]| {
+                    [
+                        offset[0] + i as f64,
+                        offset[1] + j as f64,
+                        offset[2] + k as f64,
+                    ]
+                })
+            })
+            .collect::<Vec<[f64; 3]>>(),
+    );
+    let triangles = [
+        [1, 3, 4],
+        [1, 4, 2],
+        [5, 6, 8],
+        [5, 8, 7],
+        [1, 2, 6],
+        [1, 6, 5],
+        [3, 7, 8],
+        [3, 8, 4],
+        [1, 5, 7],
+        [1, 7, 3],
+        [2, 4, 8],
+        [2, 8, 6],
+    ];
+    let (element_blocks, element_node_connectivity): (Blocks, Connectivity<TRI>) = (0..offsets
+        .len())
+        .flat_map(|cube| {
+            triangles
+                .iter()
+                .map(move |triangle| (cube as u8 + 1, triangle.map(|node| node + 8 * cube)))
+        })
+        .unzip();
+    let mut finite_elements = TriangularFiniteElements::from_data(
+        element_blocks,
+        element_node_connectivity,
+        nodal_coordinates,
+    );
+    finite_elements.node_element_connectivity().unwrap();
+    finite_elements.node_node_connectivity().unwrap();
+    finite_elements.nodal_influencers();
+    finite_elements
+}
+
+#[test]
+fn volume_preserving_smoothing() {
+    // Two blocks of hexes share an interface, so each block volume is only preserved
+    // if the constraints move the shared nodes consistently for both blocks.
+    let smoothed_volumes = |volume_preserving: bool| {
+        let mut finite_elements = hexahedral_grid([4, 2, 2], |i, _, _| 1 + (i > 1) as u8);
+        finite_elements
+            .smooth_with(
+                Smoothing::Laplacian(10, SMOOTHING_SCALE),
+                &SmoothingOptions {
+                    volume_preserving,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(
+            calculate_minimum_scaled_jacobians(
+                finite_elements.get_element_node_connectivity(),
+                finite_elements.get_nodal_coordinates()
+            )
+            .iter()
+            .all(|minimum_scaled_jacobian| minimum_scaled_jacobian > &0.0)
+        );
+        finite_elements.calculate_block_volumes().unwrap()
+    };
+    smoothed_volumes(false)
+        .iter()
+        .for_each(|(_, volume)| assert!(volume < &(8.0 - 1.0)));
+    let volumes = smoothed_volumes(true);
+    assert_eq!(volumes.len(), 2);
+    volumes
+        .iter()
+        .for_each(|(_, volume)| assert!((volume - 8.0).abs() < 1e-10));
+}
+
+#[test]
+fn volume_preserving_smoothing_tri() {
+    // The enclosed volume of each closed surface is preserved, wherever the origin is,
+    // and an open surface has no enclosed volume to preserve.
+    let mut finite_elements = closed_cube_surfaces(&[[2.0, 3.0, 4.0], [-5.0, 0.0, 1.0]]);
+    let options = SmoothingOptions {
+        volume_preserving: true,
+        ..Default::default()
+    };
+    finite_elements
+        .smooth_with(Smoothing::Laplacian(10, SMOOTHING_SCALE), &options)
+        .unwrap();
+    let volumes = finite_elements.calculate_block_volumes().unwrap();
+    assert_eq!(volumes.len(), 2);
+    volumes
+        .iter()
+        .for_each(|(_, volume)| assert!((volume - 1.0).abs() < 1e-10));
+    let mut finite_elements = closed_cube_surfaces(&[[0.0, 0.0, 0.0]]);
+    finite_elements
+        .smooth(Smoothing::Laplacian(10, SMOOTHING_SCALE))
+        .unwrap();
+    assert!(finite_elements.calculate_block_volumes().unwrap()[0].1 < 1.0);
+    let mut finite_elements = TriangularFiniteElements::from_data(
+        vec![1; 11],
+        finite_elements.get_element_node_connectivity()[1..].to_vec(),
+        finite_elements.get_nodal_coordinates().clone(),
+    );
+    assert!(finite_elements.calculate_block_volumes().is_err());
+    finite_elements.node_element_connectivity().unwrap();
+    finite_elements.node_node_connectivity().unwrap();
+    finite_elements.nodal_influencers();
+    assert!(
+        finite_elements
+            .smooth_with(Smoothing::Laplacian(10, SMOOTHING_SCALE), &options)
+            .is_err()
+    );
+}
+
+fn noisy_grid() -> HexahedralFiniteElements {
+    let mut finite_elements = hexahedral_grid([3, 3, 3], |_, _, _| 1);
+    finite_elements
+        .get_nodal_coordinates_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(node, coordinates)| {
+            coordinates
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, coordinate)| {
+                    *coordinate += 0.05 * (((7 * node + 3 * i) % 5) as f64 - 2.0)
+                })
+        });
+    finite_elements
+}
+
+#[test]
+fn hc_laplacian_smoothing() {
+    // HC smoothing pushes the nodes back toward the noisy original positions,
+    // so it shrinks the grid less than Laplacian smoothing does.
+    let volume = noisy_grid().calculate_block_volumes().unwrap()[0].1;
+    let mut laplacian = noisy_grid();
+    laplacian
+        .smooth(Smoothing::Laplacian(10, SMOOTHING_SCALE))
+        .unwrap();
+    let mut hc_laplacian = noisy_grid();
+    hc_laplacian
+        .smooth(Smoothing::HcLaplacian(10, 0.1, 0.6, SMOOTHING_SCALE))
+        .unwrap();
+    let laplacian_volume = laplacian.calculate_block_volumes().unwrap()[0].1;
+    let hc_laplacian_volume = hc_laplacian.calculate_block_volumes().unwrap()[0].1;
+    assert!(laplacian_volume < hc_laplacian_volume);
+    assert!(hc_laplacian_volume < volume);
+    assert!(
+        noisy_grid()
+            .smooth(Smoothing::HcLaplacian(10, 1.1, 0.6, SMOOTHING_SCALE))
+            .is_err()
+    );
+    assert!(
+        noisy_grid()
+            .smooth(Smoothing::HcLaplacian(10, 0.1, -0.1, SMOOTHING_SCALE))
+            .is_err()
+    );
+}
+
+#[test]
+fn optimize_untangles_inverted_elements() {
+    // An interior node of a grid is pushed past its diagonal neighbor, inverting elements,
+    // and the exterior nodes may only slide within the faces of the grid while untangling.
+    let tangled_grid = || {
+        let mut finite_elements = hexahedral_grid([3, 3, 3], |_, _, _| 1);
+        finite_elements.get_nodal_coordinates_mut()[21] = Coordinate::new([2.5, 2.5, 2.5]);
+        finite_elements
+    };
+    let mut finite_elements = tangled_grid();
+    assert!(
+        calculate_minimum_scaled_jacobians(
+            finite_elements.get_element_node_connectivity(),
+            finite_elements.get_nodal_coordinates()
+        )
+        .iter()
+        .any(|minimum_scaled_jacobian| minimum_scaled_jacobian <= &0.0)
+    );
+    assert_eq!(
+        finite_elements.optimize(0, 0.5, None).unwrap_err(),
+        "Unable to untangle elements [2, 4, 5, 10, 11, 13, 14], with a minimum scaled Jacobian of -3.380863e-1"
+    );
+    let mut finite_elements = tangled_grid();
+    finite_elements.optimize(20, 0.5, None).unwrap();
+    assert!(
+        calculate_minimum_scaled_jacobians(
+            finite_elements.get_element_node_connectivity(),
+            finite_elements.get_nodal_coordinates()
+        )
+        .iter()
+        .all(|minimum_scaled_jacobian| minimum_scaled_jacobian >= &0.5)
+    );
+    let grid = hexahedral_grid([3, 3, 3], |_, _, _| 1);
+    grid.calculate_exterior_nodes().iter().for_each(|node| {
+        grid.get_nodal_coordinates()[node - 1]
+            .iter()
+            .zip(finite_elements.get_nodal_coordinates()[node - 1].iter())
+            .filter(|(original, _)| **original == 0.0 || **original == 3.0)
+            .for_each(|(original, optimized)| assert!((original - optimized).abs() < EPSILON))
+    });
+}
+
+#[test]
+fn convergence_terminates_smoothing() {
+    // With the exterior prescribed, smoothing a perturbed grid settles back toward the regular
+    // grid, so each convergence criterion is met well before the iteration limit.
+    let perturbed_grid = || {
+        let mut finite_elements = noisy_grid();
+        let exterior_nodes = finite_elements.calculate_exterior_nodes();
+        finite_elements
+            .set_prescribed_nodes(Some(exterior_nodes), None)
+            .unwrap();
+        finite_elements.nodal_influencers();
+        finite_elements
+    };
+    let mut finite_elements = perturbed_grid();
+    finite_elements
+        .smooth(Smoothing::Laplacian(100, SMOOTHING_SCALE))
+        .unwrap();
+    assert_eq!(finite_elements.get_iterations(), 100);
+    [
+        Convergence::MaximumDisplacement(1e-3),
+        Convergence::RmsDisplacement(1e-3),
+        Convergence::Quality(Metric::EdgeRatio, 1e-3),
+    ]
+    .into_iter()
+    .for_each(|convergence| {
+        let mut finite_elements = perturbed_grid();
+        finite_elements
+            .smooth_with(
+                Smoothing::Laplacian(100, SMOOTHING_SCALE),
+                &SmoothingOptions {
+                    convergence: Some(convergence),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(finite_elements.get_iterations() < 100);
+    });
+}
+
+#[test]
+fn feature_preserving_smoothing() {
+    // The corners of a box are fixed and nodes on its edges slide along the edges,
+    // while without features the nodes on an edge are pulled onto the adjacent faces.
+    let smoothed = |feature_angle: Option<f64>| {
+        let mut finite_elements = hexahedral_grid([3, 2, 1], |_, _, _| 1);
+        finite_elements.nodal_coordinates[1] = Coordinate::new([1.3, 0.0, 0.0]);
+        finite_elements
+            .smooth_with(
+                Smoothing::Laplacian(10, SMOOTHING_SCALE),
+                &SmoothingOptions {
+                    feature_angle,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            finite_elements.get_nodal_influencers(),
+            finite_elements.get_node_node_connectivity()
+        );
+        finite_elements.get_nodal_coordinates().clone()
+    };
+    let nodal_coordinates = smoothed(Some(45.0));
+    assert_eq!(nodal_coordinates[0], Coordinate::new([0.0, 0.0, 0.0]));
+    assert!((nodal_coordinates[1][0] - 1.0).abs() < 0.3);
+    assert_eq!(nodal_coordinates[1][1], 0.0);
+    assert_eq!(nodal_coordinates[1][2], 0.0);
+    let nodal_coordinates = smoothed(None);
+    assert_ne!(nodal_coordinates[0][0], 0.0);
+    assert_ne!(nodal_coordinates[1][1], 0.0);
+}
+
+#[test]
+fn nodal_hierarchy_tri() {
+    let nodal_coordinates = Coordinates::new(
+        &(0..9)
+            .map(|node| [(node % 3) as f64, (node / 3) as f64, 0.0])
+            .collect::<Vec<[f64; 3]>>(),
+    );
+    let element_node_connectivity = vec![
+        [1, 2, 5],
+        [1, 5, 4],
+        [2, 3, 6],
+        [2, 6, 5],
+        [4, 5, 8],
+        [4, 8, 7],
+        [5, 6, 9],
+        [5, 9, 8],
+    ];
+    let mut finite_elements = TriangularFiniteElements::from_data(
+        vec![1, 1, 2, 2, 1, 1, 2, 2],
+        element_node_connectivity,
+        nodal_coordinates,
+    );
+    finite_elements.node_element_connectivity().unwrap();
+    finite_elements.node_node_connectivity().unwrap();
+    finite_elements.nodal_hierarchy().unwrap();
+    assert_eq!(
+        finite_elements.get_exterior_nodes(),
+        &vec![1, 2, 3, 4, 6, 7, 8, 9]
+    );
+    assert_eq!(finite_elements.get_interface_nodes(), &vec![2, 5, 8]);
+    assert!(finite_elements.get_interior_nodes().is_empty());
+    finite_elements.nodal_influencers();
+    assert_eq!(finite_elements.get_nodal_influencers()[1], vec![1, 3, 5, 6]);
+}
+
+#[test]
+fn nodal_hierarchy_closed_tri() {
+    // A closed surface has no exterior nodes, but the nodes on the interface between
+    // the bottom face (block 1) and the rest of the cube (block 2) are still boundary nodes.
+    let cube = closed_cube_surfaces(&[[0.0, 0.0, 0.0]]);
+    let mut finite_elements = TriangularFiniteElements::from_data(
+        (0..12).map(|element| 1 + (element > 1) as u8).collect(),
+        cube.get_element_node_connectivity().clone(),
+        cube.get_nodal_coordinates().clone(),
+    );
+    finite_elements.node_element_connectivity().unwrap();
+    finite_elements.node_node_connectivity().unwrap();
+    finite_elements.nodal_hierarchy().unwrap();
+    assert!(finite_elements.get_exterior_nodes().is_empty());
+    assert_eq!(finite_elements.get_interface_nodes(), &vec![1, 2, 3, 4]);
+    assert_eq!(finite_elements.get_interior_nodes(), &vec![5, 6, 7, 8]);
+    finite_elements.nodal_influencers();
+    let nodal_influencers = finite_elements.get_nodal_influencers();
+    (0..4).for_each(|node| {
+        assert!(!nodal_influencers[node].is_empty());
+        assert!(
+            nodal_influencers[node]
+                .iter()
+                .all(|influencer| finite_elements.get_boundary_nodes().contains(influencer))
+        )
+    });
+    assert_eq!(nodal_influencers[0], vec![2, 3, 4]);
+    (4..8).for_each(|node| {
+        assert_eq!(
+            nodal_influencers[node],
+            finite_elements.get_node_node_connectivity()[node]
+        )
+    });
+}
+
+#[test]
+fn prescribed_neighbors_of_boundary_nodes() {
+    // The interface node between the blocks is only influenced by other boundary nodes,
+    // even when a neighbor inside one of the blocks is prescribed.
+    let mut finite_elements = hexahedral_grid([4, 2, 2], |i, _, _| 1 + (i > 1) as u8);
+    finite_elements.nodal_hierarchy().unwrap();
+    finite_elements
+        .set_prescribed_nodes(Some(vec![22]), None)
+        .unwrap();
+    finite_elements.nodal_influencers();
+    assert!(finite_elements.get_nodal_influencers()[21].is_empty());
+    assert_eq!(
+        finite_elements.get_nodal_influencers()[22],
+        vec![8, 18, 28, 38]
+    );
+}
+
+#[test]
+fn node_set_from_inp() {
+    let file_path = std::env::temp_dir().join("automesh_node_set.inp");
+    std::fs::write(
+        &file_path,
+        "*NODE, NSET=ALLNODES\n\
+        10, 0.0, 0.0, 0.0\n\
+        20, 1.0, 0.0, 0.0\n\
+        30, 1.0, 1.0, 0.0\n\
+        40, 0.0, 1.0, 0.0\n\
+        **\n\
+        *NSET, NSET=FIXED\n\
+        30, 10,\n\
+        ** a comment\n\
+        30\n\
+        *Nset, nset=every_other, generate\n\
+        20, 40, 20\n",
+    )
+    .unwrap();
+    let file_path = file_path.to_str().unwrap();
+    assert_eq!(
+        HexahedralFiniteElements::node_set_from_inp(file_path, "fixed").unwrap(),
+        vec![1, 3]
+    );
+    assert_eq!(
+        HexahedralFiniteElements::node_set_from_inp(file_path, "EVERY_OTHER").unwrap(),
+        vec![2, 4]
+    );
+    assert!(HexahedralFiniteElements::node_set_from_inp(file_path, "missing").is_err());
+}
+
+#[test]
+fn block_smoothing() {
+    // Block 1 is smoothed with its own method, block 2 is frozen, and the nodes
+    // on the interface touch the frozen block so they are held fixed as well.
+    let smoothed = |block_method: Smoothing| {
+        let mut finite_elements = hexahedral_grid([4, 2, 2], |i, _, _| 1 + (i > 1) as u8);
+        finite_elements
+            .smooth_with(
+                Smoothing::Laplacian(10, SMOOTHING_SCALE),
+                &SmoothingOptions {
+                    block_smoothing: vec![(1, Some(block_method)), (2, None)],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(finite_elements.get_prescribed_nodes().is_empty());
+        assert_eq!(
+            finite_elements.get_nodal_influencers(),
+            finite_elements.get_node_node_connectivity()
+        );
+        finite_elements
+    };
+    let grid = hexahedral_grid([4, 2, 2], |i, _, _| 1 + (i > 1) as u8);
+    let taubin = smoothed(Smoothing::Taubin(10, 0.1, SMOOTHING_SCALE));
+    let frozen_nodes = taubin.calculate_nodes_in_box(&[2.0, 0.0, 0.0], &[4.0, 2.0, 2.0]);
+    assert_eq!(frozen_nodes.len(), 27);
+    taubin
+        .get_nodal_coordinates()
+        .iter()
+        .zip(grid.get_nodal_coordinates().iter())
+        .enumerate()
+        .for_each(|(node, (smoothed, original))| {
+            if frozen_nodes.contains(&(node + 1)) {
+                assert_eq!(smoothed, original)
+            } else {
+                assert_ne!(smoothed, original)
+            }
+        });
+    assert!(
+        taubin
+            .get_nodal_coordinates()
+            .iter()
+            .zip(
+                smoothed(Smoothing::Laplacian(10, SMOOTHING_SCALE))
+                    .get_nodal_coordinates()
+                    .iter()
+            )
+            .any(|(taubin, laplacian)| taubin != laplacian)
+    );
+}
+
+#[test]
+fn exterior_nodes() {
+    let finite_elements = hexahedral_grid([3, 3, 2], |_, _, _| 1);
+    let mut exterior_nodes_gold: Nodes = (1..=48).collect();
+    exterior_nodes_gold.retain(|node| ![22, 23, 26, 27].contains(node));
+    assert_eq!(
+        finite_elements.calculate_exterior_nodes(),
+        exterior_nodes_gold
+    );
+}
+
+#[test]
+fn homogeneous_and_inhomogeneous_prescribed_nodes() {
+    // The combined prescribed nodes are sorted and unique, so that looking them up
+    // when calculating the nodal influencers finds every one of them.
+    let mut finite_elements = hexahedral_grid([2, 1, 1], |_, _, _| 1);
+    finite_elements
+        .set_prescribed_nodes(
+            Some(vec![12, 5, 1, 5]),
+            Some((
+                Coordinates::new(&[[1.0, -0.5, 0.0], [0.0, 0.0, 1.5]]),
+                vec![2, 7],
+            )),
+        )
+        .unwrap();
+    assert_eq!(
+        finite_elements.get_prescribed_nodes(),
+        &vec![1, 2, 5, 7, 12]
+    );
+    finite_elements.nodal_influencers();
+    let nodal_influencers = finite_elements.get_nodal_influencers();
+    [1, 2, 5, 7, 12]
+        .iter()
+        .for_each(|node| assert!(nodal_influencers[node - 1].is_empty()));
+    assert_eq!(
+        nodal_influencers[2],
+        finite_elements.get_node_node_connectivity()[2]
+    );
+    finite_elements
+        .smooth(Smoothing::Laplacian(1, SMOOTHING_SCALE))
+        .unwrap();
+    let nodal_coordinates = finite_elements.get_nodal_coordinates();
+    assert_eq!(nodal_coordinates[1], Coordinate::new([1.0, -0.5, 0.0]));
+    assert_eq!(nodal_coordinates[4], Coordinate::new([1.0, 1.0, 0.0]));
+}
+
+#[test]
+fn reference_surface_projection() {
+    let corners = Coordinates::new(
+        &(0..8)
+            .map(|corner| {
+                [
+                    3.0 * (corner % 2) as f64,
+                    3.0 * ((corner / 2) % 2) as f64,
+                    3.0 * (corner / 4) as f64,
+                ]
+            })
+            .collect::<Vec<[f64; 3]>>(),
+    );
+    let reference = TriangularFiniteElements::from_data(
+        vec![1; 12],
+        [
+            [1, 2, 6, 5],
+            [2, 4, 8, 6],
+            [4, 3, 7, 8],
+            [3, 1, 5, 7],
+            [1, 3, 4, 2],
+            [5, 6, 8, 7],
+        ]
+        .iter()
+        .flat_map(|face| [[face[0], face[1], face[2]], [face[0], face[2], face[3]]])
+        .collect(),
+        corners,
+    )
+    .into_tesselation();
+    let smoothed_exterior = |projected: bool| {
+        let mut finite_elements = hexahedral_grid([3, 3, 3], |_, _, _| 1);
+        finite_elements
+            .smooth_with(
+                Smoothing::Laplacian(10, SMOOTHING_SCALE),
+                &SmoothingOptions {
+                    reference_surface: projected.then_some(&reference),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let nodal_coordinates = finite_elements.get_nodal_coordinates().clone();
+        finite_elements
+            .calculate_exterior_nodes()
+            .into_iter()
+            .map(|node| nodal_coordinates[node - 1].clone())
+            .collect::<Coordinates>()
+    };
+    let on_surface = |coordinates: &Coordinate| {
+        coordinates
+            .iter()
+            .all(|&coordinate| (-EPSILON..=3.0 + EPSILON).contains(&coordinate))
+            && coordinates
+                .iter()
+                .any(|&coordinate| coordinate.abs() < 1e-6 || (coordinate - 3.0).abs() < 1e-6)
+    };
+    assert!(!smoothed_exterior(false).iter().all(on_surface));
+    assert!(smoothed_exterior(true).iter().all(on_surface));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_matches_serial() {
+    // Each node and element is computed independently of the others,
+    // so the results are bitwise identical for any number of threads.
+    let metrics = [
+        Metric::AspectRatio,
+        Metric::Condition,
+        Metric::DiagonalRatio,
+        Metric::Distortion,
+        Metric::EdgeRatio,
+        Metric::ElementArea,
+        Metric::ElementVolume,
+        Metric::MaximumAngle,
+        Metric::MinimumAngle,
+        Metric::NormalDeviation,
+        Metric::Oddy,
+        Metric::RadiusRatio,
+        Metric::RelativeSizeSquared,
+        Metric::ScaledJacobian,
+        Metric::Shape,
+        Metric::Shear,
+        Metric::Skew,
+        Metric::Stretch,
+        Metric::Taper,
+        Metric::Warpage,
+    ];
+    fn bits<const N: usize>(
+        finite_elements: &mut super::FiniteElements<N>,
+        metrics: &[Metric],
+    ) -> Vec<Vec<u64>>
+    where
+        super::FiniteElements<N>: FiniteElementMethods<N>,
+    {
+        finite_elements.node_element_connectivity().unwrap();
+        finite_elements.node_node_connectivity().unwrap();
+        finite_elements.nodal_influencers();
+        let laplacian = finite_elements.laplacian(finite_elements.get_nodal_influencers());
+        finite_elements
+            .smooth(Smoothing::Taubin(10, 0.1, SMOOTHING_SCALE))
+            .unwrap();
+        let coordinates = |coordinates: &Coordinates| {
+            coordinates
+                .iter()
+                .flat_map(|coordinate| coordinate.iter().map(|entry| entry.to_bits()))
+                .collect()
+        };
+        [
+            finite_elements
+                .get_node_element_connectivity()
+                .iter()
+                .chain(finite_elements.get_node_node_connectivity())
+                .flatten()
+                .map(|&node| node as u64)
+                .collect(),
+            coordinates(&laplacian),
+            coordinates(finite_elements.get_nodal_coordinates()),
+        ]
+        .into_iter()
+        .chain(metrics.iter().filter_map(|metric| {
+            super::calculate_metric(
+                metric,
+                finite_elements.get_element_node_connectivity(),
+                finite_elements.get_nodal_coordinates(),
+            )
+            .ok()
+            .map(|values| values.iter().map(|value| value.to_bits()).collect())
+        }))
+        .collect()
+    }
+    let threads = |number_of_threads: usize| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(number_of_threads)
+            .build()
+            .unwrap()
+    };
+    let serial = threads(1).install(|| {
+        (
+            bits(&mut noisy_grid(), &metrics),
+            bits(&mut closed_cube_surfaces(&[[0.0, 0.0, 0.0]]), &metrics),
+        )
+    });
+    let parallel = threads(4).install(|| {
+        (
+            bits(&mut noisy_grid(), &metrics),
+            bits(&mut closed_cube_surfaces(&[[0.0, 0.0, 0.0]]), &metrics),
+        )
+    });
+    assert_eq!(serial.0.len(), 3 + 14);
+    assert_eq!(serial.1.len(), 3 + 11);
+    assert_eq!(serial, parallel);
+}
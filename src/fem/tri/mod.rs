@@ -3,13 +3,14 @@ use std::time::Instant;
 
 use super::{
     Connectivity, Coordinates, FiniteElementMethods, FiniteElementSpecifics, FiniteElements,
-    Metrics, NODE_NUMBERING_OFFSET, Tessellation, Vector, calculate_maximum_edge_ratios,
-    calculate_maximum_skews, calculate_minimum_scaled_jacobians, metrics_headers,
+    Metric, Metrics, NODE_NUMBERING_OFFSET, Tessellation, Vector, calculate_metrics, map_elements,
+    map_indices, metrics_headers,
 };
 use conspire::math::{Tensor, TensorArray};
 use ndarray::{Array2, s};
 use ndarray_npy::WriteNpyExt;
 use std::{
+    collections::HashMap,
     fs::File,
     io::{BufWriter, Error as ErrorIO, Write},
     path::Path,
@@ -87,25 +88,19 @@ pub fn calculate_maximum_edge_ratios_tri<const N: usize>(
     // Knupp 2006
     // https://www.osti.gov/servlets/purl/901967
     // page 19 and 26
-    let mut l0 = 0.0;
-    let mut l1 = 0.0;
-    let mut l2 = 0.0;
-    let maximum_edge_ratios = element_node_connectivity
-        .iter()
-        .map(|connectivity| {
-            l0 = (&nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET])
-                .norm();
-            l1 = (&nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET])
-                .norm();
-            l2 = (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET])
-                .norm();
-            [l0, l1, l2].into_iter().reduce(f64::max).unwrap()
-                / [l0, l1, l2].into_iter().reduce(f64::min).unwrap()
-        })
-        .collect();
+    let maximum_edge_ratios = map_elements(element_node_connectivity, |connectivity| {
+        let l0 = (&nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET])
+            .norm();
+        let l1 = (&nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET])
+            .norm();
+        let l2 = (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET])
+            .norm();
+        [l0, l1, l2].into_iter().reduce(f64::max).unwrap()
+            / [l0, l1, l2].into_iter().reduce(f64::min).unwrap()
+    });
     maximum_edge_ratios
 }
 
@@ -119,32 +114,26 @@ pub fn calculate_minimum_angles_tri<const N: usize>(
         panic!("Only implemented for triangular elements.")
     }
     // edge vectors of the triangle l0, l1, l2
-    let mut l0 = Vector::zero();
-    let mut l1 = Vector::zero();
-    let mut l2 = Vector::zero();
     let flip = -1.0; // to reverse the direction of the unit vector below
-    let minimum_angles = element_node_connectivity
-        .iter()
-        .map(|connectivity| {
-            l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
-            l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
-            l2 = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET];
-            l0.normalize();
-            l1.normalize();
-            l2.normalize();
-            [
-                ((&l0 * flip) * &l1).acos(),
-                ((&l1 * flip) * &l2).acos(),
-                ((&l2 * flip) * &l0).acos(),
-            ]
-            .into_iter()
-            .reduce(f64::min)
-            .unwrap()
-        })
-        .collect();
+    let minimum_angles = map_elements(element_node_connectivity, |connectivity| {
+        let mut l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
+        let mut l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
+        let mut l2 = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET];
+        l0.normalize();
+        l1.normalize();
+        l2.normalize();
+        [
+            ((&l0 * flip) * &l1).acos(),
+            ((&l1 * flip) * &l2).acos(),
+            ((&l2 * flip) * &l0).acos(),
+        ]
+        .into_iter()
+        .reduce(f64::min)
+        .unwrap()
+    });
     minimum_angles
 }
 
@@ -198,19 +187,14 @@ pub fn calculate_element_areas_tri<const N: usize>(
     // page 19
     #[cfg(feature = "profile")]
     let time = Instant::now();
-    let mut l0 = Vector::zero();
-    let mut l1 = Vector::zero();
-    let element_areas = element_node_connectivity
-        .iter()
-        .map(|connectivity| {
-            l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
-            l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
-                - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
-            // Calculate the area using the cross product
-            0.5 * (l0.cross(&l1)).norm()
-        })
-        .collect();
+    let element_areas = map_elements(element_node_connectivity, |connectivity| {
+        let l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
+        let l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
+        // Calculate the area using the cross product
+        0.5 * (l0.cross(&l1)).norm()
+    });
     #[cfg(feature = "profile")]
     println!(
         "             \x1b[1;93mTriangle element areas\x1b[0m {:?}",
@@ -219,6 +203,224 @@ pub fn calculate_element_areas_tri<const N: usize>(
     element_areas
 }
 
+pub fn calculate_enclosed_volumes_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // signed volume of the tetrahedron formed by each element and the origin,
+    // which sum to the enclosed volume for a closed and consistently-oriented surface
+    map_elements(element_node_connectivity, |connectivity| {
+        &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            * nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+                .cross(&nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET])
+            / 6.0
+    })
+}
+
+fn calculate_element_edge_lengths_and_area_tri(
+    connectivity: &[usize],
+    nodal_coordinates: &Coordinates,
+) -> ([f64; TRI], f64) {
+    let l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+        - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
+    let l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+        - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
+    let l2 = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+        - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET];
+    let area = 0.5 * l0.cross(&l1).norm();
+    ([l0.norm(), l1.norm(), l2.norm()], area)
+}
+
+pub fn calculate_maximum_angles_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // edge vectors of the triangle l0, l1, l2
+    let flip = -1.0; // to reverse the direction of the unit vector below
+    map_elements(element_node_connectivity, |connectivity| {
+        let mut l0 = &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET];
+        let mut l1 = &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET];
+        let mut l2 = &nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET];
+        l0.normalize();
+        l1.normalize();
+        l2.normalize();
+        [
+            ((&l0 * flip) * &l1).clamp(-1.0, 1.0).acos(),
+            ((&l1 * flip) * &l2).clamp(-1.0, 1.0).acos(),
+            ((&l2 * flip) * &l0).clamp(-1.0, 1.0).acos(),
+        ]
+        .into_iter()
+        .reduce(f64::max)
+        .unwrap()
+    })
+}
+
+pub fn calculate_aspect_ratios_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // Knupp 2006
+    // https://www.osti.gov/servlets/purl/901967
+    // page 27
+    map_elements(element_node_connectivity, |connectivity| {
+        let (lengths, area) =
+            calculate_element_edge_lengths_and_area_tri(connectivity, nodal_coordinates);
+        if area > 0.0 {
+            lengths.into_iter().reduce(f64::max).unwrap() * lengths.iter().sum::<f64>()
+                / (4.0 * 3.0_f64.sqrt() * area)
+        } else {
+            f64::INFINITY
+        }
+    })
+}
+
+pub fn calculate_radius_ratios_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // Knupp 2006
+    // https://www.osti.gov/servlets/purl/901967
+    // page 31
+    // circumradius over twice the inradius
+    map_elements(element_node_connectivity, |connectivity| {
+        let (lengths, area) =
+            calculate_element_edge_lengths_and_area_tri(connectivity, nodal_coordinates);
+        if area > 0.0 {
+            lengths.iter().product::<f64>() * lengths.iter().sum::<f64>() / (16.0 * area * area)
+        } else {
+            f64::INFINITY
+        }
+    })
+}
+
+pub fn calculate_conditions_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // Knupp 2006
+    // https://www.osti.gov/servlets/purl/901967
+    // page 28
+    map_elements(element_node_connectivity, |connectivity| {
+        let (lengths, area) =
+            calculate_element_edge_lengths_and_area_tri(connectivity, nodal_coordinates);
+        if area > 0.0 {
+            lengths.iter().map(|length| length * length).sum::<f64>()
+                / (4.0 * 3.0_f64.sqrt() * area)
+        } else {
+            f64::INFINITY
+        }
+    })
+}
+
+pub fn calculate_shapes_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // Knupp 2006
+    // https://www.osti.gov/servlets/purl/901967
+    // page 34
+    calculate_conditions_tri(element_node_connectivity, nodal_coordinates)
+        .iter()
+        .map(|condition| {
+            if condition.is_finite() {
+                1.0 / condition
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+pub fn calculate_maximum_normal_deviations_tri<const N: usize>(
+    element_node_connectivity: &Connectivity<N>,
+    nodal_coordinates: &Coordinates,
+) -> Metrics {
+    // #TODO: consider rearchitect, as these types of if-type-checks
+    // indicate rearchitecture may help code logic.
+    if N != TRI {
+        panic!("Only implemented for triangular elements.")
+    }
+    // maximum angle between the normal of an element
+    // and the normals of the elements sharing one of its edges
+    let normals: Vec<Vector> = map_indices(element_node_connectivity.len(), |element| {
+        let connectivity = &element_node_connectivity[element];
+        let normal = (&nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET]
+            - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET])
+            .cross(
+                &(&nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET]
+                    - &nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET]),
+            );
+        if normal.norm() > 0.0 {
+            normal.normalized()
+        } else {
+            normal
+        }
+    });
+    let mut edge_elements: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+    element_node_connectivity
+        .iter()
+        .enumerate()
+        .for_each(|(element, connectivity)| {
+            (0..TRI).for_each(|edge| {
+                let node_a = connectivity[edge];
+                let node_b = connectivity[(edge + 1) % TRI];
+                edge_elements
+                    .entry([node_a.min(node_b), node_a.max(node_b)])
+                    .or_default()
+                    .push(element)
+            })
+        });
+    Metrics::from_vec(map_indices(element_node_connectivity.len(), |element| {
+        let connectivity = &element_node_connectivity[element];
+        (0..TRI)
+            .flat_map(|edge| {
+                let node_a = connectivity[edge];
+                let node_b = connectivity[(edge + 1) % TRI];
+                edge_elements[&[node_a.min(node_b), node_a.max(node_b)]].iter()
+            })
+            .filter(|&&neighbor| neighbor != element)
+            .map(|&neighbor| {
+                (&normals[element] * &normals[neighbor])
+                    .clamp(-1.0, 1.0)
+                    .acos()
+            })
+            .fold(0.0, f64::max)
+    }))
+}
+
 pub fn write_finite_elements_metrics_tri<const N: usize>(
     file_path: &str,
     element_node_connectivity: &Connectivity<N>,
@@ -229,13 +431,25 @@ pub fn write_finite_elements_metrics_tri<const N: usize>(
     if N != TRI {
         panic!("Only implemented for triangular elements.")
     }
-    let maximum_edge_ratios =
-        calculate_maximum_edge_ratios(element_node_connectivity, nodal_coordinates);
-    let minimum_scaled_jacobians =
-        calculate_minimum_scaled_jacobians(element_node_connectivity, nodal_coordinates);
-    let maximum_skews = calculate_maximum_skews(element_node_connectivity, nodal_coordinates);
-    let areas = calculate_element_areas_tri(element_node_connectivity, nodal_coordinates);
-    let minimum_angles = calculate_minimum_angles_tri(element_node_connectivity, nodal_coordinates);
+    let metrics_set = calculate_metrics(
+        &[
+            Metric::EdgeRatio,
+            Metric::ScaledJacobian,
+            Metric::Skew,
+            Metric::ElementArea,
+            Metric::MinimumAngle,
+            Metric::MaximumAngle,
+            Metric::AspectRatio,
+            Metric::RadiusRatio,
+            Metric::Condition,
+            Metric::Shape,
+            Metric::NormalDeviation,
+        ],
+        element_node_connectivity,
+        nodal_coordinates,
+    )
+    .map_err(ErrorIO::other)?;
+    let number_of_elements = element_node_connectivity.len();
     #[cfg(feature = "profile")]
     let time = Instant::now();
     let mut file = BufWriter::new(File::create(file_path)?);
@@ -246,58 +460,29 @@ pub fn write_finite_elements_metrics_tri<const N: usize>(
         Some("csv") => {
             let header_string = metrics_headers::<N>();
             file.write_all(header_string.as_bytes())?;
-            maximum_edge_ratios
-                .iter()
-                .zip(
-                    minimum_scaled_jacobians.iter().zip(
-                        maximum_skews
-                            .iter()
-                            .zip(areas.iter().zip(minimum_angles.iter())),
-                    ),
+            (0..number_of_elements).try_for_each(|element| {
+                writeln!(
+                    file,
+                    "{}",
+                    metrics_set
+                        .iter()
+                        .map(|metrics| format!("{:>10.6e}", metrics[element]))
+                        .collect::<Vec<String>>()
+                        .join(",")
                 )
-                .try_for_each(
-                    |(
-                        maximum_edge_ratio,
-                        (minimum_scaled_jacobian, (maximum_skew, (area, minimum_angle))),
-                    )| {
-                        file.write_all(
-                            format!(
-                                "{:>10.6e},{:>10.6e},{:>10.6e},{:>10.6e},{:>10.6e}\n",
-                                maximum_edge_ratio,
-                                minimum_scaled_jacobian,
-                                maximum_skew,
-                                area,
-                                minimum_angle
-                            )
-                            .as_bytes(),
-                        )
-                    },
-                )?;
+            })?;
             file.flush()?
         }
         Some("npy") => {
-            let n_columns = 5; // total number of triangle metrics
-            let idx_ratios = 0; // maximum edge ratios
-            let idx_jacobians = 1; // minimum scaled jacobians
-            let idx_skews = 2; // maximum skews
-            let idx_areas = 3; // areas
-            let idx_angles = 4; // minimum angles
-            let mut metrics_set =
-                Array2::<f64>::from_elem((minimum_scaled_jacobians.len(), n_columns), 0.0);
-            metrics_set
-                .slice_mut(s![.., idx_ratios])
-                .assign(&maximum_edge_ratios);
+            let mut metrics_array =
+                Array2::<f64>::from_elem((number_of_elements, metrics_set.len()), 0.0);
             metrics_set
-                .slice_mut(s![.., idx_jacobians])
-                .assign(&minimum_scaled_jacobians);
-            metrics_set
-                .slice_mut(s![.., idx_skews])
-                .assign(&maximum_skews);
-            metrics_set.slice_mut(s![.., idx_areas]).assign(&areas);
-            metrics_set
-                .slice_mut(s![.., idx_angles])
-                .assign(&minimum_angles);
-            metrics_set.write_npy(file).unwrap();
+                .iter()
+                .enumerate()
+                .for_each(|(column, metrics)| {
+                    metrics_array.slice_mut(s![.., column]).assign(metrics)
+                });
+            metrics_array.write_npy(file).unwrap();
         }
         _ => panic!("print error message with input and extension"),
     }
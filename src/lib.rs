@@ -19,8 +19,9 @@ mod tree;
 mod voxel;
 
 pub use fem::{
-    Blocks, Connectivity, FiniteElementMethods, FiniteElementSpecifics, FiniteElements,
-    HexahedralFiniteElements, Smoothing, TriangularFiniteElements,
+    Blocks, Connectivity, Convergence, FiniteElementMethods, FiniteElementSpecifics,
+    FiniteElements, FiniteElementsKind, HexahedralFiniteElements, Metric, Smoothing,
+    SmoothingOptions, TriangularFiniteElements,
 };
 pub use geometry::{
     GeometricPrimitive, Primitive,
@@ -32,8 +33,13 @@ pub use geometry::{
     Sphere, Torus
 };
 pub use tessellation::Tessellation;
-pub use tree::{IntoFiniteElements, Octree, Tree};
-pub use voxel::{Nel, Scale, Translate, VoxelData, Voxels};
+pub use tree::{
+    Adjacency, Defeaturing, DefeaturingMethod, IntoFiniteElements, Octree, Reassignment, Tree,
+};
+pub use voxel::{
+    ChunkedVoxels, Component, Components, Morphology, Nel, Scale, StructuringElement, Translate,
+    VoxelData, Voxels,
+};
 
 use conspire::math::{TensorRank1, TensorRank1Vec};
 
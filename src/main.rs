@@ -1,13 +1,21 @@
 use automesh::{
-    Blocks, FiniteElementMethods, FiniteElementSpecifics, HexahedralFiniteElements,
-    IntoFiniteElements, Nel, Octree, Scale, Smoothing, Tessellation, Translate, Tree,
-    TriangularFiniteElements, Voxels,
+    Blocks, ChunkedVoxels, Convergence, Coordinates, Defeaturing, DefeaturingMethod,
+    FiniteElementMethods, FiniteElementSpecifics, FiniteElementsKind, HexahedralFiniteElements,
+    IntoFiniteElements, Metric, Morphology, NSD, Nel, Octree, Scale, Smoothing, SmoothingOptions,
+    StructuringElement, Tessellation, Translate, Tree, TriangularFiniteElements, Voxels,
 };
 use clap::{Parser, Subcommand};
-use conspire::math::TensorVec;
-use ndarray_npy::{ReadNpyError, WriteNpyError};
+use conspire::math::{Tensor, TensorVec};
+use ndarray::{Array1, Array2};
+use ndarray_npy::{ReadNpyError, ReadNpyExt, ViewNpyError, WriteNpyError};
 use netcdf::Error as ErrorNetCDF;
-use std::{io::Error as ErrorIO, path::Path, time::Instant};
+use std::{
+    collections::HashMap,
+    fs::{File, read_to_string},
+    io::{BufWriter, Error as ErrorIO, Write},
+    path::Path,
+    time::Instant,
+};
 use vtkio::Error as ErrorVtk;
 
 macro_rules! about {
@@ -37,6 +45,10 @@ macro_rules! about {
 struct Args {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Number of threads for parallel execution (requires the parallel feature)
+    #[arg(global = true, long, value_name = "NUM")]
+    threads: Option<usize>,
 }
 
 #[derive(Subcommand)]
@@ -61,6 +73,127 @@ enum Commands {
         #[arg(long, short, value_name = "MIN")]
         min: usize,
 
+        /// Minimum cluster sizes for specific voxel IDs (ID:MIN,...)
+        #[arg(long, value_delimiter = ',', value_name = "ID:MIN")]
+        min_id: Vec<String>,
+
+        /// Number of neighbors connecting voxels into clusters (6 | 18 | 26)
+        #[arg(default_value_t = 6, long, value_name = "NUM")]
+        connectivity: usize,
+
+        /// Voxel IDs with clusters that are never reassigned
+        #[arg(long, value_delimiter = ',', value_name = "IDS")]
+        protect: Vec<u8>,
+
+        /// Report of the reassigned clusters (csv)
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+
+        /// Defeaturing method (octree | union-find)
+        #[arg(default_value = "octree", long, value_name = "METHOD")]
+        method: String,
+
+        /// Number of voxels in the x-direction
+        #[arg(long, short = 'x', value_name = "NEL")]
+        nelx: Option<usize>,
+
+        /// Number of voxels in the y-direction
+        #[arg(long, short = 'y', value_name = "NEL")]
+        nely: Option<usize>,
+
+        /// Number of voxels in the z-direction
+        #[arg(long, short = 'z', value_name = "NEL")]
+        nelz: Option<usize>,
+
+        /// Pass to quiet the terminal output
+        #[arg(action, long, short)]
+        quiet: bool,
+    },
+
+    /// Reports the contents of a segmentation or mesh file
+    Info {
+        /// Segmentation or mesh input file (inp | npy | spn | stl)
+        #[arg(long, short, value_name = "FILE")]
+        input: String,
+
+        /// Pass to print the information as JSON
+        #[arg(action, long)]
+        json: bool,
+
+        /// Number of voxels in the x-direction
+        #[arg(long, short = 'x', value_name = "NEL")]
+        nelx: Option<usize>,
+
+        /// Number of voxels in the y-direction
+        #[arg(long, short = 'y', value_name = "NEL")]
+        nely: Option<usize>,
+
+        /// Number of voxels in the z-direction
+        #[arg(long, short = 'z', value_name = "NEL")]
+        nelz: Option<usize>,
+
+        /// Scaling (> 0.0) in the x-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        xscale: f64,
+
+        /// Scaling (> 0.0) in the y-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        yscale: f64,
+
+        /// Scaling (> 0.0) in the z-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        zscale: f64,
+
+        /// Translation in the x-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        xtranslate: f64,
+
+        /// Translation in the y-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        ytranslate: f64,
+
+        /// Translation in the z-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        ztranslate: f64,
+
+        /// Pass to quiet the terminal output
+        #[arg(action, long, short)]
+        quiet: bool,
+    },
+
+    /// Reports the connected components of each voxel ID in a segmentation
+    Inspect {
+        /// Segmentation input file (npy | spn)
+        #[arg(long, short, value_name = "FILE")]
+        input: String,
+
+        /// Segmentation output file with components split into distinct voxel IDs (npy | spn)
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Voxel IDs with components not to split
+        #[arg(long, value_delimiter = ',', value_name = "IDS")]
+        keep: Vec<u8>,
+
+        /// Voxel IDs of the background, which have components not to split
+        #[arg(default_value = "0", long, num_args = 1.., short, value_delimiter = ' ', value_name = "ID")]
+        remove: Vec<u8>,
+
         /// Number of voxels in the x-direction
         #[arg(long, short = 'x', value_name = "NEL")]
         nelx: Option<usize>,
@@ -73,6 +206,45 @@ enum Commands {
         #[arg(long, short = 'z', value_name = "NEL")]
         nelz: Option<usize>,
 
+        /// Scaling (> 0.0) in the x-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        xscale: f64,
+
+        /// Scaling (> 0.0) in the y-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        yscale: f64,
+
+        /// Scaling (> 0.0) in the z-direction, applied before translation
+        #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+        zscale: f64,
+
+        /// Translation in the x-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        xtranslate: f64,
+
+        /// Translation in the y-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        ytranslate: f64,
+
+        /// Translation in the z-direction
+        #[arg(
+            long,
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_name = "VAL"
+        )]
+        ztranslate: f64,
+
         /// Pass to quiet the terminal output
         #[arg(action, long, short)]
         quiet: bool,
@@ -94,6 +266,57 @@ enum Commands {
         #[arg(long, short, value_name = "FILE")]
         output: String,
 
+        /// Quality metrics to calculate [default: edge_ratio scaled_jacobian skew volume, or all for triangles]
+        #[arg(long, num_args = 1.., short, value_delimiter = ' ', value_name = "NAME")]
+        names: Option<Vec<String>>,
+
+        /// Pass to quiet the terminal output
+        #[arg(action, long, short)]
+        quiet: bool,
+    },
+
+    /// Applies a morphological operation and creates a new segmentation
+    Morph {
+        /// Segmentation input file (npy | spn)
+        #[arg(long, short, value_name = "FILE")]
+        input: String,
+
+        /// Morphed segmentation output file (npy | spn)
+        #[arg(long, short, value_name = "FILE")]
+        output: String,
+
+        /// Morphological operation (close | dilate | erode | fill | open)
+        #[arg(long, value_name = "OPERATION")]
+        operation: String,
+
+        /// Voxel IDs in order of priority [default: all but the background in ascending order]
+        #[arg(long, value_delimiter = ',', value_name = "IDS")]
+        ids: Vec<u8>,
+
+        /// Structuring element (ball | cross | cube)
+        #[arg(default_value = "cross", long, value_name = "ELEMENT")]
+        element: String,
+
+        /// Radius of the structuring element in voxels
+        #[arg(default_value_t = 1, long, short, value_name = "RADIUS")]
+        radius: usize,
+
+        /// Background voxel ID
+        #[arg(default_value_t = 0, long, short, value_name = "ID")]
+        background: u8,
+
+        /// Number of voxels in the x-direction
+        #[arg(long, short = 'x', value_name = "NEL")]
+        nelx: Option<usize>,
+
+        /// Number of voxels in the y-direction
+        #[arg(long, short = 'y', value_name = "NEL")]
+        nely: Option<usize>,
+
+        /// Number of voxels in the z-direction
+        #[arg(long, short = 'z', value_name = "NEL")]
+        nelz: Option<usize>,
+
         /// Pass to quiet the terminal output
         #[arg(action, long, short)]
         quiet: bool,
@@ -126,6 +349,13 @@ enum Commands {
         #[arg(long, num_args = 1.., short, value_delimiter = ' ', value_name = "ID")]
         remove: Option<Vec<usize>>,
 
+        /// Voxel ID map applied before removal (OLD:NEW,... | FILE.csv | contiguous)
+        #[arg(long, value_delimiter = ',', value_name = "MAP")]
+        relabel: Vec<String>,
+
+        #[command(flatten)]
+        edit: VoxelEditArgs,
+
         /// Scaling (> 0.0) in the x-direction, applied before translation
         #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
         xscale: f64,
@@ -142,7 +372,7 @@ enum Commands {
         #[arg(
             long,
             default_value_t = 0.0,
-            allow_negative_numbers = true,
+            allow_hyphen_values = true,
             value_name = "VAL"
         )]
         xtranslate: f64,
@@ -151,7 +381,7 @@ enum Commands {
         #[arg(
             long,
             default_value_t = 0.0,
-            allow_negative_numbers = true,
+            allow_hyphen_values = true,
             value_name = "VAL"
         )]
         ytranslate: f64,
@@ -160,7 +390,7 @@ enum Commands {
         #[arg(
             long,
             default_value_t = 0.0,
-            allow_negative_numbers = true,
+            allow_hyphen_values = true,
             value_name = "VAL"
         )]
         ztranslate: f64,
@@ -180,10 +410,6 @@ enum Commands {
 
     /// Applies smoothing to an existing mesh
     Smooth {
-        /// Pass to enable hierarchical control
-        #[arg(action, long, short = 'c')]
-        hierarchical: bool,
-
         /// Mesh input file (inp | stl)
         #[arg(long, short, value_name = "FILE")]
         input: String,
@@ -192,21 +418,8 @@ enum Commands {
         #[arg(long, short, value_name = "FILE")]
         output: String,
 
-        /// Number of smoothing iterations
-        #[arg(default_value_t = 20, long, short = 'n', value_name = "NUM")]
-        iterations: usize,
-
-        /// Smoothing method (Laplace | Taubin) [default: Taubin]
-        #[arg(long, short, value_name = "NAME")]
-        method: Option<String>,
-
-        /// Pass-band frequency (for Taubin only)
-        #[arg(default_value_t = 0.1, long, short = 'k', value_name = "FREQ")]
-        pass_band: f64,
-
-        /// Scaling parameter for all smoothing methods
-        #[arg(default_value_t = 0.6307, long, short, value_name = "SCALE")]
-        scale: f64,
+        #[command(flatten)]
+        smoothing: SmoothingArgs,
 
         /// Quality metrics output file (csv | npy)
         #[arg(long, value_name = "FILE")]
@@ -218,6 +431,7 @@ enum Commands {
     },
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum ConvertSubcommand {
     /// Converts mesh file types (inp | stl) -> (exo | mesh | stl | vtk)
@@ -263,11 +477,101 @@ struct ConvertSegmentationArgs {
     #[arg(long, short = 'z', value_name = "NEL")]
     nelz: Option<usize>,
 
+    #[command(flatten)]
+    edit: SegmentationEditArgs,
+
     /// Pass to quiet the terminal output
     #[arg(action, long, short)]
     quiet: bool,
 }
 
+#[derive(clap::Args)]
+struct SegmentationEditArgs {
+    /// Voxel ID map applied before the edits (OLD:NEW,... | FILE.csv | contiguous)
+    #[arg(long, value_delimiter = ',', value_name = "MAP")]
+    relabel: Vec<String>,
+
+    #[command(flatten)]
+    voxels: VoxelEditArgs,
+
+    /// Scaling (> 0.0) in the x-direction, updated by the edits
+    #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+    xscale: f64,
+
+    /// Scaling (> 0.0) in the y-direction, updated by the edits
+    #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+    yscale: f64,
+
+    /// Scaling (> 0.0) in the z-direction, updated by the edits
+    #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
+    zscale: f64,
+
+    /// Translation in the x-direction, updated by the edits
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        value_name = "VAL"
+    )]
+    xtranslate: f64,
+
+    /// Translation in the y-direction, updated by the edits
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        value_name = "VAL"
+    )]
+    ytranslate: f64,
+
+    /// Translation in the z-direction, updated by the edits
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        value_name = "VAL"
+    )]
+    ztranslate: f64,
+}
+
+#[derive(clap::Args)]
+struct VoxelEditArgs {
+    /// Crop to voxels XMIN,YMIN,ZMIN up to but excluding XMAX,YMAX,ZMAX
+    #[arg(long, value_delimiter = ',', value_name = "INDICES")]
+    crop: Vec<usize>,
+
+    /// Pad each side with NUM voxels, or with XLO,YLO,ZLO,XHI,YHI,ZHI voxels
+    #[arg(long, value_delimiter = ',', value_name = "NUM")]
+    pad: Vec<usize>,
+
+    /// Voxel ID used for padding
+    #[arg(default_value_t = 0, long, value_name = "ID")]
+    pad_id: u8,
+
+    /// Axes to flip (x | y | z)
+    #[arg(long, value_delimiter = ',', value_name = "AXIS")]
+    flip: Vec<String>,
+
+    /// Permutation of the axes, such as zyx to swap the x and z axes
+    #[arg(long, value_name = "AXES")]
+    permute: Option<String>,
+
+    /// Downsample by a majority vote over blocks of FACTOR voxels per direction,
+    /// where a partial block at the end of a direction extends the extent
+    #[arg(long, value_name = "FACTOR")]
+    downsample: Option<usize>,
+}
+
+impl VoxelEditArgs {
+    fn edits(&self) -> bool {
+        !self.crop.is_empty()
+            || !self.pad.is_empty()
+            || !self.flip.is_empty()
+            || self.permute.is_some()
+            || self.downsample.is_some()
+    }
+}
+
 #[derive(Subcommand)]
 enum MeshSubcommand {
     /// Creates an all-hexahedral mesh from a segmentation
@@ -309,6 +613,13 @@ struct MeshHexArgs {
     #[arg(long, num_args = 1.., short, value_delimiter = ' ', value_name = "ID")]
     remove: Option<Vec<usize>>,
 
+    /// Voxel ID map applied before removal (OLD:NEW,... | FILE.csv | contiguous)
+    #[arg(long, value_delimiter = ',', value_name = "MAP")]
+    relabel: Vec<String>,
+
+    #[command(flatten)]
+    edit: Box<VoxelEditArgs>,
+
     /// Scaling (> 0.0) in the x-direction, applied before translation
     #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
     xscale: f64,
@@ -359,6 +670,10 @@ struct MeshHexArgs {
     /// Pass to mesh using dualization
     #[arg(action, hide = true, long)]
     dual: bool,
+
+    /// Pass to store the voxels in chunks and stream the mesh to an inp file
+    #[arg(action, long)]
+    chunked: bool,
 }
 
 #[derive(clap::Args)]
@@ -394,6 +709,13 @@ struct MeshTriArgs {
     #[arg(long, num_args = 1.., short, value_delimiter = ' ', value_name = "ID")]
     remove: Option<Vec<usize>>,
 
+    /// Voxel ID map applied before removal (OLD:NEW,... | FILE.csv | contiguous)
+    #[arg(long, value_delimiter = ',', value_name = "MAP")]
+    relabel: Vec<String>,
+
+    #[command(flatten)]
+    edit: Box<VoxelEditArgs>,
+
     /// Scaling (> 0.0) in the x-direction, applied before translation
     #[arg(default_value_t = 1.0, long, value_name = "SCALE")]
     xscale: f64,
@@ -446,27 +768,104 @@ struct MeshTriArgs {
 #[derive(Subcommand, Debug)]
 enum MeshSmoothCommands {
     /// Applies smoothing to the mesh before output
-    Smooth {
-        /// Pass to enable hierarchical control
-        #[arg(action, long, short = 'c')]
-        hierarchical: bool,
+    Smooth(SmoothingArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct SmoothingArgs {
+    /// Pass to enable hierarchical control
+    #[arg(action, long, short = 'c')]
+    hierarchical: bool,
+
+    /// Number of smoothing iterations
+    #[arg(default_value_t = 20, long, short = 'n', value_name = "NUM")]
+    iterations: usize,
+
+    /// Smoothing method (HC | Laplace | Taubin) [default: Taubin]
+    #[arg(long, short, value_name = "NAME")]
+    method: Option<String>,
 
-        /// Number of smoothing iterations
-        #[arg(default_value_t = 20, long, short = 'n', value_name = "NUM")]
-        iterations: usize,
+    /// Pass-band frequency (for Taubin only)
+    #[arg(default_value_t = 0.1, long, short = 'k', value_name = "FREQ")]
+    pass_band: f64,
 
-        /// Smoothing method (Laplace | Taubin) [default: Taubin]
-        #[arg(long, short, value_name = "NAME")]
-        method: Option<String>,
+    /// Weight of the original positions (for HC only)
+    #[arg(default_value_t = 0.1, long, value_name = "ALPHA")]
+    alpha: f64,
 
-        /// Pass-band frequency (for Taubin only)
-        #[arg(default_value_t = 0.1, long, short = 'k', value_name = "FREQ")]
-        pass_band: f64,
+    /// Weight of the push-back toward the original positions (for HC only)
+    #[arg(default_value_t = 0.6, long, value_name = "BETA")]
+    beta: f64,
 
-        /// Scaling parameter for all smoothing methods
-        #[arg(default_value_t = 0.6307, long, short, value_name = "SCALE")]
-        scale: f64,
-    },
+    /// Scaling parameter for all smoothing methods
+    #[arg(default_value_t = 0.6307, long, short, value_name = "SCALE")]
+    scale: f64,
+
+    /// Pass to preserve the volume of each block (closed surface for triangles) while smoothing
+    #[arg(action, long)]
+    preserve_volume: bool,
+
+    /// Pass to backtrack smoothing moves that would invert elements
+    #[arg(action, long)]
+    safeguard: bool,
+
+    /// Dihedral angle in degrees above which exterior edges are preserved as features
+    #[arg(long, value_name = "DEG")]
+    feature_angle: Option<f64>,
+
+    /// Per-block smoothing as BLOCK:METHOD[:ITERATIONS[:SCALE]] or BLOCK:frozen
+    #[arg(long, value_name = "SPEC")]
+    block: Vec<String>,
+
+    /// Fix the nodes within the box XMIN,YMIN,ZMIN,XMAX,YMAX,ZMAX
+    #[arg(
+        allow_hyphen_values = true,
+        long,
+        value_delimiter = ',',
+        value_name = "COORDS"
+    )]
+    fix_box: Vec<f64>,
+
+    /// Fix the nodes of the named node set (*NSET) in the Abaqus input file
+    #[arg(long, value_name = "NAME")]
+    fix_set: Option<String>,
+
+    /// Prescribed nodes file (csv | npy) of node numbers, optionally with target coordinates,
+    /// where a csv file may start with a node or node,x,y,z header
+    #[arg(long, value_name = "FILE")]
+    prescribed: Option<String>,
+
+    /// Prescribe the nodes of the given block
+    #[arg(long, value_name = "BLOCK")]
+    prescribe_block: Vec<u8>,
+
+    /// Pass to prescribe the nodes on the exterior surface
+    #[arg(action, long)]
+    prescribe_surface: bool,
+
+    /// Reference surface (stl) to project the exterior nodes onto after each iteration
+    #[arg(long, value_name = "FILE")]
+    reference: Option<String>,
+
+    /// Pass to also project the interface nodes onto the reference surface
+    #[arg(action, long)]
+    project_interface: bool,
+
+    /// Criterion to stop smoothing before the number of iterations (max | rms | metric name)
+    #[arg(long, value_name = "CRITERION")]
+    convergence: Option<String>,
+
+    /// Tolerance on the nodal displacement or metric improvement per iteration
+    #[arg(default_value_t = 1e-4, long, value_name = "TOL")]
+    tolerance: f64,
+
+    /// Number of untangling optimization iterations after smoothing (for hexes only)
+    #[arg(long, value_name = "NUM")]
+    optimize: Option<usize>,
+
+    /// Target minimum scaled Jacobian for the optimization
+    #[arg(default_value_t = 0.5, long, value_name = "SJ")]
+    target: f64,
 }
 
 struct ErrorWrapper {
@@ -511,10 +910,18 @@ impl From<ReadNpyError> for ErrorWrapper {
     }
 }
 
-impl From<String> for ErrorWrapper {
-    fn from(message: String) -> ErrorWrapper {
-        ErrorWrapper { message }
-    }
+impl From<ViewNpyError> for ErrorWrapper {
+    fn from(error: ViewNpyError) -> ErrorWrapper {
+        ErrorWrapper {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<String> for ErrorWrapper {
+    fn from(message: String) -> ErrorWrapper {
+        ErrorWrapper { message }
+    }
 }
 
 impl From<&str> for ErrorWrapper {
@@ -536,6 +943,7 @@ impl From<WriteNpyError> for ErrorWrapper {
 #[allow(clippy::large_enum_variant)]
 enum InputTypes {
     Abaqus(HexahedralFiniteElements),
+    AbaqusTri(TriangularFiniteElements),
     Npy(Voxels),
     Spn(Voxels),
     Stl(Tessellation),
@@ -570,10 +978,29 @@ fn invalid_output(file: &str, extension: Option<&str>) -> Result<(), ErrorWrappe
     ))?)
 }
 
+fn invalid_hexahedral_output(file: &str) -> Result<(), ErrorWrapper> {
+    Ok(Err(format!(
+        "Cannot write hexahedral elements to output file {}",
+        file
+    ))?)
+}
+
 fn main() -> Result<(), ErrorWrapper> {
     let time = Instant::now();
     let is_quiet;
     let args = Args::parse();
+    if let Some(threads) = args.threads {
+        #[cfg(feature = "parallel")]
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| error.to_string())?;
+        #[cfg(not(feature = "parallel"))]
+        Err(format!(
+            "Need to build with the parallel feature to use {} threads",
+            threads
+        ))?
+    }
     let result = match args.command {
         Some(Commands::Convert { subcommand }) => match subcommand {
             ConvertSubcommand::Mesh(args) => {
@@ -588,6 +1015,7 @@ fn main() -> Result<(), ErrorWrapper> {
                     args.nelx,
                     args.nely,
                     args.nelz,
+                    args.edit,
                     args.quiet,
                 )
             }
@@ -596,13 +1024,73 @@ fn main() -> Result<(), ErrorWrapper> {
             input,
             output,
             min,
+            min_id,
+            connectivity,
+            protect,
+            report,
+            method,
+            nelx,
+            nely,
+            nelz,
+            quiet,
+        }) => {
+            is_quiet = quiet;
+            defeature(
+                input,
+                output,
+                min,
+                min_id,
+                connectivity,
+                protect,
+                report,
+                method,
+                nelx,
+                nely,
+                nelz,
+                quiet,
+            )
+        }
+        Some(Commands::Info {
+            input,
+            json,
+            nelx,
+            nely,
+            nelz,
+            xscale,
+            yscale,
+            zscale,
+            xtranslate,
+            ytranslate,
+            ztranslate,
+            quiet,
+        }) => {
+            is_quiet = quiet || json;
+            info(
+                input, json, nelx, nely, nelz, xscale, yscale, zscale, xtranslate, ytranslate,
+                ztranslate, quiet,
+            )
+        }
+        Some(Commands::Inspect {
+            input,
+            output,
+            keep,
+            remove,
             nelx,
             nely,
             nelz,
+            xscale,
+            yscale,
+            zscale,
+            xtranslate,
+            ytranslate,
+            ztranslate,
             quiet,
         }) => {
             is_quiet = quiet;
-            defeature(input, output, min, nelx, nely, nelz, quiet)
+            inspect(
+                input, output, keep, remove, nelx, nely, nelz, xscale, yscale, zscale, xtranslate,
+                ytranslate, ztranslate, quiet,
+            )
         }
         Some(Commands::Mesh { subcommand }) => match subcommand {
             MeshSubcommand::Hex(args) => {
@@ -616,6 +1104,8 @@ fn main() -> Result<(), ErrorWrapper> {
                     args.nely,
                     args.nelz,
                     args.remove,
+                    args.relabel,
+                    *args.edit,
                     args.xscale,
                     args.yscale,
                     args.zscale,
@@ -625,6 +1115,7 @@ fn main() -> Result<(), ErrorWrapper> {
                     args.metrics,
                     args.quiet,
                     args.dual,
+                    args.chunked,
                 )
             }
             MeshSubcommand::Tri(args) => {
@@ -638,6 +1129,8 @@ fn main() -> Result<(), ErrorWrapper> {
                     args.nely,
                     args.nelz,
                     args.remove,
+                    args.relabel,
+                    *args.edit,
                     args.xscale,
                     args.yscale,
                     args.zscale,
@@ -652,10 +1145,29 @@ fn main() -> Result<(), ErrorWrapper> {
         Some(Commands::Metrics {
             input,
             output,
+            names,
             quiet,
         }) => {
             is_quiet = quiet;
-            metrics(input, output, quiet)
+            metrics(input, output, names, quiet)
+        }
+        Some(Commands::Morph {
+            input,
+            output,
+            operation,
+            ids,
+            element,
+            radius,
+            background,
+            nelx,
+            nely,
+            nelz,
+            quiet,
+        }) => {
+            is_quiet = quiet;
+            morph(
+                input, output, operation, ids, element, radius, background, nelx, nely, nelz, quiet,
+            )
         }
         Some(Commands::Octree {
             input,
@@ -664,6 +1176,8 @@ fn main() -> Result<(), ErrorWrapper> {
             nely,
             nelz,
             remove,
+            relabel,
+            edit,
             xscale,
             yscale,
             zscale,
@@ -676,33 +1190,19 @@ fn main() -> Result<(), ErrorWrapper> {
         }) => {
             is_quiet = quiet;
             octree(
-                input, output, nelx, nely, nelz, remove, xscale, yscale, zscale, xtranslate,
-                ytranslate, ztranslate, quiet, pair, strong,
+                input, output, nelx, nely, nelz, remove, relabel, edit, xscale, yscale, zscale,
+                xtranslate, ytranslate, ztranslate, quiet, pair, strong,
             )
         }
         Some(Commands::Smooth {
             input,
             output,
-            iterations,
-            method,
-            hierarchical,
-            pass_band,
-            scale,
+            smoothing,
             metrics,
             quiet,
         }) => {
             is_quiet = quiet;
-            smooth(
-                input,
-                output,
-                iterations,
-                method,
-                hierarchical,
-                pass_band,
-                scale,
-                metrics,
-                quiet,
-            )
+            smooth(input, output, &smoothing, metrics, quiet)
         }
         None => return Ok(()),
     };
@@ -717,6 +1217,14 @@ fn convert_mesh(input: String, output: String, quiet: bool) -> Result<(), ErrorW
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
     match read_input(&input, None, None, None, quiet)? {
         InputTypes::Abaqus(finite_elements) => match output_extension {
+            Some("exo") => write_output(output, OutputTypes::Exodus(finite_elements), quiet),
+            Some("inp") => write_output(output, OutputTypes::Abaqus(finite_elements), quiet),
+            Some("mesh") => write_output(output, OutputTypes::Mesh(finite_elements), quiet),
+            Some("stl") => invalid_hexahedral_output(&output),
+            Some("vtk") => write_output(output, OutputTypes::Vtk(finite_elements), quiet),
+            _ => invalid_output(&output, output_extension),
+        },
+        InputTypes::AbaqusTri(finite_elements) => match output_extension {
             Some("exo") => write_output(output, OutputTypes::Exodus(finite_elements), quiet),
             Some("inp") => write_output(output, OutputTypes::Abaqus(finite_elements), quiet),
             Some("mesh") => write_output(output, OutputTypes::Mesh(finite_elements), quiet),
@@ -757,79 +1265,493 @@ fn convert_segmentation(
     nelx: Option<usize>,
     nely: Option<usize>,
     nelz: Option<usize>,
+    edit: SegmentationEditArgs,
     quiet: bool,
 ) -> Result<(), ErrorWrapper> {
     let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
     match read_input(&input, nelx, nely, nelz, quiet)? {
-        InputTypes::Abaqus(_finite_elements) => invalid_input(&input, input_extension),
-        InputTypes::Npy(voxels) | InputTypes::Spn(voxels) => match output_extension {
-            Some("spn") => write_output(
-                output,
-                OutputTypes::<8, HexahedralFiniteElements>::Spn(voxels),
-                quiet,
-            ),
-            Some("npy") => write_output(
-                output,
-                OutputTypes::<8, HexahedralFiniteElements>::Npy(voxels),
-                quiet,
-            ),
-            _ => invalid_output(&output, output_extension),
-        },
+        InputTypes::Abaqus(_) | InputTypes::AbaqusTri(_) => invalid_input(&input, input_extension),
+        InputTypes::Npy(voxels) | InputTypes::Spn(voxels) => {
+            let voxels = edit_segmentation(voxels, edit, quiet)?;
+            match output_extension {
+                Some("spn") => write_output(
+                    output,
+                    OutputTypes::<8, HexahedralFiniteElements>::Spn(voxels),
+                    quiet,
+                ),
+                Some("npy") => write_output(
+                    output,
+                    OutputTypes::<8, HexahedralFiniteElements>::Npy(voxels),
+                    quiet,
+                ),
+                _ => invalid_output(&output, output_extension),
+            }
+        }
         InputTypes::Stl(_voxels) => invalid_input(&input, input_extension),
     }
 }
 
+fn edit_segmentation(
+    voxels: Voxels,
+    edit: SegmentationEditArgs,
+    quiet: bool,
+) -> Result<Voxels, ErrorWrapper> {
+    let voxels = relabel_voxels(voxels, edit.relabel, quiet)?;
+    let mut scale = Scale::from([edit.xscale, edit.yscale, edit.zscale]);
+    let mut translate = Translate::from([edit.xtranslate, edit.ytranslate, edit.ztranslate]);
+    let voxels = edit_voxels(voxels, &edit.voxels, &mut scale, &mut translate, quiet)?;
+    if !quiet && edit.voxels.edits() {
+        println!(
+            "             \x1b[2mScale: [{}, {}, {}], translate: [{}, {}, {}]\x1b[0m",
+            scale.x(),
+            scale.y(),
+            scale.z(),
+            translate.x(),
+            translate.y(),
+            translate.z()
+        );
+    }
+    Ok(voxels)
+}
+
+fn edit_voxels(
+    mut voxels: Voxels,
+    edit: &VoxelEditArgs,
+    scale: &mut Scale,
+    translate: &mut Translate,
+    quiet: bool,
+) -> Result<Voxels, ErrorWrapper> {
+    if !edit.edits() {
+        return Ok(voxels);
+    }
+    let axis = |name: char| match name {
+        'x' => Ok(0),
+        'y' => Ok(1),
+        'z' => Ok(2),
+        _ => Err(format!("Invalid axis {}, need to specify x, y, or z", name)),
+    };
+    let time = Instant::now();
+    if !quiet {
+        println!("     \x1b[1;96mEditing\x1b[0m segmentation");
+    }
+    if !edit.crop.is_empty() {
+        if edit.crop.len() != NSD * 2 {
+            Err("Need to specify 6 values for cropping")?
+        }
+        voxels = voxels.crop(
+            [edit.crop[0], edit.crop[1], edit.crop[2]],
+            [edit.crop[3], edit.crop[4], edit.crop[5]],
+            scale,
+            translate,
+        )?;
+    }
+    if !edit.pad.is_empty() {
+        let (lower, upper) = match edit.pad.len() {
+            1 => ([edit.pad[0]; NSD], [edit.pad[0]; NSD]),
+            6 => (
+                [edit.pad[0], edit.pad[1], edit.pad[2]],
+                [edit.pad[3], edit.pad[4], edit.pad[5]],
+            ),
+            _ => Err("Need to specify 1 or 6 values for padding")?,
+        };
+        voxels = voxels.pad(lower, upper, edit.pad_id, scale, translate);
+    }
+    for axes in edit.flip.iter() {
+        for name in axes.chars() {
+            voxels = voxels.flip(axis(name)?)?;
+        }
+    }
+    if let Some(permutation) = &edit.permute {
+        let axes = permutation
+            .chars()
+            .map(axis)
+            .collect::<Result<Vec<usize>, String>>()?;
+        voxels = voxels.permute(
+            axes.try_into()
+                .map_err(|_| format!("Invalid permutation {} of the axes", permutation))?,
+            scale,
+            translate,
+        )?;
+    }
+    if let Some(factor) = edit.downsample {
+        voxels = voxels.downsample(factor, scale)?;
+    }
+    if !quiet {
+        let shape = voxels.get_data().shape();
+        println!(
+            "        \x1b[1;92mDone\x1b[0m {:?} \x1b[2m[nelx: {}, nely: {}, nelz: {}]\x1b[0m",
+            time.elapsed(),
+            shape[0],
+            shape[1],
+            shape[2]
+        );
+    }
+    Ok(voxels)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn defeature(
     input: String,
     output: String,
     min: usize,
+    min_id: Vec<String>,
+    connectivity: usize,
+    protect: Vec<u8>,
+    report: Option<String>,
+    method: String,
     nelx: Option<usize>,
     nely: Option<usize>,
     nelz: Option<usize>,
     quiet: bool,
 ) -> Result<(), ErrorWrapper> {
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
+    let defeaturing = Defeaturing {
+        adjacency: connectivity.try_into()?,
+        method: match method.as_str() {
+            "octree" => DefeaturingMethod::Octree,
+            "union-find" => DefeaturingMethod::UnionFind,
+            _ => Err(format!(
+                "Invalid defeaturing method {} specified, need to specify octree or union-find",
+                method
+            ))?,
+        },
+        minimum: min,
+        minimums: min_id
+            .iter()
+            .map(|entry| {
+                entry
+                    .split_once(':')
+                    .and_then(|(id, minimum)| {
+                        Some((id.trim().parse().ok()?, minimum.trim().parse().ok()?))
+                    })
+                    .ok_or(format!("Invalid minimum {}, need ID:MIN", entry))
+            })
+            .collect::<Result<_, String>>()?,
+        protected: protect,
+    };
     match read_input(&input, nelx, nely, nelz, quiet)? {
-        InputTypes::Npy(mut voxels) | InputTypes::Spn(mut voxels) => match output_extension {
-            Some("npy") => {
-                let time = Instant::now();
-                if !quiet {
+        InputTypes::Npy(voxels) | InputTypes::Spn(voxels) => {
+            let time = Instant::now();
+            if !quiet {
+                println!(
+                    " \x1b[1;96mDefeaturing\x1b[0m clusters of {} voxels or less",
+                    min
+                );
+            }
+            let (voxels, reassignments) = voxels.defeature_with(&defeaturing);
+            if !quiet {
+                println!(
+                    "        \x1b[1;92mDone\x1b[0m {:?} \x1b[2m[{} clusters reassigned]\x1b[0m",
+                    time.elapsed(),
+                    reassignments.len()
+                );
+                let mut pairs = reassignments
+                    .iter()
+                    .map(|reassignment| (reassignment.block, reassignment.new_block))
+                    .collect::<Vec<(u8, u8)>>();
+                pairs.sort();
+                pairs.dedup();
+                pairs.iter().for_each(|&(block, new_block)| {
+                    let (clusters, voxels) = reassignments
+                        .iter()
+                        .filter(|reassignment| {
+                            reassignment.block == block && reassignment.new_block == new_block
+                        })
+                        .fold((0, 0), |(clusters, voxels), reassignment| {
+                            (clusters + 1, voxels + reassignment.voxels)
+                        });
                     println!(
-                        " \x1b[1;96mDefeaturing\x1b[0m clusters of {} voxels or less",
-                        min
-                    );
-                }
-                voxels = voxels.defeature(min);
-                if !quiet {
-                    println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
-                }
-                write_output(
+                        "             \x1b[2mReassigned {} clusters ({} voxels) from {} to {}\x1b[0m",
+                        clusters, voxels, block, new_block
+                    )
+                });
+            }
+            if let Some(report) = report {
+                let mut file = BufWriter::new(File::create(&report)?);
+                writeln!(file, "block,new_block,voxels,x,y,z")?;
+                reassignments.iter().try_for_each(|reassignment| {
+                    writeln!(
+                        file,
+                        "{},{},{},{},{},{}",
+                        reassignment.block,
+                        reassignment.new_block,
+                        reassignment.voxels,
+                        reassignment.minimum[0],
+                        reassignment.minimum[1],
+                        reassignment.minimum[2]
+                    )
+                })?;
+            }
+            match output_extension {
+                Some("npy") => write_output(
                     output,
                     OutputTypes::<8, HexahedralFiniteElements>::Npy(voxels),
                     quiet,
-                )
-            }
-            Some("spn") => {
-                let time = Instant::now();
-                if !quiet {
-                    println!(
-                        " \x1b[1;96mDefeaturing\x1b[0m clusters of {} voxels or less",
-                        min
-                    );
-                }
-                voxels = voxels.defeature(min);
-                if !quiet {
-                    println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
-                }
-                write_output(
+                ),
+                Some("spn") => write_output(
                     output,
                     OutputTypes::<8, HexahedralFiniteElements>::Spn(voxels),
                     quiet,
-                )
+                ),
+                _ => invalid_output(&output, output_extension),
             }
-            _ => invalid_output(&output, output_extension),
-        },
+        }
+        _ => {
+            let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
+            Err(format!(
+                "Invalid extension .{} from input file {}",
+                input_extension.unwrap_or("UNDEFINED"),
+                input
+            ))?
+        }
+    }
+}
+
+enum MeshBasis {
+    Leaves,
+    Surfaces,
+    Voxels,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn info(
+    input: String,
+    json: bool,
+    nelx: Option<usize>,
+    nely: Option<usize>,
+    nelz: Option<usize>,
+    xscale: f64,
+    yscale: f64,
+    zscale: f64,
+    xtranslate: f64,
+    ytranslate: f64,
+    ztranslate: f64,
+    quiet: bool,
+) -> Result<(), ErrorWrapper> {
+    let scale = Scale::from([xscale, yscale, zscale]);
+    let translate = Translate::from([xtranslate, ytranslate, ztranslate]);
+    match read_input(&input, nelx, nely, nelz, quiet || json)? {
+        InputTypes::Abaqus(finite_elements) => {
+            info_mesh(&input, "hexahedral", &finite_elements, json)
+        }
+        InputTypes::AbaqusTri(finite_elements) => {
+            info_mesh(&input, "triangular", &finite_elements, json)
+        }
+        InputTypes::Npy(voxels) | InputTypes::Spn(voxels) => {
+            info_segmentation(&input, &voxels, &scale, &translate, json)
+        }
+        InputTypes::Stl(tessellation) => info_mesh(
+            &input,
+            "triangular",
+            &tessellation.into_finite_elements(),
+            json,
+        ),
+    }
+    Ok(())
+}
+
+fn info_mesh<const N: usize, T>(input: &str, kind: &str, fem: &T, json: bool)
+where
+    T: FiniteElementMethods<N>,
+{
+    let element_blocks = fem.get_element_blocks();
+    let coordinates = fem.get_nodal_coordinates();
+    let mut minimum = [f64::INFINITY; NSD];
+    let mut maximum = [f64::NEG_INFINITY; NSD];
+    coordinates.iter().for_each(|coordinate| {
+        (0..NSD).for_each(|i| {
+            minimum[i] = minimum[i].min(coordinate[i]);
+            maximum[i] = maximum[i].max(coordinate[i]);
+        })
+    });
+    let mut blocks = element_blocks.clone();
+    blocks.sort();
+    blocks.dedup();
+    let volumes = fem.calculate_block_volumes().ok();
+    let blocks = blocks
+        .into_iter()
+        .enumerate()
+        .map(|(index, block)| {
+            (
+                block,
+                element_blocks
+                    .iter()
+                    .filter(|&&entry| entry == block)
+                    .count(),
+                volumes.as_ref().map(|volumes| volumes[index].1),
+            )
+        })
+        .collect::<Vec<(u8, usize, Option<f64>)>>();
+    let volume = volumes.map(|volumes| volumes.iter().map(|(_, volume)| volume).sum::<f64>());
+    if json {
+        println!(
+            "{{\"file\": {}, \"type\": \"{}\", \"nodes\": {}, \"elements\": {}, \"bounding_box\": {{\"minimum\": {}, \"maximum\": {}}}, \"volume\": {}, \"blocks\": [{}]}}",
+            json_string(input),
+            kind,
+            coordinates.len(),
+            element_blocks.len(),
+            json_point(&minimum),
+            json_point(&maximum),
+            json_number(volume),
+            blocks
+                .iter()
+                .map(|(block, elements, volume)| format!(
+                    "{{\"id\": {}, \"elements\": {}, \"volume\": {}}}",
+                    block,
+                    elements,
+                    json_number(*volume)
+                ))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    } else {
+        println!("File: {}", input);
+        println!("Type: {}", kind);
+        println!("Nodes: {}", coordinates.len());
+        println!("Elements: {}", element_blocks.len());
+        println!("Blocks: {}", blocks.len());
+        println!(
+            "Bounding box: {} to {}",
+            format_point(&minimum),
+            format_point(&maximum)
+        );
+        match volume {
+            Some(volume) => println!("Volume: {:.6e}", volume),
+            None => println!("Volume: undefined for an open surface"),
+        }
+        blocks
+            .iter()
+            .for_each(|(block, elements, volume)| match volume {
+                Some(volume) => println!(
+                    "Block {}: {} elements, volume {:.6e}",
+                    block, elements, volume
+                ),
+                None => println!("Block {}: {} elements", block, elements),
+            });
+    }
+}
+
+fn info_segmentation(
+    input: &str,
+    voxels: &Voxels,
+    scale: &Scale,
+    translate: &Translate,
+    json: bool,
+) {
+    let data = voxels.get_data();
+    let shape = data.shape();
+    let mut counts = [0_usize; 256];
+    data.iter().for_each(|&voxel| counts[voxel as usize] += 1);
+    let histogram = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(id, count)| (id, *count))
+        .collect::<Vec<(usize, usize)>>();
+    let minimum = [*translate.x(), *translate.y(), *translate.z()];
+    let maximum = [
+        shape[0] as f64 * scale.x() + translate.x(),
+        shape[1] as f64 * scale.y() + translate.y(),
+        shape[2] as f64 * scale.z() + translate.z(),
+    ];
+    if json {
+        println!(
+            "{{\"file\": {}, \"type\": \"segmentation\", \"dimensions\": {:?}, \"voxels\": {}, \"scale\": {}, \"translate\": {}, \"extent\": {{\"minimum\": {}, \"maximum\": {}}}, \"histogram\": [{}]}}",
+            json_string(input),
+            shape,
+            data.len(),
+            json_point(&[*scale.x(), *scale.y(), *scale.z()]),
+            json_point(&[*translate.x(), *translate.y(), *translate.z()]),
+            json_point(&minimum),
+            json_point(&maximum),
+            histogram
+                .iter()
+                .map(|(id, count)| format!("{{\"id\": {}, \"voxels\": {}}}", id, count))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    } else {
+        println!("File: {}", input);
+        println!("Type: segmentation");
+        println!(
+            "Dimensions: [{}, {}, {}] ({} voxels)",
+            shape[0],
+            shape[1],
+            shape[2],
+            data.len()
+        );
+        println!(
+            "Extent: {} to {}",
+            format_point(&minimum),
+            format_point(&maximum)
+        );
+        histogram.iter().for_each(|(id, count)| {
+            println!(
+                "Voxel ID {}: {} voxels ({:.2}%)",
+                id,
+                count,
+                100.0 * *count as f64 / data.len() as f64
+            )
+        });
+    }
+}
+
+fn format_point(point: &[f64; NSD]) -> String {
+    format!("[{:.6e}, {:.6e}, {:.6e}]", point[0], point[1], point[2])
+}
+
+fn json_point(point: &[f64; NSD]) -> String {
+    format!(
+        "[{}, {}, {}]",
+        json_number(Some(point[0])),
+        json_number(Some(point[1])),
+        json_number(Some(point[2]))
+    )
+}
+
+fn json_string(string: &str) -> String {
+    let mut escaped = String::from("\"");
+    string.chars().for_each(|character| match character {
+        '"' => escaped.push_str("\\\""),
+        '\\' => escaped.push_str("\\\\"),
+        character if character.is_control() => {
+            escaped.push_str(&format!("\\u{:04x}", character as u32))
+        }
+        character => escaped.push(character),
+    });
+    escaped.push('"');
+    escaped
+}
+
+fn json_number(value: Option<f64>) -> String {
+    match value {
+        Some(value) if value.is_finite() => format!("{:?}", value),
+        _ => "null".to_string(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn inspect(
+    input: String,
+    output: Option<String>,
+    mut keep: Vec<u8>,
+    remove: Vec<u8>,
+    nelx: Option<usize>,
+    nely: Option<usize>,
+    nelz: Option<usize>,
+    xscale: f64,
+    yscale: f64,
+    zscale: f64,
+    xtranslate: f64,
+    ytranslate: f64,
+    ztranslate: f64,
+    quiet: bool,
+) -> Result<(), ErrorWrapper> {
+    let scale = Scale::from([xscale, yscale, zscale]);
+    let translate = Translate::from([xtranslate, ytranslate, ztranslate]);
+    let voxels = match read_input(&input, nelx, nely, nelz, quiet)? {
+        InputTypes::Npy(voxels) | InputTypes::Spn(voxels) => voxels,
         _ => {
             let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
             Err(format!(
@@ -838,13 +1760,86 @@ fn defeature(
                 input
             ))?
         }
+    };
+    let time = Instant::now();
+    if !quiet {
+        println!("  \x1b[1;96mInspecting\x1b[0m connected components");
+    }
+    let components = voxels.components();
+    let mut ids = components
+        .get_components()
+        .iter()
+        .map(|component| component.get_id())
+        .collect::<Vec<u8>>();
+    ids.sort();
+    ids.dedup();
+    if !quiet {
+        println!(
+            "        \x1b[1;92mDone\x1b[0m {:?} \x1b[2m[{} voxel IDs, {} components]\x1b[0m",
+            time.elapsed(),
+            ids.len(),
+            components.get_components().len()
+        );
+    }
+    let point = |location: [f64; NSD]| {
+        format_point(&[
+            location[0] * scale.x() + translate.x(),
+            location[1] * scale.y() + translate.y(),
+            location[2] * scale.z() + translate.z(),
+        ])
+    };
+    ids.iter().for_each(|&id| {
+        let mut id_components = components
+            .get_components()
+            .iter()
+            .filter(|component| component.get_id() == id)
+            .collect::<Vec<_>>();
+        id_components.sort_by_key(|component| std::cmp::Reverse(component.get_voxels()));
+        println!(
+            "Voxel ID {}: {} component(s), {} voxels",
+            id,
+            id_components.len(),
+            id_components
+                .iter()
+                .map(|component| component.get_voxels())
+                .sum::<usize>()
+        );
+        id_components.iter().for_each(|component| {
+            println!(
+                "    {} voxels, bounding box {} to {}, centroid {}",
+                component.get_voxels(),
+                point(component.get_minimum().map(|index| index as f64)),
+                point(component.get_maximum().map(|index| index as f64)),
+                point(*component.get_centroid())
+            )
+        })
+    });
+    if let Some(output) = output {
+        let time = Instant::now();
+        if !quiet {
+            println!("   \x1b[1;96mSplitting\x1b[0m disconnected components");
+        }
+        keep.extend(remove);
+        let voxels = voxels.split_components(&keep)?;
+        if !quiet {
+            println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
+        }
+        let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
+        match output_extension {
+            Some("npy") => write_output(
+                output,
+                OutputTypes::<8, HexahedralFiniteElements>::Npy(voxels),
+                quiet,
+            )?,
+            Some("spn") => write_output(
+                output,
+                OutputTypes::<8, HexahedralFiniteElements>::Spn(voxels),
+                quiet,
+            )?,
+            _ => invalid_output(&output, output_extension)?,
+        }
     }
-}
-
-enum MeshBasis {
-    Leaves,
-    Surfaces,
-    Voxels,
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -857,6 +1852,8 @@ fn mesh_hex(
     nely: Option<usize>,
     nelz: Option<usize>,
     remove: Option<Vec<usize>>,
+    relabel: Vec<String>,
+    edit: VoxelEditArgs,
     xscale: f64,
     yscale: f64,
     zscale: f64,
@@ -866,6 +1863,7 @@ fn mesh_hex(
     metrics: Option<String>,
     quiet: bool,
     dual: bool,
+    chunked: bool,
 ) -> Result<(), ErrorWrapper> {
     let mut time = Instant::now();
     let remove = remove.map(|removed_blocks| {
@@ -874,8 +1872,38 @@ fn mesh_hex(
             .map(|entry| entry as u8)
             .collect()
     });
-    let scale = Scale::from([xscale, yscale, zscale]);
-    let translate = Translate::from([xtranslate, ytranslate, ztranslate]);
+    let mut scale = Scale::from([xscale, yscale, zscale]);
+    let mut translate = Translate::from([xtranslate, ytranslate, ztranslate]);
+    if chunked {
+        if defeature.is_some()
+            || !relabel.is_empty()
+            || edit.edits()
+            || dual
+            || smoothing.is_some()
+            || metrics.is_some()
+        {
+            Err(
+                "Cannot defeature, relabel, edit, dualize, smooth, or compute metrics of chunked voxels",
+            )?
+        }
+        if Path::new(&output).extension().and_then(|ext| ext.to_str()) != Some("inp") {
+            Err(format!(
+                "Chunked voxels can only be meshed to an inp output file, not {}",
+                output
+            ))?
+        }
+        let input_type = read_chunked_input(&input, nelx, nely, nelz, quiet)?;
+        if !quiet {
+            time = Instant::now();
+            mesh_print_info(MeshBasis::Voxels, &scale, &translate);
+            println!("     \x1b[1;96mWriting\x1b[0m {}", output);
+        }
+        input_type.write_inp(&output, remove, scale, translate)?;
+        if !quiet {
+            println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
+        }
+        return Ok(());
+    }
     let mut input_type = match read_input(&input, nelx, nely, nelz, quiet)? {
         InputTypes::Npy(voxels) => voxels,
         InputTypes::Spn(voxels) => voxels,
@@ -888,6 +1916,8 @@ fn mesh_hex(
             ))?
         }
     };
+    input_type = relabel_voxels(input_type, relabel, quiet)?;
+    input_type = edit_voxels(input_type, &edit, &mut scale, &mut translate, quiet)?;
     if let Some(min_num_voxels) = defeature {
         if !quiet {
             time = Instant::now();
@@ -926,29 +1956,11 @@ fn mesh_hex(
             output_type.get_nodal_coordinates().len()
         );
     }
-    if let Some(options) = smoothing {
-        match options {
-            MeshSmoothCommands::Smooth {
-                iterations,
-                method,
-                hierarchical,
-                pass_band,
-                scale,
-            } => {
-                apply_smoothing_method(
-                    &mut output_type,
-                    iterations,
-                    method,
-                    hierarchical,
-                    pass_band,
-                    scale,
-                    quiet,
-                )?;
-            }
-        }
+    if let Some(MeshSmoothCommands::Smooth(smoothing)) = smoothing {
+        apply_smoothing_method(&mut output_type, &smoothing, None, quiet)?;
     }
     if let Some(file) = metrics {
-        metrics_inner(&output_type, file, quiet)?
+        metrics_inner(&output_type, file, None, quiet)?
     }
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
     match output_extension {
@@ -971,6 +1983,8 @@ fn mesh_tri(
     nely: Option<usize>,
     nelz: Option<usize>,
     remove: Option<Vec<usize>>,
+    relabel: Vec<String>,
+    edit: VoxelEditArgs,
     xscale: f64,
     yscale: f64,
     zscale: f64,
@@ -987,8 +2001,8 @@ fn mesh_tri(
             .map(|entry| entry as u8)
             .collect()
     });
-    let scale = Scale::from([xscale, yscale, zscale]);
-    let translate = Translate::from([xtranslate, ytranslate, ztranslate]);
+    let mut scale = Scale::from([xscale, yscale, zscale]);
+    let mut translate = Translate::from([xtranslate, ytranslate, ztranslate]);
     let input_type = match read_input(&input, nelx, nely, nelz, quiet)? {
         InputTypes::Npy(voxels) => voxels,
         InputTypes::Spn(voxels) => voxels,
@@ -1001,6 +2015,8 @@ fn mesh_tri(
             ))?
         }
     };
+    let input_type = relabel_voxels(input_type, relabel, quiet)?;
+    let input_type = edit_voxels(input_type, &edit, &mut scale, &mut translate, quiet)?;
     if !quiet {
         time = Instant::now();
         if let Some(min_num_voxels) = defeature {
@@ -1035,29 +2051,11 @@ fn mesh_tri(
             output_type.get_nodal_coordinates().len()
         );
     }
-    if let Some(options) = smoothing {
-        match options {
-            MeshSmoothCommands::Smooth {
-                iterations,
-                method,
-                hierarchical,
-                pass_band,
-                scale,
-            } => {
-                apply_smoothing_method(
-                    &mut output_type,
-                    iterations,
-                    method,
-                    hierarchical,
-                    pass_band,
-                    scale,
-                    quiet,
-                )?;
-            }
-        }
+    if let Some(MeshSmoothCommands::Smooth(smoothing)) = smoothing {
+        apply_smoothing_method(&mut output_type, &smoothing, None, quiet)?;
     }
     if let Some(file) = metrics {
-        metrics_inner(&output_type, file, quiet)?
+        metrics_inner(&output_type, file, None, quiet)?
     }
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
     match output_extension {
@@ -1113,20 +2111,32 @@ fn mesh_print_info(basis: MeshBasis, scale: &Scale, translate: &Translate) {
     }
 }
 
-fn metrics(input: String, output: String, quiet: bool) -> Result<(), ErrorWrapper> {
-    let output_type = match read_input(&input, None, None, None, quiet)? {
-        InputTypes::Abaqus(finite_elements) => finite_elements,
+fn metrics(
+    input: String,
+    output: String,
+    names: Option<Vec<String>>,
+    quiet: bool,
+) -> Result<(), ErrorWrapper> {
+    match read_input(&input, None, None, None, quiet)? {
+        InputTypes::Abaqus(finite_elements) => {
+            metrics_inner(&finite_elements, output, names, quiet)
+        }
+        InputTypes::AbaqusTri(finite_elements) => {
+            metrics_inner(&finite_elements, output, names, quiet)
+        }
         InputTypes::Npy(_) | InputTypes::Spn(_) => {
             Err(format!("No metrics for segmentation file {}", input))?
         }
-        InputTypes::Stl(_) => todo!(),
-    };
-    metrics_inner(&output_type, output, quiet)
+        InputTypes::Stl(tessellation) => {
+            metrics_inner(&tessellation.into_finite_elements(), output, names, quiet)
+        }
+    }
 }
 
 fn metrics_inner<const N: usize, T>(
     fem: &T,
     output: String,
+    names: Option<Vec<String>>,
     quiet: bool,
 ) -> Result<(), ErrorWrapper>
 where
@@ -1136,13 +2146,179 @@ where
     if !quiet {
         println!("     \x1b[1;96mMetrics\x1b[0m {}", output);
     }
-    fem.write_metrics(&output)?;
+    if let Some(names) = names {
+        let selected = names
+            .iter()
+            .map(|name| Metric::try_from(name.as_str()))
+            .collect::<Result<Vec<Metric>, String>>()?;
+        fem.write_metrics_selected(&output, &selected)?;
+    } else {
+        fem.write_metrics(&output)?;
+    }
     if !quiet {
         println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
     }
     Ok(())
 }
 
+fn relabel_voxels(
+    voxels: Voxels,
+    relabel: Vec<String>,
+    quiet: bool,
+) -> Result<Voxels, ErrorWrapper> {
+    if relabel.is_empty() {
+        return Ok(voxels);
+    }
+    let time = Instant::now();
+    if !quiet {
+        println!("  \x1b[1;96mRelabeling\x1b[0m voxel IDs");
+    }
+    let id = |entry: &str| {
+        entry
+            .trim()
+            .parse::<u8>()
+            .map_err(|error| format!("Invalid voxel ID {}: {}", entry.trim(), error))
+    };
+    let voxels = if relabel == ["contiguous"] {
+        voxels.renumber()
+    } else if relabel.len() == 1 && relabel[0].ends_with(".csv") {
+        let file = &relabel[0];
+        let map = read_to_string(file)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                if index == 0 && line.replace(' ', "").eq_ignore_ascii_case("old,new") {
+                    return Ok(None);
+                }
+                match line.split(',').map(id).collect::<Result<Vec<u8>, String>>() {
+                    Ok(row) if row.len() == 2 => Ok(Some((row[0], row[1]))),
+                    _ => Err(format!(
+                        "Invalid entry {} on line {} of {}, need OLD,NEW voxel IDs \
+                        after an optional old,new header",
+                        line.trim(),
+                        index + 1,
+                        file
+                    )),
+                }
+            })
+            .filter_map(Result::transpose)
+            .collect::<Result<HashMap<u8, u8>, String>>()?;
+        voxels.relabel(&map)
+    } else {
+        let map = relabel
+            .iter()
+            .map(|pair| match pair.split_once(':') {
+                Some((old, new)) => Ok((id(old)?, id(new)?)),
+                None => Err(format!("Invalid voxel ID map {}, need OLD:NEW", pair)),
+            })
+            .collect::<Result<HashMap<u8, u8>, String>>()?;
+        voxels.relabel(&map)
+    };
+    if !quiet {
+        let mut present = [false; 256];
+        voxels
+            .get_data()
+            .iter()
+            .for_each(|&voxel| present[voxel as usize] = true);
+        let ids = (0..=u8::MAX)
+            .filter(|&id| present[id as usize])
+            .collect::<Vec<u8>>();
+        println!(
+            "        \x1b[1;92mDone\x1b[0m {:?} \x1b[2m[voxel IDs: {:?}]\x1b[0m",
+            time.elapsed(),
+            ids
+        );
+    }
+    Ok(voxels)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn morph(
+    input: String,
+    output: String,
+    operation: String,
+    ids: Vec<u8>,
+    element: String,
+    radius: usize,
+    background: u8,
+    nelx: Option<usize>,
+    nely: Option<usize>,
+    nelz: Option<usize>,
+    quiet: bool,
+) -> Result<(), ErrorWrapper> {
+    let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
+    let operation = match operation.as_str() {
+        "close" => Morphology::Close,
+        "dilate" => Morphology::Dilate,
+        "erode" => Morphology::Erode,
+        "fill" => Morphology::Fill,
+        "open" => Morphology::Open,
+        _ => Err(format!(
+            "Invalid operation {} specified, need to specify close, dilate, erode, fill, or open",
+            operation
+        ))?,
+    };
+    let element = match element.as_str() {
+        "ball" => StructuringElement::Ball(radius),
+        "cross" => StructuringElement::Cross(radius),
+        "cube" => StructuringElement::Cube(radius),
+        _ => Err(format!(
+            "Invalid structuring element {} specified, need to specify ball, cross, or cube",
+            element
+        ))?,
+    };
+    match read_input(&input, nelx, nely, nelz, quiet)? {
+        InputTypes::Npy(mut voxels) | InputTypes::Spn(mut voxels) => {
+            let ids = if ids.is_empty() {
+                let mut ids = voxels
+                    .get_data()
+                    .iter()
+                    .filter(|&&voxel| voxel != background)
+                    .copied()
+                    .collect::<Vec<u8>>();
+                ids.sort();
+                ids.dedup();
+                ids
+            } else {
+                ids
+            };
+            let time = Instant::now();
+            if !quiet {
+                println!(
+                    "    \x1b[1;96mMorphing\x1b[0m voxel IDs {:?} with {:?}",
+                    ids, operation
+                );
+            }
+            voxels = voxels.morph(operation, &ids, element, background)?;
+            if !quiet {
+                println!("        \x1b[1;92mDone\x1b[0m {:?}", time.elapsed());
+            }
+            match output_extension {
+                Some("npy") => write_output(
+                    output,
+                    OutputTypes::<8, HexahedralFiniteElements>::Npy(voxels),
+                    quiet,
+                ),
+                Some("spn") => write_output(
+                    output,
+                    OutputTypes::<8, HexahedralFiniteElements>::Spn(voxels),
+                    quiet,
+                ),
+                _ => invalid_output(&output, output_extension),
+            }
+        }
+        _ => {
+            let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
+            Err(format!(
+                "Invalid extension .{} from input file {}",
+                input_extension.unwrap_or("UNDEFINED"),
+                input
+            ))?
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn octree(
     input: String,
@@ -1151,6 +2327,8 @@ fn octree(
     nely: Option<usize>,
     nelz: Option<usize>,
     remove: Option<Vec<usize>>,
+    relabel: Vec<String>,
+    edit: VoxelEditArgs,
     xscale: f64,
     yscale: f64,
     zscale: f64,
@@ -1167,8 +2345,8 @@ fn octree(
             .map(|entry| entry as u8)
             .collect()
     });
-    let scale = [xscale, yscale, zscale].into();
-    let translate = [xtranslate, ytranslate, ztranslate].into();
+    let mut scale = [xscale, yscale, zscale].into();
+    let mut translate = [xtranslate, ytranslate, ztranslate].into();
     let input_type = match read_input(&input, nelx, nely, nelz, quiet)? {
         InputTypes::Npy(voxels) => voxels,
         InputTypes::Spn(voxels) => voxels,
@@ -1181,6 +2359,8 @@ fn octree(
             ))?
         }
     };
+    let input_type = relabel_voxels(input_type, relabel, quiet)?;
+    let input_type = edit_voxels(input_type, &edit, &mut scale, &mut translate, quiet)?;
     let time = Instant::now();
     if !quiet {
         mesh_print_info(MeshBasis::Leaves, &scale, &translate)
@@ -1216,128 +2396,261 @@ fn octree(
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
 fn smooth(
     input: String,
     output: String,
-    iterations: usize,
-    method: Option<String>,
-    hierarchical: bool,
-    pass_band: f64,
-    scale: f64,
+    smoothing: &SmoothingArgs,
     metrics: Option<String>,
     quiet: bool,
 ) -> Result<(), ErrorWrapper> {
     let output_extension = Path::new(&output).extension().and_then(|ext| ext.to_str());
-    match read_input(&input, None, None, None, quiet)? {
+    let mut finite_elements = match read_input(&input, None, None, None, quiet)? {
         InputTypes::Abaqus(mut finite_elements) => {
-            apply_smoothing_method(
-                &mut finite_elements,
-                iterations,
-                method,
-                hierarchical,
-                pass_band,
-                scale,
-                quiet,
-            )?;
-            if let Some(file) = metrics {
-                metrics_inner(&finite_elements, file, quiet)?
+            if output_extension == Some("stl") {
+                return invalid_hexahedral_output(&output);
             }
-            match output_extension {
-                Some("exo") => write_output(output, OutputTypes::Exodus(finite_elements), quiet),
-                Some("inp") => write_output(output, OutputTypes::Abaqus(finite_elements), quiet),
-                Some("mesh") => write_output(output, OutputTypes::Mesh(finite_elements), quiet),
-                Some("stl") => write_output(
-                    output,
-                    OutputTypes::<3, TriangularFiniteElements>::Stl(
-                        finite_elements.into_tesselation(),
-                    ),
-                    quiet,
-                ),
-                Some("vtk") => write_output(output, OutputTypes::Vtk(finite_elements), quiet),
-                _ => invalid_output(&output, output_extension),
+            apply_smoothing_method(&mut finite_elements, smoothing, Some(&input), quiet)?;
+            if let Some(file) = metrics {
+                metrics_inner(&finite_elements, file, None, quiet)?
             }
-        }
-        InputTypes::Stl(tesselation) => {
-            let mut finite_elements = tesselation.into_finite_elements();
-            apply_smoothing_method(
-                &mut finite_elements,
-                iterations,
-                method,
-                hierarchical,
-                pass_band,
-                scale,
-                quiet,
-            )?;
-            match output_extension {
+            return match output_extension {
                 Some("exo") => write_output(output, OutputTypes::Exodus(finite_elements), quiet),
                 Some("inp") => write_output(output, OutputTypes::Abaqus(finite_elements), quiet),
                 Some("mesh") => write_output(output, OutputTypes::Mesh(finite_elements), quiet),
-                Some("stl") => write_output(
-                    output,
-                    OutputTypes::<3, TriangularFiniteElements>::Stl(
-                        finite_elements.into_tesselation(),
-                    ),
-                    quiet,
-                ),
                 Some("vtk") => write_output(output, OutputTypes::Vtk(finite_elements), quiet),
                 _ => invalid_output(&output, output_extension),
-            }
+            };
         }
+        InputTypes::AbaqusTri(finite_elements) => finite_elements,
+        InputTypes::Stl(tesselation) => tesselation.into_finite_elements(),
         InputTypes::Npy(_) | InputTypes::Spn(_) => {
             Err(format!("No smoothing for segmentation file {}", input))?
         }
+    };
+    apply_smoothing_method(&mut finite_elements, smoothing, Some(&input), quiet)?;
+    if let Some(file) = metrics {
+        metrics_inner(&finite_elements, file, None, quiet)?
+    }
+    match output_extension {
+        Some("exo") => write_output(output, OutputTypes::Exodus(finite_elements), quiet),
+        Some("inp") => write_output(output, OutputTypes::Abaqus(finite_elements), quiet),
+        Some("mesh") => write_output(output, OutputTypes::Mesh(finite_elements), quiet),
+        Some("stl") => write_output(
+            output,
+            OutputTypes::<3, TriangularFiniteElements>::Stl(finite_elements.into_tesselation()),
+            quiet,
+        ),
+        Some("vtk") => write_output(output, OutputTypes::Vtk(finite_elements), quiet),
+        _ => invalid_output(&output, output_extension),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
 fn apply_smoothing_method<const N: usize, T>(
     output_type: &mut T,
-    iterations: usize,
-    method: Option<String>,
-    hierarchical: bool,
-    pass_band: f64,
-    scale: f64,
+    smoothing: &SmoothingArgs,
+    input: Option<&str>,
     quiet: bool,
 ) -> Result<(), ErrorWrapper>
 where
     T: FiniteElementMethods<N>,
 {
     let time_smooth = Instant::now();
-    let smoothing_method = method.unwrap_or("Taubin".to_string());
+    let smoothing_method = smoothing.method.as_deref().unwrap_or("Taubin");
     if matches!(
-        smoothing_method.as_str(),
-        "Laplacian" | "Laplace" | "laplacian" | "laplace" | "Taubin" | "taubin"
+        smoothing_method,
+        "HC" | "hc" | "Laplacian" | "Laplace" | "laplacian" | "laplace" | "Taubin" | "taubin"
     ) {
         if !quiet {
             print!("   \x1b[1;96mSmoothing\x1b[0m ");
-            match smoothing_method.as_str() {
+            match smoothing_method {
+                "HC" | "hc" => {
+                    println!("with {} iterations of HC Laplace", smoothing.iterations)
+                }
                 "Laplacian" | "Laplace" | "laplacian" | "laplace" => {
-                    println!("with {} iterations of Laplace", iterations)
+                    println!("with {} iterations of Laplace", smoothing.iterations)
                 }
                 "Taubin" | "taubin" => {
-                    println!("with {} iterations of Taubin", iterations)
+                    println!("with {} iterations of Taubin", smoothing.iterations)
                 }
                 _ => panic!(),
             }
         }
         output_type.node_element_connectivity()?;
         output_type.node_node_connectivity()?;
-        if hierarchical {
+        if smoothing.hierarchical {
             output_type.nodal_hierarchy()?;
         }
+        let mut prescribed_nodes = vec![];
+        if let Some(name) = &smoothing.fix_set {
+            match input {
+                Some(file) if file.ends_with(".inp") => {
+                    prescribed_nodes.extend(T::node_set_from_inp(file, name)?)
+                }
+                _ => Err("Node sets can only be fixed when smoothing an Abaqus input file")?,
+            }
+        }
+        if !smoothing.fix_box.is_empty() {
+            if smoothing.fix_box.len() != NSD * 2 {
+                Err("Need to specify six coordinates for the box of fixed nodes")?
+            }
+            prescribed_nodes.extend(output_type.calculate_nodes_in_box(
+                &[
+                    smoothing.fix_box[0],
+                    smoothing.fix_box[1],
+                    smoothing.fix_box[2],
+                ],
+                &[
+                    smoothing.fix_box[3],
+                    smoothing.fix_box[4],
+                    smoothing.fix_box[5],
+                ],
+            ));
+        }
+        smoothing
+            .prescribe_block
+            .iter()
+            .for_each(|prescribed_block| {
+                output_type
+                    .get_element_blocks()
+                    .iter()
+                    .zip(output_type.get_element_node_connectivity().iter())
+                    .filter(|(block, _)| *block == prescribed_block)
+                    .for_each(|(_, connectivity)| prescribed_nodes.extend(connectivity))
+            });
+        if smoothing.prescribe_surface {
+            prescribed_nodes.extend(output_type.calculate_exterior_nodes());
+        }
+        let prescribed_nodes_inhomogeneous = match &smoothing.prescribed {
+            Some(file) => {
+                let (nodes, inhomogeneous) =
+                    read_prescribed_nodes(file, output_type.get_nodal_coordinates().len())?;
+                prescribed_nodes.extend(nodes);
+                inhomogeneous
+            }
+            None => None,
+        };
+        if !prescribed_nodes.is_empty() || prescribed_nodes_inhomogeneous.is_some() {
+            prescribed_nodes.sort();
+            prescribed_nodes.dedup();
+            if let Some((_, nodes)) = &prescribed_nodes_inhomogeneous {
+                prescribed_nodes.retain(|node| nodes.binary_search(node).is_err())
+            }
+            if !quiet {
+                println!(
+                    "             \x1b[2mPrescribed {} fixed and {} moved nodes\x1b[0m",
+                    prescribed_nodes.len(),
+                    prescribed_nodes_inhomogeneous
+                        .as_ref()
+                        .map_or(0, |(_, nodes)| nodes.len())
+                );
+            }
+            output_type
+                .set_prescribed_nodes(Some(prescribed_nodes), prescribed_nodes_inhomogeneous)?;
+        }
+        let reference_surface = smoothing
+            .reference
+            .as_ref()
+            .map(|file| Tessellation::from_stl(file))
+            .transpose()?;
+        let options = SmoothingOptions {
+            block_smoothing: smoothing
+                .block
+                .iter()
+                .map(|spec| block_smoothing_from_spec(spec, smoothing))
+                .collect::<Result<_, _>>()?,
+            convergence: smoothing
+                .convergence
+                .as_ref()
+                .map(|criterion| match criterion.as_str() {
+                    "max" | "maximum" => Ok(Convergence::MaximumDisplacement(smoothing.tolerance)),
+                    "rms" | "RMS" => Ok(Convergence::RmsDisplacement(smoothing.tolerance)),
+                    name => Metric::try_from(name)
+                        .map(|metric| Convergence::Quality(metric, smoothing.tolerance)),
+                })
+                .transpose()?,
+            feature_angle: smoothing.feature_angle,
+            project_interface: smoothing.project_interface,
+            reference_surface: reference_surface.as_ref(),
+            safeguard: smoothing.safeguard,
+            volume_preserving: smoothing.preserve_volume,
+        };
         output_type.nodal_influencers();
-        match smoothing_method.as_str() {
+        let block_volumes = output_type.calculate_block_volumes();
+        match smoothing_method {
+            "HC" | "hc" => {
+                output_type.smooth_with(
+                    Smoothing::HcLaplacian(
+                        smoothing.iterations,
+                        smoothing.alpha,
+                        smoothing.beta,
+                        smoothing.scale,
+                    ),
+                    &options,
+                )?;
+            }
             "Laplacian" | "Laplace" | "laplacian" | "laplace" => {
-                output_type.smooth(Smoothing::Laplacian(iterations, scale))?;
+                output_type.smooth_with(
+                    Smoothing::Laplacian(smoothing.iterations, smoothing.scale),
+                    &options,
+                )?;
             }
             "Taubin" | "taubin" => {
-                output_type.smooth(Smoothing::Taubin(iterations, pass_band, scale))?;
+                output_type.smooth_with(
+                    Smoothing::Taubin(smoothing.iterations, smoothing.pass_band, smoothing.scale),
+                    &options,
+                )?;
             }
             _ => panic!(),
         }
         if !quiet {
             println!("        \x1b[1;92mDone\x1b[0m {:?}", time_smooth.elapsed());
+            if smoothing.convergence.is_some() {
+                println!(
+                    "             \x1b[2mStopped after {} iterations\x1b[0m",
+                    output_type.get_iterations()
+                );
+            }
+            if smoothing.safeguard {
+                println!(
+                    "             \x1b[2mRejected {} moves that would invert elements\x1b[0m",
+                    output_type.get_rejected_moves()
+                );
+            }
+            if let (Ok(before), Ok(after)) = (&block_volumes, output_type.calculate_block_volumes())
+            {
+                before
+                    .iter()
+                    .zip(after.iter())
+                    .for_each(|((block, before), (_, after))| {
+                        println!(
+                            "             \x1b[2mBlock {} volume {:.6e} -> {:.6e} ({:+.3e}%)\x1b[0m",
+                            block,
+                            before,
+                            after,
+                            100.0 * (after - before) / before
+                        )
+                    });
+            }
+        }
+        if let Some(optimization_iterations) = smoothing.optimize {
+            let time_optimize = Instant::now();
+            if !quiet {
+                println!(
+                    "  \x1b[1;96mOptimizing\x1b[0m with {} iterations toward scaled Jacobian {}",
+                    optimization_iterations, smoothing.target
+                );
+            }
+            output_type.optimize(
+                optimization_iterations,
+                smoothing.target,
+                reference_surface.as_ref(),
+            )?;
+            if !quiet {
+                println!(
+                    "        \x1b[1;92mDone\x1b[0m {:?}",
+                    time_optimize.elapsed()
+                );
+            }
         }
         Ok(())
     } else {
@@ -1348,6 +2661,134 @@ where
     }
 }
 
+fn block_smoothing_from_spec(
+    spec: &str,
+    smoothing: &SmoothingArgs,
+) -> Result<(u8, Option<Smoothing>), String> {
+    let (pass_band, alpha, beta) = (smoothing.pass_band, smoothing.alpha, smoothing.beta);
+    let invalid = || format!("Invalid block smoothing {} specified", spec);
+    let fields: Vec<&str> = spec.split(':').collect();
+    if fields.len() < 2 || fields.len() > 4 {
+        return Err(invalid());
+    }
+    let block = fields[0].parse::<u8>().map_err(|_| invalid())?;
+    let iterations = match fields.get(2) {
+        Some(field) => field.parse::<usize>().map_err(|_| invalid())?,
+        None => smoothing.iterations,
+    };
+    let scale = match fields.get(3) {
+        Some(field) => field.parse::<f64>().map_err(|_| invalid())?,
+        None => smoothing.scale,
+    };
+    match fields[1] {
+        "frozen" | "Frozen" if fields.len() == 2 => Ok((block, None)),
+        "HC" | "hc" => Ok((
+            block,
+            Some(Smoothing::HcLaplacian(iterations, alpha, beta, scale)),
+        )),
+        "Laplacian" | "Laplace" | "laplacian" | "laplace" => {
+            Ok((block, Some(Smoothing::Laplacian(iterations, scale))))
+        }
+        "Taubin" | "taubin" => Ok((block, Some(Smoothing::Taubin(iterations, pass_band, scale)))),
+        _ => Err(invalid()),
+    }
+}
+
+type PrescribedNodes = (Vec<usize>, Option<(Coordinates, Vec<usize>)>);
+
+fn is_prescribed_nodes_header(line: &str) -> bool {
+    let header: Vec<String> = line
+        .split(',')
+        .map(|entry| entry.trim().to_lowercase())
+        .collect();
+    header == ["node"] || header == ["node", "x", "y", "z"]
+}
+
+fn read_prescribed_nodes(
+    file: &str,
+    number_of_nodes: usize,
+) -> Result<PrescribedNodes, ErrorWrapper> {
+    let rows: Vec<Vec<f64>> = match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => read_to_string(file)?
+            .lines()
+            .map(|line| {
+                (
+                    line,
+                    line.split(',')
+                        .map(|entry| entry.trim().parse::<f64>())
+                        .collect::<Result<Vec<f64>, _>>(),
+                )
+            })
+            .enumerate()
+            .filter_map(|(index, (line, row))| match row {
+                Ok(row) => Some(Ok(row)),
+                Err(_) if index == 0 && is_prescribed_nodes_header(line) => None,
+                Err(error) => Some(Err(format!(
+                    "Invalid entry on line {} of {}: {} ({})",
+                    index + 1,
+                    file,
+                    line,
+                    error
+                ))),
+            })
+            .collect::<Result<_, _>>()?,
+        Some("npy") => match Array2::<f64>::read_npy(File::open(file)?) {
+            Ok(array) => array.outer_iter().map(|row| row.to_vec()).collect(),
+            Err(_) => Array1::<i64>::read_npy(File::open(file)?)?
+                .iter()
+                .map(|&node| vec![node as f64])
+                .collect(),
+        },
+        extension => Err(format!(
+            "Invalid extension .{} for prescribed nodes file {} (csv | npy)",
+            extension.unwrap_or(""),
+            file
+        ))?,
+    };
+    let node = |entry: f64| {
+        if entry.fract() == 0.0 && entry >= 1.0 && entry <= number_of_nodes as f64 {
+            Ok(entry as usize)
+        } else {
+            Err(format!(
+                "Prescribed node {} does not exist in the mesh",
+                entry
+            ))
+        }
+    };
+    if rows.iter().all(|row| row.len() == 1) {
+        Ok((
+            rows.iter()
+                .map(|row| node(row[0]))
+                .collect::<Result<_, _>>()?,
+            None,
+        ))
+    } else if rows.iter().all(|row| row.len() == NSD + 1) {
+        let mut rows = rows
+            .iter()
+            .map(|row| Ok((node(row[0])?, [row[1], row[2], row[3]])))
+            .collect::<Result<Vec<_>, String>>()?;
+        rows.sort_by_key(|(node, _)| *node);
+        rows.dedup_by_key(|(node, _)| *node);
+        Ok((
+            vec![],
+            Some((
+                Coordinates::new(
+                    &rows
+                        .iter()
+                        .map(|(_, coordinates)| *coordinates)
+                        .collect::<Vec<_>>(),
+                ),
+                rows.iter().map(|(node, _)| *node).collect(),
+            )),
+        ))
+    } else {
+        Err(format!(
+            "Prescribed nodes file {} needs one column of nodes or four columns of nodes and coordinates",
+            file
+        ))?
+    }
+}
+
 fn read_input(
     input: &str,
     nelx: Option<usize>,
@@ -1365,8 +2806,18 @@ fn read_input(
         print!("     \x1b[1;96mReading\x1b[0m {}", input);
     }
     let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
+    let mut warning = None;
     let result = match input_extension {
-        Some("inp") => InputTypes::Abaqus(HexahedralFiniteElements::from_inp(input)?),
+        Some("inp") => match FiniteElementsKind::from_inp(input)? {
+            FiniteElementsKind::Hexahedral(finite_elements) => InputTypes::Abaqus(finite_elements),
+            FiniteElementsKind::Triangular(finite_elements) => {
+                InputTypes::AbaqusTri(finite_elements)
+            }
+            FiniteElementsKind::AssumedHexahedral(finite_elements, message) => {
+                warning = Some(message);
+                InputTypes::Abaqus(finite_elements)
+            }
+        },
         Some("npy") => InputTypes::Npy(Voxels::from_npy(input)?),
         Some("spn") => {
             let nel = Nel::from_input([nelx, nely, nelz])?;
@@ -1408,6 +2859,48 @@ fn read_input(
                 println!();
             }
         }
+        if let Some(message) = warning {
+            println!("     \x1b[1;93mWarning\x1b[0m {}", message);
+        }
+    }
+    Ok(result)
+}
+
+fn read_chunked_input(
+    input: &str,
+    nelx: Option<usize>,
+    nely: Option<usize>,
+    nelz: Option<usize>,
+    quiet: bool,
+) -> Result<ChunkedVoxels, ErrorWrapper> {
+    let time = Instant::now();
+    if !quiet {
+        println!(
+            "\x1b[1m    {} {}\x1b[0m",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        );
+        print!("     \x1b[1;96mReading\x1b[0m {}", input);
+    }
+    let input_extension = Path::new(&input).extension().and_then(|ext| ext.to_str());
+    let result = match input_extension {
+        Some("npy") => ChunkedVoxels::from_npy(input)?,
+        Some("spn") => ChunkedVoxels::from_spn(input, Nel::from_input([nelx, nely, nelz])?)?,
+        _ => Err(format!(
+            "Invalid extension .{} from input file {}",
+            input_extension.unwrap_or("UNDEFINED"),
+            input
+        ))?,
+    };
+    if !quiet {
+        let nel = result.get_nel();
+        println!(
+            " \x1b[2m[nelx: {}, nely: {}, nelz: {}]\x1b[0m\n        \x1b[1;92mDone\x1b[0m {:?}",
+            nel.x(),
+            nel.y(),
+            nel.z(),
+            time.elapsed()
+        );
     }
     Ok(result)
 }
@@ -10,7 +10,7 @@ use super::{
     voxel::{Nel, Scale, Translate, VoxelData, Voxels},
 };
 use conspire::math::{TensorArray, TensorRank1Vec, TensorVec};
-use ndarray::{Axis, s};
+use ndarray::s;
 use std::array::from_fn;
 
 const NUM_FACES: usize = 6;
@@ -79,12 +79,105 @@ type Cluster = Vec<usize>;
 type Clusters = Vec<Cluster>;
 type Supercells = Vec<Option<[usize; 2]>>;
 
+/// The adjacency of voxels used to form clusters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Adjacency {
+    /// Voxels sharing a face (6 neighbors).
+    #[default]
+    Face,
+    /// Voxels sharing a face or an edge (18 neighbors).
+    Edge,
+    /// Voxels sharing a face, an edge, or a vertex (26 neighbors).
+    Vertex,
+}
+
+impl TryFrom<usize> for Adjacency {
+    type Error = String;
+    fn try_from(neighbors: usize) -> Result<Self, Self::Error> {
+        match neighbors {
+            6 => Ok(Self::Face),
+            18 => Ok(Self::Edge),
+            26 => Ok(Self::Vertex),
+            _ => Err(format!(
+                "Invalid connectivity {}, need to specify 6, 18, or 26",
+                neighbors
+            )),
+        }
+    }
+}
+
+/// The implementation used for defeaturing clusters of voxels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DefeaturingMethod {
+    /// Clusters of leaves in a balanced octree with the voxels padded to a whole number of roots.
+    #[default]
+    Octree,
+    /// Clusters of voxels found in place using union-find.
+    ///
+    /// The results match [`DefeaturingMethod::Octree`] except where touching clusters are
+    /// reassigned in a different order.
+    UnionFind,
+}
+
+/// The settings for defeaturing clusters of voxels.
+#[derive(Clone, Debug, Default)]
+pub struct Defeaturing {
+    /// The adjacency of voxels used to form clusters.
+    pub adjacency: Adjacency,
+    /// The implementation used for defeaturing.
+    pub method: DefeaturingMethod,
+    /// The minimum number of voxels in a cluster.
+    pub minimum: usize,
+    /// The minimum number of voxels in a cluster for specific blocks.
+    pub minimums: Vec<(u8, usize)>,
+    /// The blocks with clusters that are never reassigned.
+    pub protected: Blocks,
+}
+
+impl Defeaturing {
+    pub(crate) fn threshold(&self, block: u8) -> Option<usize> {
+        if self.protected.contains(&block) {
+            None
+        } else {
+            Some(
+                self.minimums
+                    .iter()
+                    .find(|(entry, _)| entry == &block)
+                    .map_or(self.minimum, |(_, minimum)| *minimum),
+            )
+        }
+    }
+}
+
+impl From<usize> for Defeaturing {
+    fn from(minimum: usize) -> Self {
+        Self {
+            minimum,
+            ..Default::default()
+        }
+    }
+}
+
+/// A cluster of voxels reassigned to another block while defeaturing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reassignment {
+    /// The original block of the cluster.
+    pub block: u8,
+    /// The minimum corner of the bounding box of the cluster.
+    pub minimum: [usize; NSD],
+    /// The block the cluster was reassigned to.
+    pub new_block: u8,
+    /// The number of voxels in the cluster.
+    pub voxels: usize,
+}
+
 /// Methods for trees such as quadtrees or octrees.
 pub trait Tree {
     fn balance(&mut self, strong: bool);
     fn boundaries(&mut self, nel_padded: &Nel);
     fn clusters(&self, remove: &Option<Blocks>, supercells: Option<&Supercells>) -> Clusters;
     fn defeature(&mut self, min_num_voxels: usize);
+    fn defeature_with(&mut self, defeaturing: &Defeaturing) -> Vec<Reassignment>;
     fn from_voxels(voxels: Voxels) -> (Nel, Self);
     fn octree_into_finite_elements(
         self,
@@ -93,7 +186,7 @@ pub trait Tree {
         translate: Translate,
     ) -> Result<HexahedralFiniteElements, String>;
     fn pair(&mut self);
-    fn protrusions(&mut self, supercells: &Supercells) -> bool;
+    fn protrusions(&mut self, supercells: &Supercells, protected: &Blocks) -> bool;
     fn prune(&mut self);
     fn subdivide(&mut self, index: usize);
     fn supercells(&self) -> Supercells;
@@ -930,222 +1023,13 @@ impl Tree for Octree {
         clusters
     }
     fn defeature(&mut self, min_num_voxels: usize) {
-        //
-        // Should cells of a reassigned cluster be reassigned one at a time instead?
-        //
-        // Do the clusters need to be updated each time another changes?
-        // In case a cluster inherits the reassigned cluster and becomes large enough?
-        //
-        // Still may not understand why `blocks` could be empty below.
-        //
-        let mut block = 0;
-        let mut blocks = vec![];
-        let mut clusters;
-        let mut counts: Vec<usize> = vec![];
-        let mut defeatured;
-        let mut face_block = 0;
-        let mut neighbor_block = 0;
-        let mut new_block = 0;
-        let mut protruded;
-        let mut unique_blocks = vec![];
-        let mut volumes: Vec<usize>;
-        let supercells = self.supercells();
-        #[allow(unused_variables)]
-        for iteration in 1.. {
-            clusters = self.clusters(&None, Some(&supercells));
-            #[cfg(feature = "profile")]
-            let time = Instant::now();
-            volumes = clusters
-                .iter()
-                .map(|cluster| {
-                    cluster
-                        .iter()
-                        .map(|&cell| self[cell].get_lngth().pow(NSD as u32) as usize)
-                        .sum()
-                })
-                .collect();
-            defeatured = volumes.iter().all(|volume| volume >= &min_num_voxels);
-            if !defeatured {
-                clusters
-                    .iter()
-                    .zip(volumes)
-                    .filter(|(_, volume)| volume < &min_num_voxels)
-                    .for_each(|(cluster, _)| {
-                        block = self[cluster[0]].get_block();
-                        blocks = cluster
-                            .iter()
-                            .flat_map(|&cell| {
-                                self[cell]
-                                    .get_faces()
-                                    .iter()
-                                    .enumerate()
-                                    .filter_map(|(face, &face_cell)| {
-                                        if let Some(neighbor) = face_cell {
-                                            if let Some(subcells) = self[neighbor].get_cells() {
-                                                Some(
-                                                    subcells_on_neighbor_face(face)
-                                                        .into_iter()
-                                                        .filter_map(|subcell| {
-                                                            face_block =
-                                                                self[subcells[subcell]].get_block();
-                                                            if face_block != block {
-                                                                Some(face_block)
-                                                            } else {
-                                                                None
-                                                            }
-                                                        })
-                                                        .collect(),
-                                                )
-                                            } else {
-                                                face_block = self[neighbor].get_block();
-                                                if face_block != block {
-                                                    Some(vec![face_block])
-                                                } else {
-                                                    None
-                                                }
-                                            }
-                                        } else {
-                                            None
-                                        }
-                                    })
-                                    .collect::<Vec<Blocks>>()
-                            })
-                            .chain(cluster.iter().filter_map(|&cell| {
-                                if let Some([parent, subcell]) = supercells[cell] {
-                                    Some(
-                                        self[parent]
-                                            .get_faces()
-                                            .iter()
-                                            .enumerate()
-                                            .filter_map(|(face, face_cell)| {
-                                                if let Some(neighbor_cell) = face_cell {
-                                                    if self[*neighbor_cell].is_leaf()
-                                                        && subcells_on_own_face(face)
-                                                            .contains(&subcell)
-                                                    {
-                                                        neighbor_block =
-                                                            self[*neighbor_cell].get_block();
-                                                        if neighbor_block != block {
-                                                            Some(neighbor_block)
-                                                        } else {
-                                                            None
-                                                        }
-                                                    } else {
-                                                        None
-                                                    }
-                                                } else {
-                                                    None
-                                                }
-                                            })
-                                            .collect(),
-                                    )
-                                } else {
-                                    None
-                                }
-                            }))
-                            .collect::<Vec<Blocks>>()
-                            .into_iter()
-                            .flatten()
-                            .collect();
-                        unique_blocks = blocks.to_vec();
-                        unique_blocks.sort();
-                        unique_blocks.dedup();
-                        counts = unique_blocks
-                            .iter()
-                            .map(|unique_block| {
-                                blocks.iter().filter(|&block| block == unique_block).count()
-                            })
-                            .collect();
-                        if !blocks.is_empty() {
-                            new_block = unique_blocks[counts
-                                .iter()
-                                .position(|count| {
-                                    count == counts.iter().max().expect("maximum not found")
-                                })
-                                .expect("position of maximum not found")];
-                            cluster
-                                .iter()
-                                .for_each(|&cell| self[cell].block = Some(new_block));
-                        }
-                    });
-            }
-            #[cfg(feature = "profile")]
-            println!(
-                "             \x1b[1;93mDefeaturing iteration {}\x1b[0m {:?} ",
-                iteration,
-                time.elapsed()
-            );
-            protruded = self.protrusions(&supercells);
-            if defeatured && protruded {
-                return;
-            }
-        }
+        self.defeature_with(&min_num_voxels.into());
+    }
+    fn defeature_with(&mut self, defeaturing: &Defeaturing) -> Vec<Reassignment> {
+        defeature_octree(self, defeaturing, None)
     }
     fn from_voxels(voxels: Voxels) -> (Nel, Self) {
-        #[cfg(feature = "profile")]
-        let time = Instant::now();
-        let data_voxels = voxels.get_data();
-        let mut nel_i = 0;
-        let nel_padded = data_voxels
-            .shape()
-            .iter()
-            .map(|nel_0| {
-                nel_i = *nel_0;
-                while (nel_i & (nel_i - 1)) != 0 {
-                    nel_i += 1
-                }
-                nel_i
-            })
-            .max()
-            .unwrap();
-        let nel = Nel::from([nel_padded; NSD]);
-        let mut data = VoxelData::from(nel);
-        data.axis_iter_mut(Axis(2))
-            .zip(data_voxels.axis_iter(Axis(2)))
-            .for_each(|(mut data_i, data_voxels_i)| {
-                data_i
-                    .axis_iter_mut(Axis(1))
-                    .zip(data_voxels_i.axis_iter(Axis(1)))
-                    .for_each(|(mut data_ij, data_voxels_ij)| {
-                        data_ij
-                            .iter_mut()
-                            .zip(data_voxels_ij.iter())
-                            .for_each(|(data_ijk, data_voxels_ijk)| *data_ijk = *data_voxels_ijk)
-                    })
-            });
-        let nel_min = nel.iter().min().unwrap();
-        let lngth = *nel_min as u16;
-        let mut tree = vec![];
-        (0..(nel.x() / nel_min)).for_each(|i| {
-            (0..(nel.y() / nel_min)).for_each(|j| {
-                (0..(nel.z() / nel_min)).for_each(|k| {
-                    tree.push(Cell {
-                        block: None,
-                        cells: None,
-                        faces: [None; NUM_FACES],
-                        lngth,
-                        min_x: lngth * i as u16,
-                        min_y: lngth * j as u16,
-                        min_z: lngth * k as u16,
-                    })
-                })
-            })
-        });
-        let mut index = 0;
-        while index < tree.len() {
-            if let Some(block) = tree[index].homogeneous(&data) {
-                tree[index].block = Some(block)
-            } else {
-                tree.subdivide(index)
-            }
-            index += 1;
-        }
-        #[cfg(feature = "profile")]
-        println!(
-            "           \x1b[1;93m⤷ Octree initialization\x1b[0m {:?} ",
-            time.elapsed()
-        );
-        (nel, tree)
+        octree_from_voxel_data(voxels.get_data(), Some(0))
     }
     fn octree_into_finite_elements(
         self,
@@ -1242,79 +1126,8 @@ impl Tree for Octree {
             time.elapsed()
         );
     }
-    fn protrusions(&mut self, supercells: &Supercells) -> bool {
-        let mut blocks = vec![];
-        let mut complete = true;
-        let mut counts: Vec<usize> = vec![];
-        let mut new_block = 0;
-        let mut protrusions: Vec<(usize, Blocks)>;
-        let mut unique_blocks = vec![];
-        #[allow(unused_variables)]
-        for iteration in 1.. {
-            #[cfg(feature = "profile")]
-            let time = Instant::now();
-            protrusions = self
-                .iter()
-                .enumerate()
-                .filter(|(_, cell)| cell.is_voxel())
-                .flat_map(|(voxel_cell_index, voxel_cell)| {
-                    blocks = voxel_cell
-                        .get_faces()
-                        .iter()
-                        .enumerate()
-                        .flat_map(|(face_index, &face)| {
-                            if let Some(face_cell_index) = face {
-                                Some(self[face_cell_index].get_block())
-                            } else if let Some([parent, _]) = supercells[voxel_cell_index] {
-                                self[parent].get_faces()[face_index]
-                                    .map(|neighbor| self[neighbor].get_block())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    if blocks
-                        .iter()
-                        .filter(|&&face_block| voxel_cell.get_block() != face_block)
-                        .count()
-                        >= 5
-                    {
-                        Some((voxel_cell_index, blocks.clone()))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            if !protrusions.is_empty() {
-                complete = false;
-                protrusions.iter().for_each(|(voxel_cell_index, blocks)| {
-                    unique_blocks = blocks.to_vec();
-                    unique_blocks.sort();
-                    unique_blocks.dedup();
-                    counts = unique_blocks
-                        .iter()
-                        .map(|unique_block| {
-                            blocks.iter().filter(|&block| block == unique_block).count()
-                        })
-                        .collect();
-                    new_block = unique_blocks[counts
-                        .iter()
-                        .position(|count| count == counts.iter().max().expect("maximum not found"))
-                        .expect("position of maximum not found")];
-                    self[*voxel_cell_index].block = Some(new_block)
-                })
-            }
-            #[cfg(feature = "profile")]
-            println!(
-                "             \x1b[1;93mProtrusions iteration {}\x1b[0m {:?} ",
-                iteration,
-                time.elapsed()
-            );
-            if protrusions.is_empty() {
-                break;
-            }
-        }
-        complete
+    fn protrusions(&mut self, supercells: &Supercells, protected: &Blocks) -> bool {
+        protrusions_octree(self, supercells, protected, None)
     }
     fn prune(&mut self) {
         #[cfg(feature = "profile")]
@@ -1529,8 +1342,10 @@ impl IntoFiniteElements<TriangularFiniteElements> for Octree {
         let mut faces_connectivity = vec![];
         let mut nodal_coordinates = Coordinates::zero(0);
         let mut node_new = 1;
-        let nodes_len = (self[0].get_lngth() + 1) as usize;
-        let mut nodes = vec![vec![vec![None::<usize>; nodes_len]; nodes_len]; nodes_len];
+        let mut nodes = vec![
+            vec![vec![None::<usize>; nel_padded.z() + 1]; nel_padded.y() + 1];
+            nel_padded.x() + 1
+        ];
         (0..boundaries_cells_faces.len()).for_each(|boundary| {
             boundaries_cells_faces[boundary]
                 .iter()
@@ -1864,3 +1679,486 @@ impl IntoFiniteElements<HexahedralFiniteElements> for Octree {
         fem
     }
 }
+
+/// Creates an octree from voxel data, where the roots tile the voxels with each direction padded
+/// only to the next multiple of their length using the padding voxel ID.
+/// The length is the power of two fitting the smallest dimension that balances the padding against
+/// the coarsening, such that the padded voxels and the roots take the least memory.
+/// Without a padding voxel ID, the length is the largest power of two dividing each dimension.
+pub(crate) fn octree_from_voxel_data(
+    data_voxels: &VoxelData,
+    padding: Option<u8>,
+) -> (Nel, Octree) {
+    #[cfg(feature = "profile")]
+    let time = Instant::now();
+    let shape = data_voxels.shape();
+    let memory = |lngth: usize| -> usize {
+        shape
+            .iter()
+            .map(|nel_n| nel_n.div_ceil(lngth) * lngth)
+            .product::<usize>()
+            * size_of::<u8>()
+            + shape
+                .iter()
+                .map(|nel_n| nel_n.div_ceil(lngth))
+                .product::<usize>()
+                * size_of::<Cell>()
+    };
+    let lngth = if padding.is_some() {
+        let mut lngth = 1;
+        let mut candidate = 1;
+        while 2 * candidate <= *shape.iter().min().unwrap() {
+            candidate *= 2;
+            if memory(candidate) <= memory(lngth) {
+                lngth = candidate
+            }
+        }
+        lngth
+    } else {
+        1 << shape
+            .iter()
+            .map(|nel_n| nel_n.trailing_zeros().min(15))
+            .min()
+            .unwrap()
+    };
+    let roots: [usize; NSD] = from_fn(|n| shape[n].div_ceil(lngth));
+    let nel = Nel::from(roots.map(|roots_n| roots_n * lngth));
+    let mut data =
+        VoxelData::from_elem::<(usize, usize, usize)>(nel.into(), padding.unwrap_or_default());
+    data.slice_mut(s![..shape[0], ..shape[1], ..shape[2]])
+        .assign(data_voxels);
+    let root = |i: usize, j: usize, k: usize| (i * roots[1] + j) * roots[2] + k;
+    let mut tree = vec![];
+    (0..roots[0]).for_each(|i| {
+        (0..roots[1]).for_each(|j| {
+            (0..roots[2]).for_each(|k| {
+                tree.push(Cell {
+                    block: None,
+                    cells: None,
+                    faces: [
+                        (j > 0).then(|| root(i, j - 1, k)),
+                        (i + 1 < roots[0]).then(|| root(i + 1, j, k)),
+                        (j + 1 < roots[1]).then(|| root(i, j + 1, k)),
+                        (i > 0).then(|| root(i - 1, j, k)),
+                        (k > 0).then(|| root(i, j, k - 1)),
+                        (k + 1 < roots[2]).then(|| root(i, j, k + 1)),
+                    ],
+                    lngth: lngth as u16,
+                    min_x: (lngth * i) as u16,
+                    min_y: (lngth * j) as u16,
+                    min_z: (lngth * k) as u16,
+                })
+            })
+        })
+    });
+    let mut index = 0;
+    while index < tree.len() {
+        if let Some(block) = tree[index].homogeneous(&data) {
+            tree[index].block = Some(block)
+        } else {
+            tree.subdivide(index)
+        }
+        index += 1;
+    }
+    #[cfg(feature = "profile")]
+    println!(
+        "           \x1b[1;93m⤷ Octree initialization\x1b[0m {:?} ",
+        time.elapsed()
+    );
+    (nel, tree)
+}
+
+/// Defeatures the octree, with the padding voxel ID neither reassigned nor voting for clusters.
+pub(crate) fn defeature_octree(
+    tree: &mut Octree,
+    defeaturing: &Defeaturing,
+    padding: Option<u8>,
+) -> Vec<Reassignment> {
+    //
+    // Should cells of a reassigned cluster be reassigned one at a time instead?
+    //
+    // Do the clusters need to be updated each time another changes?
+    // In case a cluster inherits the reassigned cluster and becomes large enough?
+    //
+    // Still may not understand why `blocks` could be empty below.
+    //
+    let mut block = 0;
+    let mut blocks = vec![];
+    let mut clusters;
+    let mut counts: Vec<usize> = vec![];
+    let mut defeatured;
+    let mut face_block = 0;
+    let mut neighbor_block = 0;
+    let mut new_block = 0;
+    let mut protruded;
+    let mut reassigned;
+    let mut reassignments = vec![];
+    let mut small: Vec<bool>;
+    let mut unique_blocks = vec![];
+    let mut volumes: Vec<usize>;
+    let supercells = tree.supercells();
+    #[allow(unused_variables)]
+    for iteration in 1.. {
+        clusters = tree.clusters(&None, Some(&supercells));
+        if defeaturing.adjacency != Adjacency::Face {
+            clusters = merge_clusters(tree, clusters, defeaturing.adjacency)
+        }
+        #[cfg(feature = "profile")]
+        let time = Instant::now();
+        volumes = clusters
+            .iter()
+            .map(|cluster| {
+                cluster
+                    .iter()
+                    .map(|&cell| tree[cell].get_lngth().pow(NSD as u32) as usize)
+                    .sum()
+            })
+            .collect();
+        small = clusters
+            .iter()
+            .zip(volumes.iter())
+            .map(|(cluster, volume)| {
+                block = tree[cluster[0]].get_block();
+                padding != Some(block)
+                    && defeaturing
+                        .threshold(block)
+                        .is_some_and(|minimum| volume < &minimum)
+            })
+            .collect();
+        defeatured = small.iter().all(|small| !small);
+        reassigned = false;
+        if !defeatured {
+            clusters
+                .iter()
+                .zip(volumes)
+                .zip(small)
+                .filter(|(_, small)| *small)
+                .for_each(|((cluster, volume), _)| {
+                    block = tree[cluster[0]].get_block();
+                    blocks = cluster
+                        .iter()
+                        .flat_map(|&cell| {
+                            tree[cell]
+                                .get_faces()
+                                .iter()
+                                .enumerate()
+                                .filter_map(|(face, &face_cell)| {
+                                    if let Some(neighbor) = face_cell {
+                                        if let Some(subcells) = tree[neighbor].get_cells() {
+                                            Some(
+                                                subcells_on_neighbor_face(face)
+                                                    .into_iter()
+                                                    .filter_map(|subcell| {
+                                                        face_block =
+                                                            tree[subcells[subcell]].get_block();
+                                                        if face_block != block
+                                                            && padding != Some(face_block)
+                                                        {
+                                                            Some(face_block)
+                                                        } else {
+                                                            None
+                                                        }
+                                                    })
+                                                    .collect(),
+                                            )
+                                        } else {
+                                            face_block = tree[neighbor].get_block();
+                                            if face_block != block && padding != Some(face_block) {
+                                                Some(vec![face_block])
+                                            } else {
+                                                None
+                                            }
+                                        }
+                                    } else {
+                                        None
+                                    }
+                                })
+                                .collect::<Vec<Blocks>>()
+                        })
+                        .chain(cluster.iter().filter_map(|&cell| {
+                            if let Some([parent, subcell]) = supercells[cell] {
+                                Some(
+                                    tree[parent]
+                                        .get_faces()
+                                        .iter()
+                                        .enumerate()
+                                        .filter_map(|(face, face_cell)| {
+                                            if let Some(neighbor_cell) = face_cell {
+                                                if tree[*neighbor_cell].is_leaf()
+                                                    && subcells_on_own_face(face).contains(&subcell)
+                                                {
+                                                    neighbor_block =
+                                                        tree[*neighbor_cell].get_block();
+                                                    if neighbor_block != block
+                                                        && padding != Some(neighbor_block)
+                                                    {
+                                                        Some(neighbor_block)
+                                                    } else {
+                                                        None
+                                                    }
+                                                } else {
+                                                    None
+                                                }
+                                            } else {
+                                                None
+                                            }
+                                        })
+                                        .collect(),
+                                )
+                            } else {
+                                None
+                            }
+                        }))
+                        .collect::<Vec<Blocks>>()
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    unique_blocks = blocks.to_vec();
+                    unique_blocks.sort();
+                    unique_blocks.dedup();
+                    counts = unique_blocks
+                        .iter()
+                        .map(|unique_block| {
+                            blocks.iter().filter(|&block| block == unique_block).count()
+                        })
+                        .collect();
+                    if !blocks.is_empty() {
+                        new_block = unique_blocks[counts
+                            .iter()
+                            .position(|count| {
+                                count == counts.iter().max().expect("maximum not found")
+                            })
+                            .expect("position of maximum not found")];
+                        cluster
+                            .iter()
+                            .for_each(|&cell| tree[cell].block = Some(new_block));
+                        reassignments.push(Reassignment {
+                            block,
+                            minimum: cluster.iter().fold([usize::MAX; NSD], |minimum, &cell| {
+                                [
+                                    minimum[0].min(*tree[cell].get_min_x() as usize),
+                                    minimum[1].min(*tree[cell].get_min_y() as usize),
+                                    minimum[2].min(*tree[cell].get_min_z() as usize),
+                                ]
+                            }),
+                            new_block,
+                            voxels: volume,
+                        });
+                        reassigned = true
+                    }
+                });
+        }
+        #[cfg(feature = "profile")]
+        println!(
+            "             \x1b[1;93mDefeaturing iteration {}\x1b[0m {:?} ",
+            iteration,
+            time.elapsed()
+        );
+        protruded = protrusions_octree(tree, &supercells, &defeaturing.protected, padding);
+        if !reassigned && protruded {
+            break;
+        }
+    }
+    reassignments
+}
+
+fn protrusions_octree(
+    tree: &mut Octree,
+    supercells: &Supercells,
+    protected: &Blocks,
+    padding: Option<u8>,
+) -> bool {
+    let mut blocks = vec![];
+    let mut complete = true;
+    let mut counts: Vec<usize> = vec![];
+    let mut new_block = 0;
+    let mut protrusions: Vec<(usize, Blocks)>;
+    let mut unique_blocks = vec![];
+    #[allow(unused_variables)]
+    for iteration in 1.. {
+        #[cfg(feature = "profile")]
+        let time = Instant::now();
+        protrusions = tree
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| {
+                cell.is_voxel()
+                    && !protected.contains(&cell.get_block())
+                    && padding != Some(cell.get_block())
+            })
+            .flat_map(|(voxel_cell_index, voxel_cell)| {
+                blocks = voxel_cell
+                    .get_faces()
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(face_index, &face)| {
+                        if let Some(face_cell_index) = face {
+                            Some(tree[face_cell_index].get_block())
+                        } else if let Some([parent, _]) = supercells[voxel_cell_index] {
+                            tree[parent].get_faces()[face_index]
+                                .map(|neighbor| tree[neighbor].get_block())
+                        } else {
+                            None
+                        }
+                    })
+                    .filter(|&face_block| padding != Some(face_block))
+                    .collect();
+                if blocks
+                    .iter()
+                    .filter(|&&face_block| voxel_cell.get_block() != face_block)
+                    .count()
+                    >= 5
+                {
+                    Some((voxel_cell_index, blocks.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if !protrusions.is_empty() {
+            complete = false;
+            protrusions.iter().for_each(|(voxel_cell_index, blocks)| {
+                unique_blocks = blocks.to_vec();
+                unique_blocks.sort();
+                unique_blocks.dedup();
+                counts = unique_blocks
+                    .iter()
+                    .map(|unique_block| {
+                        blocks.iter().filter(|&block| block == unique_block).count()
+                    })
+                    .collect();
+                new_block = unique_blocks[counts
+                    .iter()
+                    .position(|count| count == counts.iter().max().expect("maximum not found"))
+                    .expect("position of maximum not found")];
+                tree[*voxel_cell_index].block = Some(new_block)
+            })
+        }
+        #[cfg(feature = "profile")]
+        println!(
+            "             \x1b[1;93mProtrusions iteration {}\x1b[0m {:?} ",
+            iteration,
+            time.elapsed()
+        );
+        if protrusions.is_empty() {
+            break;
+        }
+    }
+    complete
+}
+
+fn merge_clusters(tree: &Octree, clusters: Clusters, adjacency: Adjacency) -> Clusters {
+    let roots = tree
+        .iter()
+        .take_while(|cell| cell.get_lngth() == tree[0].get_lngth())
+        .count();
+    let mut owners = vec![usize::MAX; tree.len()];
+    clusters
+        .iter()
+        .enumerate()
+        .for_each(|(index, cluster)| cluster.iter().for_each(|&cell| owners[cell] = index));
+    let mut parents: Vec<usize> = (0..clusters.len()).collect();
+    let find = |parents: &mut Vec<usize>, mut index: usize| {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    };
+    clusters.iter().enumerate().for_each(|(index, cluster)| {
+        cluster.iter().for_each(|&cell| {
+            touching_leaves(tree, roots, cell, adjacency)
+                .into_iter()
+                .filter(|&leaf| {
+                    owners[leaf] != usize::MAX && tree[leaf].get_block() == tree[cell].get_block()
+                })
+                .for_each(|leaf| {
+                    let root = find(&mut parents, index);
+                    let other = find(&mut parents, owners[leaf]);
+                    parents[root.max(other)] = root.min(other);
+                })
+        })
+    });
+    let mut merged: Vec<Cluster> = vec![vec![]; clusters.len()];
+    clusters
+        .into_iter()
+        .enumerate()
+        .for_each(|(index, cluster)| {
+            let root = find(&mut parents, index);
+            merged[root].extend(cluster)
+        });
+    merged.retain(|cluster| !cluster.is_empty());
+    merged
+}
+
+fn leaf_containing(tree: &Octree, roots: usize, point: [i64; NSD]) -> Option<usize> {
+    let contains = |cell: &Cell| {
+        (*cell.get_min_x() as i64..cell.get_max_x() as i64).contains(&point[0])
+            && (*cell.get_min_y() as i64..cell.get_max_y() as i64).contains(&point[1])
+            && (*cell.get_min_z() as i64..cell.get_max_z() as i64).contains(&point[2])
+    };
+    let mut index = (0..roots).find(|&root| contains(&tree[root]))?;
+    while let Some(cells) = tree[index].get_cells() {
+        let cell = &tree[index];
+        let half = (cell.get_lngth() / 2) as i64;
+        index = cells[(point[0] >= *cell.get_min_x() as i64 + half) as usize
+            + 2 * (point[1] >= *cell.get_min_y() as i64 + half) as usize
+            + 4 * (point[2] >= *cell.get_min_z() as i64 + half) as usize];
+    }
+    Some(index)
+}
+
+fn touching_leaves(tree: &Octree, roots: usize, index: usize, adjacency: Adjacency) -> Vec<usize> {
+    let cell = &tree[index];
+    let minimum = [
+        *cell.get_min_x() as i64,
+        *cell.get_min_y() as i64,
+        *cell.get_min_z() as i64,
+    ];
+    let maximum = [
+        cell.get_max_x() as i64,
+        cell.get_max_y() as i64,
+        cell.get_max_z() as i64,
+    ];
+    let mut leaves = vec![];
+    [[0, 1, 2], [1, 2, 0], [2, 0, 1]]
+        .into_iter()
+        .for_each(|[a, b, c]| {
+            [minimum[a] - 1, maximum[a]]
+                .into_iter()
+                .for_each(|point_a| {
+                    [minimum[b] - 1, maximum[b]]
+                        .into_iter()
+                        .for_each(|point_b| {
+                            let mut point = [0; NSD];
+                            point[a] = point_a;
+                            point[b] = point_b;
+                            point[c] = minimum[c];
+                            while point[c] < maximum[c] {
+                                if let Some(leaf) = leaf_containing(tree, roots, point) {
+                                    leaves.push(leaf);
+                                    point[c] = match c {
+                                        0 => tree[leaf].get_max_x(),
+                                        1 => tree[leaf].get_max_y(),
+                                        _ => tree[leaf].get_max_z(),
+                                    } as i64
+                                } else {
+                                    break;
+                                }
+                            }
+                        })
+                })
+        });
+    if adjacency == Adjacency::Vertex {
+        [minimum[0] - 1, maximum[0]].into_iter().for_each(|x| {
+            [minimum[1] - 1, maximum[1]].into_iter().for_each(|y| {
+                [minimum[2] - 1, maximum[2]].into_iter().for_each(|z| {
+                    if let Some(leaf) = leaf_containing(tree, roots, [x, y, z]) {
+                        leaves.push(leaf)
+                    }
+                })
+            })
+        })
+    }
+    leaves
+}
@@ -10,22 +10,26 @@ use std::time::Instant;
 use super::{
     Coordinate, Coordinates, NSD, Octree, Tree, Vector,
     fem::{
-        Blocks, Connectivity, FiniteElementMethods, HEX, HexahedralFiniteElements,
-        NODE_NUMBERING_OFFSET,
+        AbaqusWriter, Blocks, Connectivity, ELEMENT_NUMBERING_OFFSET, FiniteElementMethods, HEX,
+        HexahedralFiniteElements, NODE_NUMBERING_OFFSET,
+    },
+    tree::{
+        Adjacency, Defeaturing, DefeaturingMethod, Reassignment, defeature_octree,
+        octree_from_voxel_data,
     },
 };
-use conspire::math::TensorArray;
-use ndarray::{Array3, Axis};
-use ndarray_npy::{ReadNpyError, ReadNpyExt, WriteNpyError, WriteNpyExt};
+use conspire::math::{TensorArray, TensorVec};
+use memmap2::Mmap;
+use ndarray::{Array3, ArrayView3, Axis, s};
+use ndarray_npy::{ReadNpyError, ReadNpyExt, ViewNpyError, ViewNpyExt, WriteNpyError, WriteNpyExt};
 use std::{
+    array::from_fn,
+    collections::HashMap,
+    convert::Infallible,
     fs::File,
     io::{BufRead, BufReader, BufWriter, Error, Write},
 };
 
-type InitialNodalCoordinates = Vec<Option<Coordinate>>;
-type VoxelDataFlattened = Blocks;
-type VoxelDataSized<const N: usize> = Vec<[usize; N]>;
-
 /// The segmentation data corresponding to voxels.
 pub type VoxelData = Array3<u8>;
 
@@ -170,15 +174,233 @@ impl From<[f64; NSD]> for Translate {
     }
 }
 
+/// A connected component of voxels with the same ID.
+///
+/// Locations are in units of voxels from the origin, such that voxel `i` spans from `i` to `i + 1`.
+#[derive(Clone, Debug)]
+pub struct Component {
+    centroid: [f64; NSD],
+    id: u8,
+    maximum: [usize; NSD],
+    minimum: [usize; NSD],
+    voxels: usize,
+}
+
+impl Component {
+    /// Returns the centroid of the component.
+    pub fn get_centroid(&self) -> &[f64; NSD] {
+        &self.centroid
+    }
+    /// Returns the voxel ID of the component.
+    pub fn get_id(&self) -> u8 {
+        self.id
+    }
+    /// Returns the maximum corner of the bounding box of the component.
+    pub fn get_maximum(&self) -> &[usize; NSD] {
+        &self.maximum
+    }
+    /// Returns the minimum corner of the bounding box of the component.
+    pub fn get_minimum(&self) -> &[usize; NSD] {
+        &self.minimum
+    }
+    /// Returns the number of voxels in the component.
+    pub fn get_voxels(&self) -> usize {
+        self.voxels
+    }
+}
+
+/// The connected components of voxels.
+pub struct Components {
+    components: Vec<Component>,
+    labels: Array3<usize>,
+}
+
+impl Components {
+    /// Returns the components in the order they were found.
+    pub fn get_components(&self) -> &[Component] {
+        &self.components
+    }
+    /// Returns the index of the component containing each voxel.
+    pub fn get_labels(&self) -> &Array3<usize> {
+        &self.labels
+    }
+}
+
+/// The morphological operations on voxels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Morphology {
+    /// Dilation followed by erosion.
+    Close,
+    /// Growth into neighboring voxels.
+    Dilate,
+    /// Removal of voxels with neighbors of other IDs.
+    Erode,
+    /// Filling of enclosed background voxels.
+    Fill,
+    /// Erosion followed by dilation.
+    Open,
+}
+
+/// The structuring element for morphological operations, given by a radius in voxels.
+#[derive(Clone, Copy, Debug)]
+pub enum StructuringElement {
+    /// Voxels within the radius in the Euclidean norm.
+    Ball(usize),
+    /// Voxels within the radius in the taxicab norm.
+    Cross(usize),
+    /// Voxels within the radius in the maximum norm.
+    Cube(usize),
+}
+
+impl StructuringElement {
+    fn offsets(&self) -> Vec<[isize; NSD]> {
+        let radius = match *self {
+            Self::Ball(radius) | Self::Cross(radius) | Self::Cube(radius) => radius as isize,
+        };
+        (-radius..=radius)
+            .flat_map(|i| {
+                (-radius..=radius).flat_map(move |j| (-radius..=radius).map(move |k| [i, j, k]))
+            })
+            .filter(|&[i, j, k]| {
+                (i, j, k) != (0, 0, 0)
+                    && match self {
+                        Self::Ball(_) => i * i + j * j + k * k <= radius * radius,
+                        Self::Cross(_) => i.abs() + j.abs() + k.abs() <= radius,
+                        Self::Cube(_) => true,
+                    }
+            })
+            .collect()
+    }
+}
+
 /// The voxels type.
 pub struct Voxels {
     data: VoxelData,
 }
 
 impl Voxels {
+    /// Closes the voxels with the IDs listed in order of priority, see [`Voxels::morph`].
+    pub fn close(
+        self,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        self.morph(Morphology::Close, ids, element, background)
+    }
+    /// Returns the face-connected components of voxels with the same ID.
+    pub fn components(&self) -> Components {
+        components_voxels(self.get_data())
+    }
+    /// Crops the voxels to those from the minimum indices up to but excluding the maximum indices,
+    /// and translates to keep the cropped voxels in place.
+    pub fn crop(
+        self,
+        minimum: [usize; NSD],
+        maximum: [usize; NSD],
+        scale: &Scale,
+        translate: &mut Translate,
+    ) -> Result<Self, String> {
+        let shape = self.data.shape();
+        if (0..NSD).any(|i| minimum[i] >= maximum[i] || maximum[i] > shape[i]) {
+            return Err(format!(
+                "Invalid crop from {:?} to {:?} for voxels of shape {:?}",
+                minimum, maximum, shape
+            ));
+        }
+        (0..NSD).for_each(|i| translate.0[i] += minimum[i] as f64 * scale.0[i]);
+        Ok(Self {
+            data: self
+                .data
+                .slice(s![
+                    minimum[0]..maximum[0],
+                    minimum[1]..maximum[1],
+                    minimum[2]..maximum[2]
+                ])
+                .to_owned(),
+        })
+    }
     /// Defeatures clusters with less than a minimum number of voxels.
     pub fn defeature(self, min_num_voxels: usize) -> Self {
-        defeature_voxels(min_num_voxels, self)
+        defeature_voxels(&min_num_voxels.into(), self).0
+    }
+    /// Defeatures clusters using the settings, returning the reassigned clusters.
+    pub fn defeature_with(self, defeaturing: &Defeaturing) -> (Self, Vec<Reassignment>) {
+        defeature_voxels(defeaturing, self)
+    }
+    /// Dilates the voxels with the IDs listed in order of priority, see [`Voxels::morph`].
+    pub fn dilate(
+        self,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        self.morph(Morphology::Dilate, ids, element, background)
+    }
+    /// Downsamples the voxels by the factor in each direction using a majority vote,
+    /// with ties going to the smallest voxel ID, and multiplies the scale by the factor.
+    /// The extent is kept when the number of voxels in each direction is divisible by the factor,
+    /// and otherwise grows since the last voxel in that direction covers a partial block.
+    pub fn downsample(self, factor: usize, scale: &mut Scale) -> Result<Self, String> {
+        if factor == 0 {
+            return Err("Need to specify a downsampling factor > 0".to_string());
+        }
+        let shape = self.data.shape();
+        let data = VoxelData::from_shape_fn(
+            (
+                shape[0].div_ceil(factor),
+                shape[1].div_ceil(factor),
+                shape[2].div_ceil(factor),
+            ),
+            |(i, j, k)| {
+                let mut counts = [0_usize; 256];
+                self.data
+                    .slice(s![
+                        i * factor..((i + 1) * factor).min(shape[0]),
+                        j * factor..((j + 1) * factor).min(shape[1]),
+                        k * factor..((k + 1) * factor).min(shape[2])
+                    ])
+                    .iter()
+                    .for_each(|&voxel| counts[voxel as usize] += 1);
+                counts
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .max_by_key(|(_, count)| **count)
+                    .map(|(voxel, _)| voxel as u8)
+                    .unwrap()
+            },
+        );
+        (0..NSD).for_each(|i| scale.0[i] *= factor as f64);
+        Ok(Self { data })
+    }
+    /// Erodes the voxels with the IDs listed in order of priority, see [`Voxels::morph`].
+    pub fn erode(
+        self,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        self.morph(Morphology::Erode, ids, element, background)
+    }
+    /// Fills holes in the voxels with the IDs listed in order of priority, see [`Voxels::morph`].
+    pub fn fill_holes(
+        self,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        self.morph(Morphology::Fill, ids, element, background)
+    }
+    /// Flips the voxels along the axis.
+    pub fn flip(mut self, axis: usize) -> Result<Self, String> {
+        if axis >= NSD {
+            return Err(format!("Invalid axis {} to flip", axis));
+        }
+        self.data.invert_axis(Axis(axis));
+        Ok(Self {
+            data: self.data.as_standard_layout().into_owned(),
+        })
     }
     /// Constructs and returns a new voxels type from an NPY file.
     pub fn from_npy(file_path: &str) -> Result<Self, ReadNpyError> {
@@ -225,6 +447,85 @@ impl Voxels {
     pub fn get_data(&self) -> &VoxelData {
         &self.data
     }
+    /// Applies a morphological operation to the voxels with the IDs listed in order of priority.
+    ///
+    /// The operation is applied to each ID separately, where neighbors outside the voxels are ignored.
+    /// Voxels removed from an ID become background, and voxels claimed by more than one ID go to the
+    /// one listed first. Voxels with IDs not listed, other than the background, are left unchanged.
+    /// Holes are background voxels not connected to the boundary through voxels of other IDs,
+    /// where connectivity is given by the structuring element.
+    pub fn morph(
+        self,
+        operation: Morphology,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        morph_voxels(self, operation, ids, element, background)
+    }
+    /// Opens the voxels with the IDs listed in order of priority, see [`Voxels::morph`].
+    pub fn open(
+        self,
+        ids: &[u8],
+        element: StructuringElement,
+        background: u8,
+    ) -> Result<Self, String> {
+        self.morph(Morphology::Open, ids, element, background)
+    }
+    /// Pads the voxels with the voxel ID on the lower and upper sides of each direction,
+    /// and translates to keep the original voxels in place.
+    pub fn pad(
+        self,
+        lower: [usize; NSD],
+        upper: [usize; NSD],
+        voxel: u8,
+        scale: &Scale,
+        translate: &mut Translate,
+    ) -> Self {
+        let shape = self.data.shape();
+        let mut data = VoxelData::from_elem(
+            (
+                lower[0] + shape[0] + upper[0],
+                lower[1] + shape[1] + upper[1],
+                lower[2] + shape[2] + upper[2],
+            ),
+            voxel,
+        );
+        data.slice_mut(s![
+            lower[0]..lower[0] + shape[0],
+            lower[1]..lower[1] + shape[1],
+            lower[2]..lower[2] + shape[2]
+        ])
+        .assign(&self.data);
+        (0..NSD).for_each(|i| translate.0[i] -= lower[i] as f64 * scale.0[i]);
+        Self { data }
+    }
+    /// Permutes the axes of the voxels, such that new axis `i` is the old axis `axes[i]`,
+    /// and permutes the scale and translation accordingly.
+    pub fn permute(
+        self,
+        axes: [usize; NSD],
+        scale: &mut Scale,
+        translate: &mut Translate,
+    ) -> Result<Self, String> {
+        let mut sorted_axes = axes;
+        sorted_axes.sort();
+        if sorted_axes != [0, 1, 2] {
+            return Err(format!("Invalid permutation {:?} of the axes", axes));
+        }
+        let (old_scale, old_translate) = (scale.0.clone(), translate.0.clone());
+        (0..NSD).for_each(|i| {
+            scale.0[i] = old_scale[axes[i]];
+            translate.0[i] = old_translate[axes[i]];
+        });
+        Ok(Self {
+            data: self
+                .data
+                .permuted_axes(axes)
+                .as_standard_layout()
+                .into_owned(),
+        })
+    }
     /// Converts the voxels type into a finite elements type, consuming the voxels type.
     pub fn into_finite_elements(
         self,
@@ -240,6 +541,73 @@ impl Voxels {
             nodal_coordinates,
         ))
     }
+    /// Relabels the voxels using the map from old to new voxel IDs,
+    /// leaving voxel IDs not in the map unchanged.
+    pub fn relabel(self, map: &HashMap<u8, u8>) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|id| id as u8);
+        map.iter()
+            .for_each(|(&old, &new)| table[old as usize] = new);
+        Self {
+            data: self.data.mapv(|voxel| table[voxel as usize]),
+        }
+    }
+    /// Renumbers the nonzero voxel IDs contiguously from one in ascending order.
+    pub fn renumber(self) -> Self {
+        let mut present = [false; 256];
+        self.data
+            .iter()
+            .for_each(|&voxel| present[voxel as usize] = true);
+        let map = (1..=u8::MAX)
+            .filter(|&id| present[id as usize])
+            .zip(1..)
+            .collect();
+        self.relabel(&map)
+    }
+    /// Splits disconnected components into distinct voxel IDs, where the largest component keeps
+    /// the original voxel ID and the others take voxel IDs above those in use in ascending order,
+    /// except for components with the voxel IDs to keep.
+    pub fn split_components(self, keep: &[u8]) -> Result<Self, String> {
+        let components = self.components();
+        let mut present = [false; 256];
+        components
+            .get_components()
+            .iter()
+            .for_each(|component| present[component.get_id() as usize] = true);
+        let mut largest = [None; 256];
+        components
+            .get_components()
+            .iter()
+            .enumerate()
+            .for_each(|(index, component)| {
+                let id = component.get_id() as usize;
+                if largest[id].is_none_or(|other: usize| {
+                    components.get_components()[other].get_voxels() < component.get_voxels()
+                }) {
+                    largest[id] = Some(index)
+                }
+            });
+        let mut unused =
+            (present.iter().rposition(|&used| used).unwrap_or(0) + 1..=255).map(|id| id as u8);
+        let ids = components
+            .get_components()
+            .iter()
+            .enumerate()
+            .map(|(index, component)| {
+                let id = component.get_id();
+                if keep.contains(&id) || largest[id as usize] == Some(index) {
+                    Ok(id)
+                } else {
+                    unused.next().ok_or(format!(
+                        "Not enough unused voxel IDs to split the {} components",
+                        components.get_components().len()
+                    ))
+                }
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        Ok(Self {
+            data: components.get_labels().mapv(|label| ids[label]),
+        })
+    }
     /// Writes the internal voxels data to an NPY file.
     pub fn write_npy(&self, file_path: &str) -> Result<(), WriteNpyError> {
         write_voxels_to_npy(self.get_data(), file_path)
@@ -250,178 +618,577 @@ impl Voxels {
     }
 }
 
-fn defeature_voxels(min_num_voxels: usize, voxels: Voxels) -> Voxels {
-    let (nel, mut tree) = Octree::from_voxels(voxels);
-    tree.balance(true);
-    tree.defeature(min_num_voxels);
-    Voxels::from_octree(nel, tree)
+/// The number of voxels along each side of a chunk.
+const CHUNK_LENGTH: usize = 32;
+
+const CHUNK_VOLUME: usize = CHUNK_LENGTH * CHUNK_LENGTH * CHUNK_LENGTH;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Chunk {
+    Dense(Box<[u8]>),
+    Uniform(u8),
 }
 
-fn filter_voxel_data(data: &VoxelData, remove: Option<Blocks>) -> (VoxelDataSized<NSD>, Blocks) {
-    #[cfg(feature = "profile")]
-    let time = Instant::now();
-    let mut removed_data = remove.unwrap_or_default();
-    removed_data.sort();
-    removed_data.dedup();
-    let (filtered_voxel_data, element_blocks) = data
-        .axis_iter(Axis(2))
-        .enumerate()
-        .flat_map(|(k, data_k)| {
-            data_k
-                .axis_iter(Axis(1))
-                .enumerate()
-                .flat_map(|(j, data_kj)| {
-                    data_kj
-                        .iter()
-                        .enumerate()
-                        .filter(|&(_, &data_kji)| removed_data.binary_search(&data_kji).is_err())
-                        .map(|(i, data_kji)| ([i, j, k], *data_kji))
-                        .collect::<Vec<([usize; NSD], u8)>>()
+impl Chunk {
+    fn new(shape: [usize; NSD], origin: [usize; NSD], voxel: impl Fn([usize; NSD]) -> u8) -> Self {
+        //
+        // Voxels past the end repeat the last voxel so that uniform chunks stay uniform.
+        //
+        let data = (0..CHUNK_VOLUME)
+            .map(|index| {
+                let local = [
+                    index % CHUNK_LENGTH,
+                    index / CHUNK_LENGTH % CHUNK_LENGTH,
+                    index / CHUNK_LENGTH / CHUNK_LENGTH,
+                ];
+                voxel(from_fn(|n| (origin[n] + local[n]).min(shape[n] - 1)))
+            })
+            .collect::<Vec<u8>>();
+        if data.iter().all(|&voxel| voxel == data[0]) {
+            Self::Uniform(data[0])
+        } else {
+            Self::Dense(data.into_boxed_slice())
+        }
+    }
+}
+
+/// The voxels type stored in chunks, where chunks with a single voxel ID take no extra space.
+pub struct ChunkedVoxels {
+    chunks: Vec<Chunk>,
+    nel: Nel,
+}
+
+impl ChunkedVoxels {
+    /// Constructs and returns a new chunked voxels type from a memory-mapped NPY file.
+    ///
+    /// The file must not be modified or truncated by another process while it is being read,
+    /// since memory-mapping it is only sound if its contents do not change.
+    /// The mapping is dropped before returning, so the chunked voxels do not borrow the file.
+    pub fn from_npy(file_path: &str) -> Result<Self, ViewNpyError> {
+        let file = File::open(file_path).map_err(ViewNpyError::Io)?;
+        //
+        // Safe as long as the file is not changed during the call, as documented above.
+        //
+        let mmap = unsafe { Mmap::map(&file) }.map_err(ViewNpyError::Io)?;
+        Ok(Self::from_view(&ArrayView3::view_npy(&mmap)?))
+    }
+    /// Constructs and returns a new chunked voxels type from an SPN file, one slab of chunks at a time.
+    pub fn from_spn(file_path: &str, nel: Nel) -> Result<Self, String> {
+        let layer = nel.x() * nel.y();
+        let total = layer * nel.z();
+        let mut chunks = vec![];
+        let mut count = 0;
+        let slab_len = layer * CHUNK_LENGTH;
+        let mut slab = vec![0; slab_len];
+        read_spn(file_path, &nel, |voxel| {
+            slab[count % slab_len] = voxel;
+            count += 1;
+            if count % slab_len == 0 || count == total {
+                let shape = [*nel.x(), *nel.y(), (count - 1) % slab_len / layer + 1];
+                (0..nel.y().div_ceil(CHUNK_LENGTH)).for_each(|cj| {
+                    (0..nel.x().div_ceil(CHUNK_LENGTH)).for_each(|ci| {
+                        chunks.push(Chunk::new(
+                            shape,
+                            [ci * CHUNK_LENGTH, cj * CHUNK_LENGTH, 0],
+                            |[i, j, k]| slab[i + nel.x() * (j + nel.y() * k)],
+                        ))
+                    })
+                });
+            }
+        })?;
+        Ok(Self { chunks, nel })
+    }
+    /// Returns the voxel ID at the given indices.
+    pub fn get(&self, [i, j, k]: [usize; NSD]) -> u8 {
+        let chunk = i / CHUNK_LENGTH
+            + self.nel.x().div_ceil(CHUNK_LENGTH)
+                * (j / CHUNK_LENGTH + self.nel.y().div_ceil(CHUNK_LENGTH) * (k / CHUNK_LENGTH));
+        match &self.chunks[chunk] {
+            Chunk::Dense(data) => {
+                data[i % CHUNK_LENGTH
+                    + CHUNK_LENGTH * (j % CHUNK_LENGTH + CHUNK_LENGTH * (k % CHUNK_LENGTH))]
+            }
+            Chunk::Uniform(voxel) => *voxel,
+        }
+    }
+    fn from_view(data: &ArrayView3<u8>) -> Self {
+        let shape = [data.shape()[0], data.shape()[1], data.shape()[2]];
+        let mut chunks = vec![];
+        (0..shape[2].div_ceil(CHUNK_LENGTH)).for_each(|ck| {
+            (0..shape[1].div_ceil(CHUNK_LENGTH)).for_each(|cj| {
+                (0..shape[0].div_ceil(CHUNK_LENGTH)).for_each(|ci| {
+                    chunks.push(Chunk::new(
+                        shape,
+                        [ci * CHUNK_LENGTH, cj * CHUNK_LENGTH, ck * CHUNK_LENGTH],
+                        |index| data[index],
+                    ))
                 })
-                .collect::<Vec<([usize; NSD], u8)>>()
-        })
-        .unzip();
-    #[cfg(feature = "profile")]
-    println!(
-        "           \x1b[1;93m⤷ Removed voxels\x1b[0m {:?}",
-        time.elapsed()
-    );
-    (filtered_voxel_data, element_blocks)
+            })
+        });
+        Self {
+            chunks,
+            nel: shape.into(),
+        }
+    }
+    /// Returns the number of voxels in each direction.
+    pub fn get_nel(&self) -> &Nel {
+        &self.nel
+    }
+    /// Writes the hexahedral finite elements of the chunked voxels to an Abaqus input file.
+    ///
+    /// Nodes are only created for the voxels that are not removed.
+    /// The voxels are swept once for the nodes and once for the elements of each block,
+    /// so the nodes and elements are never all held in memory.
+    pub fn write_inp(
+        &self,
+        file_path: &str,
+        remove: Option<Blocks>,
+        scale: Scale,
+        translate: Translate,
+    ) -> Result<(), Error> {
+        let nel = [*self.nel.x(), *self.nel.y(), *self.nel.z()];
+        let voxel = |index| self.get(index);
+        let kept = kept_blocks(remove);
+        let mut number_of_elements = 0;
+        let mut number_of_nodes = 0;
+        let mut present = [false; 256];
+        let Ok(()) = sweep_voxels::<Infallible>(
+            nel,
+            voxel,
+            &kept,
+            &scale,
+            &translate,
+            |_| {
+                number_of_nodes += 1;
+                Ok(())
+            },
+            |block, _| {
+                number_of_elements += 1;
+                present[block as usize] = true;
+                Ok(())
+            },
+        );
+        let blocks = (0..=u8::MAX)
+            .filter(|&block| present[block as usize])
+            .collect::<Blocks>();
+        let mut writer =
+            AbaqusWriter::<HEX>::create(file_path, number_of_elements, number_of_nodes)?;
+        let mut node = NODE_NUMBERING_OFFSET;
+        sweep_voxels::<Error>(
+            nel,
+            voxel,
+            &kept,
+            &scale,
+            &translate,
+            |coordinates| {
+                writer.node(node, &coordinates)?;
+                node += 1;
+                Ok(())
+            },
+            |_, _| Ok(()),
+        )?;
+        blocks.iter().try_for_each(|current_block| {
+            writer.block(current_block)?;
+            let mut element = ELEMENT_NUMBERING_OFFSET;
+            sweep_voxels::<Error>(
+                nel,
+                voxel,
+                &kept,
+                &scale,
+                &translate,
+                |_| Ok(()),
+                |block, connectivity| {
+                    if &block == current_block {
+                        writer.element(element, &connectivity)?
+                    }
+                    element += 1;
+                    Ok(())
+                },
+            )
+        })?;
+        writer.finish(&blocks)
+    }
 }
 
-fn initial_element_node_connectivity(
-    filtered_voxel_data: &VoxelDataSized<NSD>,
-    nelxplus1: &usize,
-    nelyplus1: &usize,
-) -> Connectivity<HEX> {
-    #[cfg(feature = "profile")]
-    let time = Instant::now();
-    let element_node_connectivity: Connectivity<HEX> = filtered_voxel_data
+impl From<ChunkedVoxels> for Voxels {
+    fn from(voxels: ChunkedVoxels) -> Self {
+        Self {
+            data: VoxelData::from_shape_fn(
+                <(usize, usize, usize)>::from(voxels.nel),
+                |(i, j, k)| voxels.get([i, j, k]),
+            ),
+        }
+    }
+}
+
+impl From<Voxels> for ChunkedVoxels {
+    fn from(voxels: Voxels) -> Self {
+        Self::from_view(&voxels.data.view())
+    }
+}
+
+fn defeature_voxels(defeaturing: &Defeaturing, voxels: Voxels) -> (Voxels, Vec<Reassignment>) {
+    match defeaturing.method {
+        DefeaturingMethod::Octree => {
+            //
+            // The padding uses an unused voxel ID, if any, so that it neither joins nor votes for
+            // clusters, and is cropped off afterward.
+            //
+            let shape = voxels.data.shape().to_vec();
+            let padding = unused_voxel_id(&voxels.data);
+            let (nel, mut tree) = octree_from_voxel_data(&voxels.data, padding);
+            tree.balance(true);
+            let reassignments = defeature_octree(&mut tree, defeaturing, padding);
+            let data = Voxels::from_octree(nel, tree)
+                .data
+                .slice(s![..shape[0], ..shape[1], ..shape[2]])
+                .to_owned();
+            (Voxels { data }, reassignments)
+        }
+        DefeaturingMethod::UnionFind => defeature_voxels_union_find(defeaturing, voxels),
+    }
+}
+
+fn defeature_voxels_union_find(
+    defeaturing: &Defeaturing,
+    voxels: Voxels,
+) -> (Voxels, Vec<Reassignment>) {
+    let mut data = voxels.data.as_standard_layout().into_owned();
+    let shape = data.shape().to_vec();
+    let faces = StructuringElement::Cross(1).offsets();
+    let forward = adjacency_offsets(defeaturing.adjacency)
+        .into_iter()
+        .filter(|offset| offset > &[0; NSD])
+        .collect::<Vec<[isize; NSD]>>();
+    let flat = |[i, j, k]: [usize; NSD]| (i * shape[1] + j) * shape[2] + k;
+    let mut clusters: Vec<Vec<[usize; NSD]>>;
+    let mut counts = vec![];
+    let mut forest;
+    let mut reassigned;
+    let mut reassignments = vec![];
+    let mut roots = HashMap::new();
+    #[allow(unused_variables)]
+    for iteration in 1.. {
+        #[cfg(feature = "profile")]
+        let time = Instant::now();
+        forest = vec![-1; data.len()];
+        data.indexed_iter().for_each(|((i, j, k), &id)| {
+            neighbors([i, j, k], &shape, &forward)
+                .filter(|&neighbor| data[neighbor] == id)
+                .for_each(|neighbor| union_roots(&mut forest, flat([i, j, k]), flat(neighbor)))
+        });
+        clusters = vec![];
+        roots.clear();
+        data.indexed_iter().for_each(|((i, j, k), &id)| {
+            let root = find_root(&mut forest, flat([i, j, k]));
+            if defeaturing
+                .threshold(id)
+                .is_some_and(|minimum| ((-forest[root]) as usize) < minimum)
+            {
+                let cluster = *roots.entry(root).or_insert_with(|| {
+                    clusters.push(vec![]);
+                    clusters.len() - 1
+                });
+                clusters[cluster].push([i, j, k])
+            }
+        });
+        reassigned = false;
+        clusters.iter().for_each(|cluster| {
+            let block = data[cluster[0]];
+            count_blocks(
+                &mut counts,
+                cluster.iter().flat_map(|&voxel| {
+                    neighbors(voxel, &shape, &faces)
+                        .map(|neighbor| data[neighbor])
+                        .filter(|&face_block| face_block != block)
+                }),
+            );
+            if !counts.is_empty() {
+                let new_block = majority_block(&counts);
+                cluster.iter().for_each(|&voxel| data[voxel] = new_block);
+                reassignments.push(Reassignment {
+                    block,
+                    minimum: cluster.iter().fold([usize::MAX; NSD], |minimum, voxel| {
+                        [
+                            minimum[0].min(voxel[0]),
+                            minimum[1].min(voxel[1]),
+                            minimum[2].min(voxel[2]),
+                        ]
+                    }),
+                    new_block,
+                    voxels: cluster.len(),
+                });
+                reassigned = true
+            }
+        });
+        #[cfg(feature = "profile")]
+        println!(
+            "             \x1b[1;93mDefeaturing iteration {}\x1b[0m {:?} ",
+            iteration,
+            time.elapsed()
+        );
+        if !protrusions_voxels(&mut data, &faces, &defeaturing.protected) && !reassigned {
+            break;
+        }
+    }
+    (Voxels { data }, reassignments)
+}
+
+fn protrusions_voxels(data: &mut VoxelData, faces: &[[isize; NSD]], protected: &Blocks) -> bool {
+    let shape = data.shape().to_vec();
+    let mut counts = vec![];
+    let mut protruded = false;
+    let mut protrusions: Vec<([usize; NSD], u8)>;
+    loop {
+        protrusions = data
+            .indexed_iter()
+            .filter(|(_, block)| !protected.contains(block))
+            .filter_map(|((i, j, k), &block)| {
+                count_blocks(
+                    &mut counts,
+                    neighbors([i, j, k], &shape, faces).map(|neighbor| data[neighbor]),
+                );
+                (counts
+                    .iter()
+                    .filter(|(face_block, _)| *face_block != block)
+                    .map(|(_, count)| count)
+                    .sum::<usize>()
+                    >= 5)
+                    .then(|| ([i, j, k], majority_block(&counts)))
+            })
+            .collect();
+        if protrusions.is_empty() {
+            return protruded;
+        }
+        protruded = true;
+        protrusions
+            .iter()
+            .for_each(|&(voxel, new_block)| data[voxel] = new_block)
+    }
+}
+
+fn adjacency_offsets(adjacency: Adjacency) -> Vec<[isize; NSD]> {
+    let order = match adjacency {
+        Adjacency::Face => 1,
+        Adjacency::Edge => 2,
+        Adjacency::Vertex => 3,
+    };
+    StructuringElement::Cube(1)
+        .offsets()
+        .into_iter()
+        .filter(|offset| offset.iter().map(|n| n.abs()).sum::<isize>() <= order)
+        .collect()
+}
+
+fn find_root(forest: &mut [isize], mut node: usize) -> usize {
+    while forest[node] >= 0 {
+        let parent = forest[node] as usize;
+        if forest[parent] >= 0 {
+            forest[node] = forest[parent]
+        }
+        node = parent
+    }
+    node
+}
+
+fn count_blocks(counts: &mut Vec<(u8, usize)>, blocks: impl Iterator<Item = u8>) {
+    counts.clear();
+    blocks.for_each(
+        |block| match counts.iter_mut().find(|(entry, _)| *entry == block) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((block, 1)),
+        },
+    )
+}
+
+fn majority_block(counts: &[(u8, usize)]) -> u8 {
+    counts
         .iter()
-        .map(|&[i, j, k]| {
-            [
-                i + j * nelxplus1 + k * nelxplus1 * nelyplus1 + NODE_NUMBERING_OFFSET,
-                i + j * nelxplus1 + k * nelxplus1 * nelyplus1 + 1 + NODE_NUMBERING_OFFSET,
-                i + (j + 1) * nelxplus1 + k * nelxplus1 * nelyplus1 + 1 + NODE_NUMBERING_OFFSET,
-                i + (j + 1) * nelxplus1 + k * nelxplus1 * nelyplus1 + NODE_NUMBERING_OFFSET,
-                i + j * nelxplus1 + (k + 1) * nelxplus1 * nelyplus1 + NODE_NUMBERING_OFFSET,
-                i + j * nelxplus1 + (k + 1) * nelxplus1 * nelyplus1 + 1 + NODE_NUMBERING_OFFSET,
-                i + (j + 1) * nelxplus1
-                    + (k + 1) * nelxplus1 * nelyplus1
-                    + 1
-                    + NODE_NUMBERING_OFFSET,
-                i + (j + 1) * nelxplus1 + (k + 1) * nelxplus1 * nelyplus1 + NODE_NUMBERING_OFFSET,
-            ]
+        .fold((0, 0), |(block, maximum), &(candidate, count)| {
+            if count > maximum || (count == maximum && candidate < block) {
+                (candidate, count)
+            } else {
+                (block, maximum)
+            }
         })
-        .collect();
-    #[cfg(feature = "profile")]
-    println!(
-        "             \x1b[1;93mElement-to-node connectivity\x1b[0m {:?}",
-        time.elapsed()
-    );
-    element_node_connectivity
+        .0
 }
 
-fn initial_nodal_coordinates(
-    element_node_connectivity: &Connectivity<HEX>,
-    filtered_voxel_data: &VoxelDataSized<NSD>,
-    number_of_nodes_unfiltered: usize,
-    scale: Scale,
-    translate: Translate,
-) -> Result<InitialNodalCoordinates, String> {
-    #[cfg(feature = "profile")]
-    let time = Instant::now();
-    let mut nodal_coordinates: InitialNodalCoordinates =
-        (0..number_of_nodes_unfiltered).map(|_| None).collect();
-    filtered_voxel_data
-        .iter()
-        .zip(element_node_connectivity.iter())
-        .for_each(|(&[x, y, z], connectivity)| {
-            nodal_coordinates[connectivity[0] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                x as f64 * scale.x() + translate.x(),
-                y as f64 * scale.y() + translate.y(),
-                z as f64 * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[1] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                (x as f64 + 1.0) * scale.x() + translate.x(),
-                y as f64 * scale.y() + translate.y(),
-                z as f64 * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[2] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                (x as f64 + 1.0) * scale.x() + translate.x(),
-                (y as f64 + 1.0) * scale.y() + translate.y(),
-                z as f64 * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[3] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                x as f64 * scale.x() + translate.x(),
-                (y as f64 + 1.0) * scale.y() + translate.y(),
-                z as f64 * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[4] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                x as f64 * scale.x() + translate.x(),
-                y as f64 * scale.y() + translate.y(),
-                (z as f64 + 1.0) * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[5] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                (x as f64 + 1.0) * scale.x() + translate.x(),
-                y as f64 * scale.y() + translate.y(),
-                (z as f64 + 1.0) * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[6] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                (x as f64 + 1.0) * scale.x() + translate.x(),
-                (y as f64 + 1.0) * scale.y() + translate.y(),
-                (z as f64 + 1.0) * scale.z() + translate.z(),
-            ]));
-            nodal_coordinates[connectivity[7] - NODE_NUMBERING_OFFSET] = Some(Coordinate::new([
-                x as f64 * scale.x() + translate.x(),
-                (y as f64 + 1.0) * scale.y() + translate.y(),
-                (z as f64 + 1.0) * scale.z() + translate.z(),
-            ]));
-        });
-    #[cfg(feature = "profile")]
-    println!(
-        "             \x1b[1;93mNodal coordinates\x1b[0m {:?}",
-        time.elapsed()
-    );
-    Ok(nodal_coordinates)
+fn union_roots(forest: &mut [isize], a: usize, b: usize) {
+    let mut root_a = find_root(forest, a);
+    let mut root_b = find_root(forest, b);
+    if root_a != root_b {
+        if forest[root_a] > forest[root_b] {
+            std::mem::swap(&mut root_a, &mut root_b)
+        }
+        forest[root_a] += forest[root_b];
+        forest[root_b] = root_a as isize
+    }
 }
 
-fn renumber_nodes(
-    element_node_connectivity: &mut Connectivity<HEX>,
-    mut initial_nodal_coordinates: InitialNodalCoordinates,
-    number_of_nodes_unfiltered: usize,
-) -> Coordinates {
-    #[cfg(feature = "profile")]
-    let time = std::time::Instant::now();
-    let mut mapping = vec![0; number_of_nodes_unfiltered];
-    let mut nodes = 1..=number_of_nodes_unfiltered;
-    initial_nodal_coordinates
+type Mask = Array3<bool>;
+
+fn unused_voxel_id(data: &VoxelData) -> Option<u8> {
+    let mut present = [false; 256];
+    data.iter().for_each(|&id| present[id as usize] = true);
+    present.iter().position(|&used| !used).map(|id| id as u8)
+}
+
+fn components_voxels(data: &VoxelData) -> Components {
+    //
+    // The padding uses an unused voxel ID, if any, so that it does not connect components.
+    //
+    let padding = unused_voxel_id(data);
+    let (_, mut tree) = octree_from_voxel_data(data, padding);
+    tree.balance(true);
+    let corner = |leaf: usize| {
+        [
+            *tree[leaf].get_min_x() as usize,
+            *tree[leaf].get_min_y() as usize,
+            *tree[leaf].get_min_z() as usize,
+        ]
+    };
+    let mut clusters = tree.clusters(&padding.map(|id| vec![id]), None);
+    clusters
+        .iter_mut()
+        .for_each(|cluster| cluster.sort_by_key(|&leaf| corner(leaf)));
+    clusters.sort_by_key(|cluster| corner(cluster[0]));
+    let mut labels = Array3::from_elem(data.dim(), usize::MAX);
+    let components = clusters
         .iter()
         .enumerate()
-        .filter(|&(_, coordinate)| coordinate.is_some())
-        .for_each(|(index, _)| {
-            if let Some(node) = nodes.next() {
-                mapping[index] = node;
+        .map(|(label, cluster)| {
+            let mut centroid = [0.0; NSD];
+            let mut maximum = [0; NSD];
+            let mut minimum = [usize::MAX; NSD];
+            let mut voxels = 0;
+            cluster.iter().for_each(|&leaf| {
+                let lngth = *tree[leaf].get_lngth() as usize;
+                let [i, j, k] = corner(leaf);
+                let count = lngth.pow(3);
+                voxels += count;
+                [i, j, k].iter().enumerate().for_each(|(n, &index)| {
+                    centroid[n] += count as f64 * (index as f64 + 0.5 * lngth as f64);
+                    maximum[n] = maximum[n].max(index + lngth);
+                    minimum[n] = minimum[n].min(index);
+                });
+                labels
+                    .slice_mut(s![i..i + lngth, j..j + lngth, k..k + lngth])
+                    .fill(label)
+            });
+            Component {
+                centroid: centroid.map(|sum| sum / voxels as f64),
+                id: tree[cluster[0]].get_block(),
+                maximum,
+                minimum,
+                voxels,
             }
-        });
-    element_node_connectivity
-        .iter_mut()
-        .for_each(|connectivity| {
-            connectivity
-                .iter_mut()
-                .for_each(|node| *node = mapping[*node - NODE_NUMBERING_OFFSET])
-        });
-    initial_nodal_coordinates.retain(|coordinate| coordinate.is_some());
-    let nodal_coordinates = initial_nodal_coordinates
-        .into_iter()
-        .map(|entry| entry.unwrap())
+        })
         .collect();
-    #[cfg(feature = "profile")]
-    println!(
-        "             \x1b[1;93mRenumbered nodes\x1b[0m {:?}",
-        time.elapsed()
-    );
-    nodal_coordinates
+    Components { components, labels }
+}
+
+fn morph_voxels(
+    voxels: Voxels,
+    operation: Morphology,
+    ids: &[u8],
+    element: StructuringElement,
+    background: u8,
+) -> Result<Voxels, String> {
+    if ids.contains(&background) {
+        return Err(format!(
+            "Cannot apply morphological operations to the background voxel ID {}",
+            background
+        ));
+    }
+    let offsets = element.offsets();
+    let data = voxels.data;
+    let masks = ids
+        .iter()
+        .map(|&id| {
+            let mask = data.mapv(|voxel| voxel == id);
+            match operation {
+                Morphology::Close => erode_mask(&dilate_mask(&mask, &offsets), &offsets),
+                Morphology::Dilate => dilate_mask(&mask, &offsets),
+                Morphology::Erode => erode_mask(&mask, &offsets),
+                Morphology::Fill => fill_mask(&mask, &data, background, &offsets),
+                Morphology::Open => dilate_mask(&erode_mask(&mask, &offsets), &offsets),
+            }
+        })
+        .collect::<Vec<Mask>>();
+    Ok(Voxels {
+        data: VoxelData::from_shape_fn(data.dim(), |index| {
+            let voxel = data[index];
+            if voxel != background && !ids.contains(&voxel) {
+                voxel
+            } else {
+                ids.iter()
+                    .zip(masks.iter())
+                    .find(|(_, mask)| mask[index])
+                    .map_or(background, |(&id, _)| id)
+            }
+        }),
+    })
+}
+
+fn neighbors<'a>(
+    [i, j, k]: [usize; NSD],
+    shape: &'a [usize],
+    offsets: &'a [[isize; NSD]],
+) -> impl Iterator<Item = [usize; NSD]> + 'a {
+    offsets.iter().filter_map(move |[a, b, c]| {
+        let neighbor = [
+            i.checked_add_signed(*a)?,
+            j.checked_add_signed(*b)?,
+            k.checked_add_signed(*c)?,
+        ];
+        (neighbor[0] < shape[0] && neighbor[1] < shape[1] && neighbor[2] < shape[2])
+            .then_some(neighbor)
+    })
+}
+
+fn dilate_mask(mask: &Mask, offsets: &[[isize; NSD]]) -> Mask {
+    let shape = mask.shape();
+    Mask::from_shape_fn(mask.dim(), |(i, j, k)| {
+        mask[[i, j, k]] || neighbors([i, j, k], shape, offsets).any(|neighbor| mask[neighbor])
+    })
+}
+
+fn erode_mask(mask: &Mask, offsets: &[[isize; NSD]]) -> Mask {
+    let shape = mask.shape();
+    Mask::from_shape_fn(mask.dim(), |(i, j, k)| {
+        mask[[i, j, k]] && neighbors([i, j, k], shape, offsets).all(|neighbor| mask[neighbor])
+    })
+}
+
+fn fill_mask(mask: &Mask, data: &VoxelData, background: u8, offsets: &[[isize; NSD]]) -> Mask {
+    let shape = mask.shape();
+    let mut outside = Mask::from_elem(mask.dim(), false);
+    let mut stack = mask
+        .indexed_iter()
+        .filter(|((i, j, k), inside)| {
+            !**inside
+                && (*i == 0
+                    || *j == 0
+                    || *k == 0
+                    || *i == shape[0] - 1
+                    || *j == shape[1] - 1
+                    || *k == shape[2] - 1)
+        })
+        .map(|((i, j, k), _)| [i, j, k])
+        .collect::<Vec<[usize; NSD]>>();
+    stack.iter().for_each(|&index| outside[index] = true);
+    while let Some(index) = stack.pop() {
+        neighbors(index, shape, offsets).for_each(|neighbor| {
+            if !mask[neighbor] && !outside[neighbor] {
+                outside[neighbor] = true;
+                stack.push(neighbor)
+            }
+        })
+    }
+    Mask::from_shape_fn(mask.dim(), |index| {
+        mask[index] || (!outside[index] && data[index] == background)
+    })
 }
 
 fn finite_element_data_from_data(
@@ -431,26 +1198,141 @@ fn finite_element_data_from_data(
     translate: Translate,
 ) -> Result<(Blocks, Connectivity<HEX>, Coordinates), String> {
     let shape = data.shape();
-    let nelxplus1 = shape[0] + 1;
-    let nelyplus1 = shape[1] + 1;
-    let nelzplus1 = shape[2] + 1;
-    let number_of_nodes_unfiltered = nelxplus1 * nelyplus1 * nelzplus1;
-    let (filtered_voxel_data, element_blocks) = filter_voxel_data(data, remove);
-    let mut element_node_connectivity =
-        initial_element_node_connectivity(&filtered_voxel_data, &nelxplus1, &nelyplus1);
-    let initial_nodal_coordinates = initial_nodal_coordinates(
-        &element_node_connectivity,
-        &filtered_voxel_data,
-        number_of_nodes_unfiltered,
+    Ok(finite_element_data_from_voxels(
+        [shape[0], shape[1], shape[2]],
+        |index| data[index],
+        remove,
         scale,
         translate,
-    )?;
-    let nodal_coordinates = renumber_nodes(
-        &mut element_node_connectivity,
-        initial_nodal_coordinates,
-        number_of_nodes_unfiltered,
+    ))
+}
+
+fn finite_element_data_from_voxels(
+    nel: [usize; NSD],
+    voxel: impl Fn([usize; NSD]) -> u8,
+    remove: Option<Blocks>,
+    scale: Scale,
+    translate: Translate,
+) -> (Blocks, Connectivity<HEX>, Coordinates) {
+    #[cfg(feature = "profile")]
+    let time = Instant::now();
+    let mut element_blocks = vec![];
+    let mut element_node_connectivity = vec![];
+    let mut nodal_coordinates = Coordinates::zero(0);
+    let Ok(()) = sweep_voxels::<Infallible>(
+        nel,
+        voxel,
+        &kept_blocks(remove),
+        &scale,
+        &translate,
+        |coordinates| {
+            nodal_coordinates.push(coordinates);
+            Ok(())
+        },
+        |block, connectivity| {
+            element_blocks.push(block);
+            element_node_connectivity.push(connectivity);
+            Ok(())
+        },
     );
-    Ok((element_blocks, element_node_connectivity, nodal_coordinates))
+    #[cfg(feature = "profile")]
+    println!(
+        "             \x1b[1;93mStreamed hexahedral elements\x1b[0m {:?}",
+        time.elapsed()
+    );
+    (element_blocks, element_node_connectivity, nodal_coordinates)
+}
+
+fn kept_blocks(remove: Option<Blocks>) -> [bool; 256] {
+    let mut kept = [true; 256];
+    remove
+        .unwrap_or_default()
+        .into_iter()
+        .for_each(|block| kept[block as usize] = false);
+    kept
+}
+
+fn sweep_voxels<E>(
+    nel: [usize; NSD],
+    voxel: impl Fn([usize; NSD]) -> u8,
+    kept: &[bool; 256],
+    scale: &Scale,
+    translate: &Translate,
+    mut each_node: impl FnMut(Coordinate) -> Result<(), E>,
+    mut each_element: impl FnMut(u8, [usize; HEX]) -> Result<(), E>,
+) -> Result<(), E> {
+    //
+    // Sweeps through the layers of nodes in the z-direction, only keeping the node numbers
+    // of the previous layer, and numbering the nodes of each layer that neighbor a kept voxel.
+    // The elements and nodes are numbered in the same order as the full grid of voxels.
+    //
+    let [nelx, nely, nelz] = nel;
+    let layer = |k: usize| -> Vec<Option<u8>> {
+        (0..nely)
+            .flat_map(|j| {
+                (0..nelx).map(move |i| (i, j)).map(|(i, j)| {
+                    let block = voxel([i, j, k]);
+                    kept[block as usize].then_some(block)
+                })
+            })
+            .collect()
+    };
+    let mut number_of_nodes = 0;
+    let mut nodes_below = vec![None; (nelx + 1) * (nely + 1)];
+    let mut nodes_above = nodes_below.clone();
+    let mut voxels_below = vec![None; nelx * nely];
+    let node = |i: usize, j: usize| i + j * (nelx + 1);
+    (0..=nelz).try_for_each(|k| {
+        let voxels_above = if k < nelz {
+            layer(k)
+        } else {
+            vec![None; nelx * nely]
+        };
+        (0..=nely).try_for_each(|j| {
+            (0..=nelx).try_for_each(|i| {
+                nodes_above[node(i, j)] = if (j.saturating_sub(1)..(j + 1).min(nely)).any(|b| {
+                    (i.saturating_sub(1)..(i + 1).min(nelx)).any(|a| {
+                        voxels_below[a + b * nelx].is_some() || voxels_above[a + b * nelx].is_some()
+                    })
+                }) {
+                    each_node(Coordinate::new([
+                        i as f64 * scale.x() + translate.x(),
+                        j as f64 * scale.y() + translate.y(),
+                        k as f64 * scale.z() + translate.z(),
+                    ]))?;
+                    number_of_nodes += 1;
+                    Some(number_of_nodes - 1 + NODE_NUMBERING_OFFSET)
+                } else {
+                    None
+                };
+                Ok(())
+            })
+        })?;
+        if k > 0 {
+            (0..nely).try_for_each(|j| {
+                (0..nelx).try_for_each(|i| match voxels_below[i + j * nelx] {
+                    Some(block) => each_element(
+                        block,
+                        [
+                            nodes_below[node(i, j)],
+                            nodes_below[node(i + 1, j)],
+                            nodes_below[node(i + 1, j + 1)],
+                            nodes_below[node(i, j + 1)],
+                            nodes_above[node(i, j)],
+                            nodes_above[node(i + 1, j)],
+                            nodes_above[node(i + 1, j + 1)],
+                            nodes_above[node(i, j + 1)],
+                        ]
+                        .map(|node| node.expect("node of kept voxel not numbered")),
+                    ),
+                    None => Ok(()),
+                })
+            })?
+        }
+        std::mem::swap(&mut nodes_below, &mut nodes_above);
+        voxels_below = voxels_above;
+        Ok(())
+    })
 }
 
 pub struct IntermediateError {
@@ -475,27 +1357,67 @@ fn voxel_data_from_npy(file_path: &str) -> Result<VoxelData, ReadNpyError> {
     VoxelData::read_npy(File::open(file_path)?)
 }
 
-fn voxel_data_from_spn(file_path: &str, nel: Nel) -> Result<VoxelData, IntermediateError> {
-    let data_flattened = BufReader::new(File::open(file_path)?)
-        .lines()
-        .map(|line| line.unwrap().parse().unwrap())
-        .collect::<VoxelDataFlattened>();
+fn voxel_data_from_spn(file_path: &str, nel: Nel) -> Result<VoxelData, String> {
     let mut data = VoxelData::from(nel);
-    data.axis_iter_mut(Axis(2))
-        .enumerate()
-        .for_each(|(k, mut data_k)| {
-            data_k
-                .axis_iter_mut(Axis(1))
-                .enumerate()
-                .for_each(|(j, mut data_jk)| {
-                    data_jk.iter_mut().enumerate().for_each(|(i, data_ijk)| {
-                        *data_ijk = data_flattened[i + nel.x() * (j + nel.y() * k)]
-                    })
-                })
-        });
+    let layer = nel.x() * nel.y();
+    let mut count = 0;
+    read_spn(file_path, &nel, |voxel| {
+        data[[count % nel.x(), count % layer / nel.x(), count / layer]] = voxel;
+        count += 1;
+    })?;
     Ok(data)
 }
 
+fn read_spn(file_path: &str, nel: &Nel, mut each: impl FnMut(u8)) -> Result<(), String> {
+    let total = nel.x() * nel.y() * nel.z();
+    let mut count = 0;
+    BufReader::new(File::open(file_path).map_err(|error| error.to_string())?)
+        .lines()
+        .enumerate()
+        .try_for_each(|(index, line)| {
+            let line =
+                line.map_err(|error| format!("{} on line {} of {}", error, index + 1, file_path))?;
+            let entry = line.trim();
+            if entry.is_empty() {
+                return Ok(());
+            }
+            if count == total {
+                return Err(format!(
+                    "Found more than nelx*nely*nelz = {}*{}*{} = {} voxels in {} on line {}",
+                    nel.x(),
+                    nel.y(),
+                    nel.z(),
+                    total,
+                    file_path,
+                    index + 1
+                ));
+            }
+            each(entry.parse().map_err(|_| {
+                format!(
+                    "Invalid voxel ID {} on line {} of {}",
+                    entry,
+                    index + 1,
+                    file_path
+                )
+            })?);
+            count += 1;
+            Ok(())
+        })?;
+    if count < total {
+        Err(format!(
+            "Found {} voxels in {}, need nelx*nely*nelz = {}*{}*{} = {}",
+            count,
+            file_path,
+            nel.x(),
+            nel.y(),
+            nel.z(),
+            total
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 fn write_voxels_to_npy(data: &VoxelData, file_path: &str) -> Result<(), WriteNpyError> {
     data.write_npy(BufWriter::new(File::create(file_path)?))
 }
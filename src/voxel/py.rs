@@ -41,11 +41,12 @@ impl Voxels {
     /// Defeatures clusters with less than a minimum number of voxels.
     pub fn defeature(&mut self, min_num_voxels: usize) {
         self.data = defeature_voxels(
-            min_num_voxels,
+            &min_num_voxels.into(),
             super::Voxels {
                 data: self.data.clone(),
             },
         )
+        .0
         .get_data()
         .clone()
     }
@@ -1,4 +1,14 @@
-use super::{Nel, Voxels, filter_voxel_data};
+use super::{
+    Chunk, Morphology, NODE_NUMBERING_OFFSET, Nel, Scale, StructuringElement, Translate, VoxelData,
+    Voxels,
+};
+use crate::{
+    FiniteElementMethods, TriangularFiniteElements,
+    tree::{
+        Adjacency, Defeaturing, DefeaturingMethod, IntoFiniteElements, Octree, Reassignment, Tree,
+    },
+};
+use conspire::math::Tensor;
 
 const NUM_ELEMENTS: usize = 39;
 
@@ -47,19 +57,20 @@ const VOXELS_GOLD: [[usize; 3]; NUM_ELEMENTS] = [
 
 #[test]
 fn filter() {
-    let spn = Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap();
-    let (filtered_voxel_data, element_blocks) = filter_voxel_data(spn.get_data(), Some(vec![0]));
-    assert_eq!(element_blocks.len(), NUM_ELEMENTS);
-    BLOCKS_GOLD
-        .iter()
-        .zip(element_blocks.iter())
-        .for_each(|(gold_n, block_n)| assert_eq!(gold_n, block_n));
-    assert_eq!(filtered_voxel_data.len(), NUM_ELEMENTS);
+    let fem = letter_f_3d()
+        .into_finite_elements(Some(vec![0]), Scale::default(), Translate::default())
+        .unwrap();
+    assert_eq!(fem.get_element_blocks(), &BLOCKS_GOLD);
+    assert_eq!(fem.get_element_node_connectivity().len(), NUM_ELEMENTS);
     VOXELS_GOLD
         .iter()
-        .flatten()
-        .zip(filtered_voxel_data.iter().flatten())
-        .for_each(|(entry, gold)| assert_eq!(entry, gold));
+        .zip(fem.get_element_node_connectivity().iter())
+        .for_each(|(gold, connectivity)| {
+            let coordinates = &fem.get_nodal_coordinates()[connectivity[0] - NODE_NUMBERING_OFFSET];
+            gold.iter()
+                .zip(coordinates.iter())
+                .for_each(|(gold, coordinate)| assert_eq!(*gold as f64, *coordinate))
+        });
 }
 
 mod nel {
@@ -111,3 +122,604 @@ mod nel {
         }
     }
 }
+
+fn letter_f_3d() -> Voxels {
+    Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap()
+}
+
+mod chunked {
+    use super::*;
+    use crate::ChunkedVoxels;
+    #[test]
+    fn from_npy() {
+        let voxels = ChunkedVoxels::from_npy("tests/input/letter_f_3d.npy").unwrap();
+        assert_eq!(Voxels::from(voxels).get_data(), letter_f_3d().get_data());
+    }
+    #[test]
+    fn from_spn() {
+        let voxels =
+            ChunkedVoxels::from_spn("tests/input/letter_f_3d.spn", [4, 5, 3].into()).unwrap();
+        assert_eq!(Voxels::from(voxels).get_data(), letter_f_3d().get_data());
+        assert_eq!(
+            ChunkedVoxels::from_spn("tests/input/letter_f_3d.spn", [4, 5, 2].into())
+                .err()
+                .unwrap(),
+            "Found more than nelx*nely*nelz = 4*5*2 = 40 voxels in tests/input/letter_f_3d.spn on line 41"
+        );
+        assert_eq!(
+            ChunkedVoxels::from_spn("tests/input/letter_f_3d.spn", [4, 5, 4].into())
+                .err()
+                .unwrap(),
+            "Found 60 voxels in tests/input/letter_f_3d.spn, need nelx*nely*nelz = 4*5*4 = 80"
+        );
+    }
+    #[test]
+    fn sparse() {
+        let mut data = VoxelData::zeros((40, 70, 33));
+        data.slice_mut(ndarray::s![35..40, 10..20, 0..5]).fill(2);
+        let voxels = ChunkedVoxels::from(Voxels { data: data.clone() });
+        assert_eq!(voxels.chunks.len(), 12);
+        assert_eq!(
+            voxels
+                .chunks
+                .iter()
+                .filter(|chunk| matches!(chunk, Chunk::Dense(_)))
+                .count(),
+            1
+        );
+        assert_eq!(Voxels::from(voxels).get_data(), &data);
+    }
+}
+
+mod edit {
+    use super::*;
+    #[test]
+    fn crop() {
+        let scale = Scale::from([2.0, 3.0, 4.0]);
+        let mut translate = Translate::default();
+        let voxels = letter_f_3d()
+            .crop([1, 2, 0], [4, 5, 2], &scale, &mut translate)
+            .unwrap();
+        assert_eq!(voxels.get_data().shape(), [3, 3, 2]);
+        assert_eq!(
+            voxels.get_data()[[0, 0, 1]],
+            letter_f_3d().get_data()[[1, 2, 1]]
+        );
+        assert_eq!(
+            [translate.x(), translate.y(), translate.z()],
+            [&2.0, &6.0, &0.0]
+        );
+    }
+    #[test]
+    fn crop_invalid() {
+        let result = letter_f_3d().crop(
+            [0, 0, 0],
+            [5, 5, 3],
+            &Scale::default(),
+            &mut Translate::default(),
+        );
+        assert!(matches!(result, Err(error) if error.starts_with("Invalid crop")));
+    }
+    #[test]
+    fn downsample() {
+        let mut data = VoxelData::zeros((3, 2, 2));
+        data[[0, 0, 0]] = 1;
+        data[[0, 1, 0]] = 1;
+        data[[1, 0, 0]] = 2;
+        data[[1, 1, 0]] = 2;
+        data[[0, 0, 1]] = 2;
+        data[[1, 0, 1]] = 2;
+        data[[2, 0, 0]] = 3;
+        data[[2, 1, 1]] = 3;
+        let mut scale = Scale::from([1.0, 2.0, 3.0]);
+        let voxels = Voxels { data }.downsample(2, &mut scale).unwrap();
+        assert_eq!(voxels.get_data().shape(), [2, 1, 1]);
+        assert_eq!(voxels.get_data()[[0, 0, 0]], 2);
+        assert_eq!(voxels.get_data()[[1, 0, 0]], 0);
+        assert_eq!([scale.x(), scale.y(), scale.z()], [&2.0, &4.0, &6.0]);
+    }
+    #[test]
+    fn flip() {
+        let voxels = letter_f_3d().flip(1).unwrap();
+        let original = letter_f_3d();
+        original
+            .get_data()
+            .indexed_iter()
+            .for_each(|((i, j, k), voxel)| assert_eq!(voxels.get_data()[[i, 4 - j, k]], *voxel));
+        assert!(letter_f_3d().flip(3).is_err());
+    }
+    #[test]
+    fn pad() {
+        let scale = Scale::from([2.0, 1.0, 1.0]);
+        let mut translate = Translate::default();
+        let voxels = letter_f_3d().pad([1, 0, 2], [0, 3, 1], 7, &scale, &mut translate);
+        assert_eq!(voxels.get_data().shape(), [5, 8, 6]);
+        assert_eq!(voxels.get_data()[[0, 0, 0]], 7);
+        assert_eq!(
+            voxels.get_data()[[1, 0, 2]],
+            letter_f_3d().get_data()[[0, 0, 0]]
+        );
+        assert_eq!(
+            [translate.x(), translate.y(), translate.z()],
+            [&-2.0, &0.0, &-2.0]
+        );
+    }
+    #[test]
+    fn permute() {
+        let mut scale = Scale::from([1.0, 2.0, 3.0]);
+        let mut translate = Translate::from([4.0, 5.0, 6.0]);
+        let voxels = letter_f_3d()
+            .permute([2, 0, 1], &mut scale, &mut translate)
+            .unwrap();
+        assert_eq!(voxels.get_data().shape(), [3, 4, 5]);
+        letter_f_3d()
+            .get_data()
+            .indexed_iter()
+            .for_each(|((i, j, k), voxel)| assert_eq!(voxels.get_data()[[k, i, j]], *voxel));
+        assert_eq!([scale.x(), scale.y(), scale.z()], [&3.0, &1.0, &2.0]);
+        assert_eq!(
+            [translate.x(), translate.y(), translate.z()],
+            [&6.0, &4.0, &5.0]
+        );
+        assert!(
+            letter_f_3d()
+                .permute([0, 0, 1], &mut scale, &mut translate)
+                .is_err()
+        );
+    }
+}
+
+mod morph {
+    use super::*;
+    fn count(voxels: &Voxels, id: u8) -> usize {
+        voxels
+            .get_data()
+            .iter()
+            .filter(|&&voxel| voxel == id)
+            .count()
+    }
+    fn single() -> Voxels {
+        let mut data = VoxelData::zeros((5, 5, 5));
+        data[[2, 2, 2]] = 1;
+        Voxels { data }
+    }
+    #[test]
+    fn background() {
+        assert!(
+            single()
+                .dilate(&[1, 0], StructuringElement::Cross(1), 0)
+                .is_err()
+        );
+    }
+    #[test]
+    fn close() {
+        let data = VoxelData::from_shape_vec((3, 1, 1), vec![1, 0, 1]).unwrap();
+        let voxels = Voxels { data }
+            .close(&[1], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(count(&voxels, 1), 3);
+    }
+    #[test]
+    fn dilate() {
+        [
+            (StructuringElement::Ball(1), 7),
+            (StructuringElement::Ball(2), 33),
+            (StructuringElement::Cross(1), 7),
+            (StructuringElement::Cross(2), 25),
+            (StructuringElement::Cube(1), 27),
+        ]
+        .into_iter()
+        .for_each(|(element, gold)| {
+            assert_eq!(count(&single().dilate(&[1], element, 0).unwrap(), 1), gold)
+        });
+    }
+    #[test]
+    fn erode() {
+        let mut data = VoxelData::zeros((5, 5, 5));
+        data.slice_mut(ndarray::s![1..4, 1..4, 1..4]).fill(1);
+        let voxels = Voxels { data }
+            .erode(&[1], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(count(&voxels, 1), 1);
+        assert_eq!(voxels.get_data()[[2, 2, 2]], 1);
+    }
+    #[test]
+    fn fill() {
+        let mut data = VoxelData::zeros((5, 5, 5));
+        data.slice_mut(ndarray::s![1..4, 1..4, 1..4]).fill(1);
+        data[[2, 2, 2]] = 0;
+        data[[0, 0, 0]] = 2;
+        let voxels = Voxels { data: data.clone() }
+            .fill_holes(&[1], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(count(&voxels, 1), 27);
+        assert_eq!(voxels.get_data()[[0, 0, 0]], 2);
+        data[[2, 2, 2]] = 3;
+        let voxels = Voxels { data }
+            .morph(Morphology::Fill, &[1], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(count(&voxels, 1), 26);
+        assert_eq!(voxels.get_data()[[2, 2, 2]], 3);
+    }
+    #[test]
+    fn open() {
+        let mut data = VoxelData::zeros((7, 7, 7));
+        data.slice_mut(ndarray::s![1..4, 1..4, 1..4]).fill(1);
+        data[[4, 2, 2]] = 1;
+        let voxels = Voxels { data }
+            .open(&[1], StructuringElement::Cube(1), 0)
+            .unwrap();
+        assert_eq!(count(&voxels, 1), 27);
+        assert_eq!(voxels.get_data()[[4, 2, 2]], 0);
+    }
+    #[test]
+    fn priority() {
+        let data = VoxelData::from_shape_vec((3, 1, 1), vec![1, 0, 2]).unwrap();
+        let voxels = Voxels { data: data.clone() }
+            .dilate(&[2, 1], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(voxels.get_data().iter().collect::<Vec<_>>(), [&1, &2, &2]);
+        let voxels = Voxels { data }
+            .dilate(&[1, 2], StructuringElement::Cross(1), 0)
+            .unwrap();
+        assert_eq!(voxels.get_data().iter().collect::<Vec<_>>(), [&1, &1, &2]);
+    }
+    #[test]
+    fn unlisted() {
+        let data = VoxelData::from_shape_vec((4, 1, 1), vec![1, 3, 0, 0]).unwrap();
+        let voxels = Voxels { data }
+            .dilate(&[1], StructuringElement::Cross(2), 0)
+            .unwrap();
+        assert_eq!(
+            voxels.get_data().iter().collect::<Vec<_>>(),
+            [&1, &3, &1, &0]
+        );
+    }
+}
+
+mod relabel {
+    use super::*;
+    use std::collections::HashMap;
+    #[test]
+    fn merge() {
+        let data = VoxelData::from_shape_vec((4, 1, 1), vec![0, 3, 4, 5]).unwrap();
+        let voxels = Voxels { data }.relabel(&HashMap::from([(4, 3), (5, 9)]));
+        assert_eq!(
+            voxels.get_data().iter().collect::<Vec<_>>(),
+            [&0, &3, &3, &9]
+        );
+    }
+    #[test]
+    fn renumber() {
+        let data = VoxelData::from_shape_vec((5, 1, 1), vec![7, 0, 3, 7, 250]).unwrap();
+        let voxels = Voxels { data }.renumber();
+        assert_eq!(
+            voxels.get_data().iter().collect::<Vec<_>>(),
+            [&2, &0, &1, &2, &3]
+        );
+    }
+    #[test]
+    fn swap() {
+        let voxels = letter_f_3d().relabel(&HashMap::from([(0, 1), (1, 0)]));
+        letter_f_3d()
+            .get_data()
+            .iter()
+            .zip(voxels.get_data().iter())
+            .for_each(|(old, new)| assert_eq!(*old, 1 - new));
+    }
+}
+
+mod components {
+    use super::*;
+    fn islands() -> Voxels {
+        let mut data = VoxelData::zeros((4, 3, 2));
+        data[[0, 0, 0]] = 2;
+        data[[0, 1, 0]] = 2;
+        data[[3, 2, 1]] = 2;
+        data[[1, 1, 1]] = 2;
+        data[[2, 0, 0]] = 5;
+        Voxels { data }
+    }
+    #[test]
+    fn letter_f_3d_gold() {
+        let components = letter_f_3d().components();
+        assert_eq!(components.get_components().len(), 2);
+        let component = components
+            .get_components()
+            .iter()
+            .find(|component| component.get_id() == 1)
+            .unwrap();
+        assert_eq!(component.get_voxels(), NUM_ELEMENTS);
+        assert_eq!(component.get_minimum(), &[0, 0, 0]);
+        assert_eq!(component.get_maximum(), &[4, 5, 3]);
+        let centroid = VOXELS_GOLD.iter().fold([0.0; 3], |sum, voxel| {
+            [0, 1, 2].map(|n| sum[n] + (voxel[n] as f64 + 0.5) / NUM_ELEMENTS as f64)
+        });
+        component
+            .get_centroid()
+            .iter()
+            .zip(centroid.iter())
+            .for_each(|(entry, gold)| assert!((entry - gold).abs() < 1e-12));
+    }
+    #[test]
+    fn islands_gold() {
+        let components = islands().components();
+        let mut counts = components
+            .get_components()
+            .iter()
+            .map(|component| (component.get_id(), component.get_voxels()))
+            .collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, [(0, 19), (2, 1), (2, 1), (2, 2), (5, 1)]);
+        components
+            .get_labels()
+            .iter()
+            .zip(islands().get_data().iter())
+            .for_each(|(&label, &id)| assert_eq!(components.get_components()[label].get_id(), id));
+    }
+    #[test]
+    fn split() {
+        let voxels = islands().split_components(&[]).unwrap();
+        assert_eq!(voxels.get_data()[[0, 0, 0]], 2);
+        assert_eq!(voxels.get_data()[[0, 1, 0]], 2);
+        assert_eq!(voxels.get_data()[[2, 0, 0]], 5);
+        let mut ids = [voxels.get_data()[[1, 1, 1]], voxels.get_data()[[3, 2, 1]]];
+        ids.sort();
+        assert_eq!(ids, [6, 7]);
+        let voxels = islands().split_components(&[2]).unwrap();
+        assert_eq!(voxels.get_data(), islands().get_data());
+    }
+    #[test]
+    fn split_cavity() {
+        let mut data = VoxelData::from_elem((7, 6, 5), 0);
+        data.slice_mut(ndarray::s![1..6, 1..5, 1..4]).fill(1);
+        data[[3, 2, 2]] = 0;
+        let voxels = Voxels { data };
+        let components = voxels.components();
+        let mut counts = components
+            .get_components()
+            .iter()
+            .map(|component| (component.get_id(), component.get_voxels()))
+            .collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, [(0, 1), (0, 150), (1, 59)]);
+        let split = Voxels {
+            data: voxels.get_data().clone(),
+        }
+        .split_components(&[])
+        .unwrap();
+        assert_eq!(split.get_data()[[3, 2, 2]], 2);
+        let split = voxels.split_components(&[0]).unwrap();
+        assert_eq!(split.get_data()[[3, 2, 2]], 0);
+        assert_eq!(split.get_data()[[0, 0, 0]], 0);
+    }
+}
+
+mod defeature {
+    use super::*;
+    fn inclusions(corner: bool) -> Voxels {
+        let mut data = VoxelData::from_elem((8, 8, 8), 1);
+        data.slice_mut(ndarray::s![2..4, 2..4, 2..4]).fill(2);
+        if corner {
+            data.slice_mut(ndarray::s![4..6, 4..6, 4..6]).fill(2);
+        } else {
+            data.slice_mut(ndarray::s![4..6, 4..6, 2..4]).fill(2);
+        }
+        Voxels { data }
+    }
+    fn count(voxels: &Voxels, id: u8) -> usize {
+        voxels
+            .get_data()
+            .iter()
+            .filter(|&&voxel| voxel == id)
+            .count()
+    }
+    #[test]
+    fn adjacency() {
+        [
+            (false, Adjacency::Face, 0),
+            (false, Adjacency::Edge, 16),
+            (false, Adjacency::Vertex, 16),
+            (true, Adjacency::Edge, 0),
+            (true, Adjacency::Vertex, 16),
+        ]
+        .into_iter()
+        .for_each(|(corner, adjacency, gold)| {
+            [DefeaturingMethod::Octree, DefeaturingMethod::UnionFind]
+                .into_iter()
+                .for_each(|method| {
+                    let (voxels, _) = inclusions(corner).defeature_with(&Defeaturing {
+                        adjacency,
+                        method,
+                        minimum: 9,
+                        ..Default::default()
+                    });
+                    assert_eq!(count(&voxels, 2), gold)
+                })
+        });
+        assert!(Adjacency::try_from(12).is_err());
+    }
+    #[test]
+    fn forest() {
+        let (nel, tree) = Octree::from_voxels(Voxels {
+            data: VoxelData::from_elem((16, 16, 9), 1),
+        });
+        assert_eq!([*nel.x(), *nel.y(), *nel.z()], [16, 16, 16]);
+        assert_eq!(
+            tree.iter()
+                .take_while(|cell| cell.get_lngth() == &8)
+                .count(),
+            8
+        );
+        let mut data = VoxelData::from_elem((16, 8, 8), 1);
+        data.slice_mut(ndarray::s![7..9, 3..5, 3..5]).fill(2);
+        data.slice_mut(ndarray::s![10..14, 2..6, 2..6]).fill(3);
+        let (voxels, reassignments) = Voxels { data }.defeature_with(&9.into());
+        let mut gold = VoxelData::from_elem((16, 8, 8), 1);
+        gold.slice_mut(ndarray::s![10..14, 2..6, 2..6]).fill(3);
+        assert_eq!(voxels.get_data(), &gold);
+        assert_eq!(reassignments.len(), 1);
+    }
+    fn elements<const N: usize, F: FiniteElementMethods<N>>(fem: &F) -> Vec<(u8, Vec<[u64; 3]>)> {
+        let coordinates = fem.get_nodal_coordinates();
+        let mut elements = fem
+            .get_element_blocks()
+            .iter()
+            .zip(fem.get_element_node_connectivity().iter())
+            .map(|(&block, connectivity)| {
+                let mut nodes = connectivity
+                    .iter()
+                    .map(|&node| {
+                        let coordinate = &coordinates[node - NODE_NUMBERING_OFFSET];
+                        [0, 1, 2].map(|n| coordinate[n].to_bits())
+                    })
+                    .collect::<Vec<_>>();
+                nodes.sort();
+                (block, nodes)
+            })
+            .collect::<Vec<_>>();
+        elements.sort();
+        elements
+    }
+    #[test]
+    fn forest_meshes() {
+        let voxels = || Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap();
+        let mut data = VoxelData::from_elem((8, 8, 8), 0);
+        data.slice_mut(ndarray::s![..4, ..5, ..3])
+            .assign(voxels().get_data());
+        let baseline = Voxels { data };
+        let (nel, mut forest) = Octree::from_voxels(voxels());
+        let (nel_baseline, mut tree) = Octree::from_voxels(baseline);
+        forest.balance(true);
+        tree.balance(true);
+        let [tri, tri_baseline] = [(nel, forest.clone()), (nel_baseline, tree.clone())].map(
+            |(nel, tree)| -> TriangularFiniteElements {
+                tree.into_finite_elements(
+                    nel,
+                    Some(vec![0]),
+                    Scale::default(),
+                    Translate::default(),
+                )
+                .unwrap()
+            },
+        );
+        forest.prune();
+        tree.prune();
+        assert_eq!([*nel.x(), *nel.y(), *nel.z()], [4, 6, 4]);
+        assert_eq!([forest.len(), tree.len()], [82, 127]);
+        let [hex, hex_baseline] = [forest, tree].map(|tree| {
+            tree.octree_into_finite_elements(Some(vec![0]), Scale::default(), Translate::default())
+                .unwrap()
+        });
+        assert_eq!(elements(&hex), elements(&hex_baseline));
+        assert_eq!(elements(&tri), elements(&tri_baseline));
+    }
+    #[test]
+    fn minimums() {
+        let (voxels, reassignments) = inclusions(false).defeature_with(&Defeaturing {
+            minimum: 9,
+            minimums: vec![(2, 8)],
+            ..Default::default()
+        });
+        assert_eq!(count(&voxels, 2), 16);
+        assert!(reassignments.is_empty());
+    }
+    #[test]
+    fn protected() {
+        let (voxels, reassignments) = inclusions(false).defeature_with(&Defeaturing {
+            minimum: 9,
+            protected: vec![2],
+            ..Default::default()
+        });
+        assert_eq!(count(&voxels, 2), 16);
+        assert!(reassignments.is_empty());
+    }
+    #[test]
+    fn report() {
+        let (_, mut reassignments) = inclusions(false).defeature_with(&9.into());
+        reassignments.sort_by_key(|reassignment| reassignment.minimum);
+        assert_eq!(
+            reassignments,
+            [
+                Reassignment {
+                    block: 2,
+                    minimum: [2, 2, 2],
+                    new_block: 1,
+                    voxels: 8,
+                },
+                Reassignment {
+                    block: 2,
+                    minimum: [4, 4, 2],
+                    new_block: 1,
+                    voxels: 8,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn union_find() {
+        let mut data = inclusions(false)
+            .get_data()
+            .slice(ndarray::s![.., ..7, ..6])
+            .to_owned();
+        data[[0, 0, 0]] = 3;
+        data[[6, 6, 5]] = 2;
+        let defeaturing = Defeaturing {
+            method: DefeaturingMethod::UnionFind,
+            minimum: 9,
+            ..Default::default()
+        };
+        let (voxels, mut reassignments) = Voxels { data }.defeature_with(&defeaturing);
+        reassignments.sort_by_key(|reassignment| reassignment.minimum);
+        assert_eq!(voxels.get_data(), &VoxelData::from_elem((8, 7, 6), 1));
+        assert_eq!(
+            reassignments
+                .iter()
+                .map(|reassignment| (
+                    reassignment.block,
+                    reassignment.minimum,
+                    reassignment.voxels
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (3, [0, 0, 0], 1),
+                (2, [2, 2, 2], 8),
+                (2, [4, 4, 2], 8),
+                (2, [6, 6, 5], 1)
+            ]
+        );
+    }
+    #[test]
+    fn union_find_matches_octree() {
+        [
+            (
+                Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap(),
+                2..39,
+            ),
+            (
+                Voxels::from_spn("tests/input/cube_with_inclusion.spn", [3; 3].into()).unwrap(),
+                2..60,
+            ),
+        ]
+        .into_iter()
+        .for_each(|(voxels, minimums)| {
+            minimums.for_each(|minimum| {
+                let [(octree, mut gold), (union_find, mut reassignments)] =
+                    [DefeaturingMethod::Octree, DefeaturingMethod::UnionFind].map(|method| {
+                        Voxels {
+                            data: voxels.get_data().clone(),
+                        }
+                        .defeature_with(&Defeaturing {
+                            method,
+                            minimum,
+                            ..Default::default()
+                        })
+                    });
+                gold.sort_by_key(|reassignment| reassignment.minimum);
+                reassignments.sort_by_key(|reassignment| reassignment.minimum);
+                assert_eq!(union_find.get_data(), octree.get_data());
+                assert_eq!(reassignments, gold);
+            })
+        })
+    }
+}
@@ -6,6 +6,11 @@ translate = [0, 0, 0]
 voxels = Voxels.from_npy('tests/input/letter_f_3d.npy')
 
 
+def test_smooth_hc():
+    fem = voxels.as_finite_elements(remove, scale, translate)
+    fem.smooth(method='HC', alpha=0.1, beta=0.6)
+
+
 def test_smooth_laplace():
     fem = voxels.as_finite_elements(remove, scale, translate)
     fem.smooth(method='Laplace')
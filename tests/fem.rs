@@ -1,4 +1,4 @@
-use automesh::{FiniteElementMethods, NSD, Scale, Translate, Voxels};
+use automesh::{ChunkedVoxels, FiniteElementMethods, NSD, Scale, Translate, Voxels};
 use conspire::math::Tensor;
 use std::{
     fs::File,
@@ -43,6 +43,23 @@ fn compare_files(
         .into_finite_elements(Some(vec![0]), scale, translate)
         .unwrap();
     fem.write_inp(file_path).unwrap();
+    compare_inp(file_path, gold_path)
+}
+
+fn compare_chunked_files(file_path: &str, gold_path: &str, spn_path: &str, nel: [usize; NSD]) {
+    let voxels = ChunkedVoxels::from_spn(spn_path, nel.into()).unwrap();
+    voxels
+        .write_inp(
+            file_path,
+            Some(vec![0]),
+            [1.0, 1.0, 1.0].into(),
+            [0.0, 0.0, 0.0].into(),
+        )
+        .unwrap();
+    compare_inp(file_path, gold_path)
+}
+
+fn compare_inp(file_path: &str, gold_path: &str) {
     let mut gold = String::new();
     let mut line = String::new();
     let mut gold_reader = BufReader::new(File::open(gold_path).unwrap());
@@ -92,6 +109,68 @@ mod read_inp {
             .zip(read.get_nodal_coordinates().iter())
             .for_each(|(a, b)| a.iter().zip(b.iter()).for_each(|(c, d)| assert_eq!(c, d)));
     }
+    #[test]
+    fn detect_element_type() {
+        use automesh::{FiniteElementsKind, Tessellation};
+        match FiniteElementsKind::from_inp("tests/input/single.inp").unwrap() {
+            FiniteElementsKind::Hexahedral(fem) => {
+                assert_eq!(fem.get_element_node_connectivity().len(), 1)
+            }
+            _ => panic!("Expected hexahedral elements."),
+        }
+        let fem = Tessellation::from_stl("tests/input/single_valence_04_noise2.stl")
+            .unwrap()
+            .into_finite_elements();
+        let directory = std::env::temp_dir();
+        let file_path = directory.join("automesh_detect_element_type.inp");
+        let file_path = file_path.to_str().unwrap();
+        fem.write_inp(file_path).unwrap();
+        match FiniteElementsKind::from_inp(file_path).unwrap() {
+            FiniteElementsKind::Triangular(read) => {
+                assert_eq!(fem.get_element_blocks(), read.get_element_blocks());
+                assert_eq!(
+                    fem.get_element_node_connectivity(),
+                    read.get_element_node_connectivity()
+                );
+            }
+            _ => panic!("Expected triangular elements."),
+        }
+        assert!(HexahedralFiniteElements::from_inp(file_path).is_err());
+        let single = std::fs::read_to_string("tests/input/single.inp").unwrap();
+        ["TYPE=C3D8RH, ", "TYPE=C3D8I, "]
+            .iter()
+            .for_each(|element_type| {
+                let file_path = directory.join("automesh_detect_element_type_hex.inp");
+                let file_path = file_path.to_str().unwrap();
+                std::fs::write(file_path, single.replace("TYPE=C3D8R, ", element_type)).unwrap();
+                match FiniteElementsKind::from_inp(file_path).unwrap() {
+                    FiniteElementsKind::Hexahedral(fem) => {
+                        assert_eq!(fem.get_element_node_connectivity().len(), 1)
+                    }
+                    _ => panic!("Expected hexahedral elements."),
+                }
+            });
+        [("TYPE=FOO, ", "Unrecognized FOO"), ("", "Missing")]
+            .iter()
+            .for_each(|(element_type, prefix)| {
+                let file_path = directory.join("automesh_detect_element_type_assumed.inp");
+                let file_path = file_path.to_str().unwrap();
+                std::fs::write(file_path, single.replace("TYPE=C3D8R, ", element_type)).unwrap();
+                match FiniteElementsKind::from_inp(file_path).unwrap() {
+                    FiniteElementsKind::AssumedHexahedral(fem, warning) => {
+                        assert_eq!(fem.get_element_node_connectivity().len(), 1);
+                        assert_eq!(
+                            warning,
+                            format!(
+                                "{} element type in input file {}, reading as hexahedral elements",
+                                prefix, file_path
+                            )
+                        )
+                    }
+                    _ => panic!("Expected assumed hexahedral elements."),
+                }
+            });
+    }
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -119,6 +198,24 @@ mod write_inp {
             [0.0, 0.0, 0.0].into(),
         );
     }
+    #[test]
+    fn letter_f_3d_chunked() {
+        compare_chunked_files(
+            "target/letter_f_3d_chunked.inp",
+            "tests/input/letter_f_3d.inp",
+            "tests/input/letter_f_3d.spn",
+            [4, 5, 3],
+        );
+    }
+    #[test]
+    fn sparse_chunked() {
+        compare_chunked_files(
+            "target/sparse_chunked.inp",
+            "tests/input/sparse.inp",
+            "tests/input/sparse.spn",
+            [5, 5, 5],
+        );
+    }
 }
 
 mod write_mesh {
@@ -152,3 +249,111 @@ mod write_vtk {
         fem.write_vtk("target/letter_f_3d.vtk").unwrap();
     }
 }
+
+mod smooth {
+    use super::*;
+    use automesh::{Metric, Smoothing, SmoothingOptions};
+    fn smoothed_minimum_scaled_jacobian(
+        voxels: Voxels,
+        safeguard: bool,
+        volume_preserving: bool,
+    ) -> (f64, usize) {
+        let mut fem = voxels
+            .into_finite_elements(
+                Some(vec![0]),
+                [1.0, 1.0, 1.0].into(),
+                [0.0, 0.0, 0.0].into(),
+            )
+            .unwrap();
+        fem.node_element_connectivity().unwrap();
+        fem.node_node_connectivity().unwrap();
+        fem.nodal_influencers();
+        fem.smooth_with(
+            Smoothing::Laplacian(40, 0.9),
+            &SmoothingOptions {
+                safeguard,
+                volume_preserving,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        (
+            fem.calculate_metric(&Metric::ScaledJacobian)
+                .unwrap()
+                .into_iter()
+                .reduce(f64::min)
+                .unwrap(),
+            fem.get_rejected_moves(),
+        )
+    }
+    #[test]
+    fn safeguard() {
+        let letter_f_3d = || Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap();
+        let (minimum_scaled_jacobian, rejected_moves) =
+            smoothed_minimum_scaled_jacobian(letter_f_3d(), false, false);
+        assert!(minimum_scaled_jacobian <= 0.0);
+        assert_eq!(rejected_moves, 0);
+        let (minimum_scaled_jacobian, rejected_moves) =
+            smoothed_minimum_scaled_jacobian(letter_f_3d(), true, false);
+        assert!(minimum_scaled_jacobian > 0.0);
+        assert!(rejected_moves > 0);
+    }
+    #[test]
+    fn volume_preserving() {
+        //
+        // Unlike a regular grid, the sparse mesh has elements that smoothing can invert,
+        // and the safeguard then trades some of the preserved volume for valid elements.
+        //
+        let smoothed_volumes = |safeguard: bool| {
+            let mut fem = Voxels::from_spn("tests/input/sparse.spn", [5, 5, 5].into())
+                .unwrap()
+                .into_finite_elements(
+                    Some(vec![0]),
+                    [1.0, 1.0, 1.0].into(),
+                    [0.0, 0.0, 0.0].into(),
+                )
+                .unwrap();
+            let volumes = fem.calculate_block_volumes().unwrap();
+            fem.node_element_connectivity().unwrap();
+            fem.node_node_connectivity().unwrap();
+            fem.nodal_influencers();
+            fem.smooth_with(
+                Smoothing::Laplacian(40, 0.9),
+                &SmoothingOptions {
+                    safeguard,
+                    volume_preserving: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            (volumes, fem.calculate_block_volumes().unwrap())
+        };
+        let (volumes, smoothed) = smoothed_volumes(false);
+        assert_eq!(volumes.len(), 2);
+        volumes
+            .iter()
+            .zip(smoothed.iter())
+            .for_each(|((_, volume), (_, smoothed))| assert!((volume - smoothed).abs() < 1e-10));
+        let (volumes, smoothed) = smoothed_volumes(true);
+        volumes
+            .iter()
+            .zip(smoothed.iter())
+            .for_each(|((_, volume), (_, smoothed))| {
+                assert!((volume - smoothed).abs() < 0.05 * volume)
+            });
+    }
+    #[test]
+    fn safeguard_volume_preserving() {
+        //
+        // The volume correction inverts elements of the sparse mesh,
+        // so the safeguard has to check the moves after the correction.
+        //
+        let sparse = || Voxels::from_spn("tests/input/sparse.spn", [5, 5, 5].into()).unwrap();
+        let (minimum_scaled_jacobian, _) = smoothed_minimum_scaled_jacobian(sparse(), false, true);
+        assert!(minimum_scaled_jacobian <= 0.0);
+        let (minimum_scaled_jacobian, rejected_moves) =
+            smoothed_minimum_scaled_jacobian(sparse(), true, true);
+        assert!(minimum_scaled_jacobian > 0.0);
+        assert!(rejected_moves > 0);
+    }
+}
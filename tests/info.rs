@@ -0,0 +1,41 @@
+use std::process::Command;
+
+fn info_json(arguments: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_automesh"))
+        .arg("info")
+        .args(arguments)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn mesh() {
+    assert_eq!(
+        info_json(&["--input", "tests/input/single.inp"]),
+        "{\"file\": \"tests/input/single.inp\", \"type\": \"hexahedral\", \"nodes\": 8, \"elements\": 1, \"bounding_box\": {\"minimum\": [0.0, 0.0, 0.0], \"maximum\": [1.0, 1.0, 1.0]}, \"volume\": 1.0, \"blocks\": [{\"id\": 1, \"elements\": 1, \"volume\": 1.0}]}\n"
+    );
+}
+
+#[test]
+fn segmentation() {
+    assert_eq!(
+        info_json(&[
+            "--input",
+            "tests/input/letter_f_3d.spn",
+            "--nelx",
+            "4",
+            "--nely",
+            "5",
+            "--nelz",
+            "3",
+            "--xscale",
+            "inf",
+            "--ytranslate",
+            "2",
+        ]),
+        "{\"file\": \"tests/input/letter_f_3d.spn\", \"type\": \"segmentation\", \"dimensions\": [4, 5, 3], \"voxels\": 60, \"scale\": [null, 1.0, 1.0], \"translate\": [0.0, 2.0, 0.0], \"extent\": {\"minimum\": [0.0, 2.0, 0.0], \"maximum\": [null, 7.0, 3.0]}, \"histogram\": [{\"id\": 0, \"voxels\": 21}, {\"id\": 1, \"voxels\": 39}]}\n"
+    );
+}
@@ -1371,11 +1371,14 @@ mod defeature {
                 .take(nel)
                 .for_each(|b| b.iter().take(nel).for_each(|&c| assert_eq!(c, 11)))
         });
-        voxels.get_data().outer_iter().skip(nel).for_each(|a| {
-            a.outer_iter()
-                .skip(nel)
-                .for_each(|b| b.iter().skip(nel).for_each(|&c| assert_eq!(c, 0)))
-        })
+        assert_eq!(voxels.get_data().shape(), [nel; NSD])
+    }
+    #[test]
+    fn letter_f_3d() {
+        let voxels = Voxels::from_npy("tests/input/letter_f_3d.npy").unwrap();
+        let shape = voxels.get_data().shape().to_vec();
+        assert_eq!(shape, [4, 5, 3]);
+        assert_eq!(voxels.defeature(2).get_data().shape(), shape)
     }
 }
 
@@ -1445,13 +1448,29 @@ mod from_spn {
             .unwrap();
     }
     #[test]
-    #[should_panic(expected = "ParseIntError { kind: InvalidDigit }")]
+    #[should_panic(
+        expected = "Invalid voxel ID This is the test file `f.txt`. on line 1 of tests/input/letter_f_3d.txt"
+    )]
     fn file_unreadable() {
         Voxels::from_spn("tests/input/letter_f_3d.txt", [4, 5, 3].into())
             .map_err(|e| e.to_string())
             .unwrap();
     }
     #[test]
+    #[should_panic(
+        expected = "Found more than nelx*nely*nelz = 4*5*2 = 40 voxels in tests/input/letter_f_3d.spn on line 41"
+    )]
+    fn too_many_voxels() {
+        Voxels::from_spn("tests/input/letter_f_3d.spn", [4, 5, 2].into()).unwrap();
+    }
+    #[test]
+    #[should_panic(
+        expected = "Found 60 voxels in tests/input/letter_f_3d.spn, need nelx*nely*nelz = 4*5*4 = 80"
+    )]
+    fn too_few_voxels() {
+        Voxels::from_spn("tests/input/letter_f_3d.spn", [4, 5, 4].into()).unwrap();
+    }
+    #[test]
     #[should_panic(expected = "Need to specify nel > 0.")]
     fn nelx_positive() {
         Voxels::from_spn("tests/input/single.spn", [0, 1, 1].into()).unwrap();